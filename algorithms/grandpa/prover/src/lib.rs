@@ -157,11 +157,19 @@ where
 			.block_hash(Some(latest_finalized_height.into()))
 			.await?
 			.ok_or_else(|| anyhow!("Block hash not found for number: {latest_finalized_height}"))?;
+		self.query_parachain_head_at(latest_finalized_hash).await
+	}
+
+	/// Returns the parachain head stored in the relay chain state at the given relay chain block.
+	pub async fn query_parachain_head_at(
+		&self,
+		relay_hash: T::Hash,
+	) -> Result<T::Header, anyhow::Error> {
 		let key = polkadot::api::storage().paras().heads(&Id(self.para_id));
 		let header = self
 			.relay_client
 			.storage()
-			.fetch(&key, Some(latest_finalized_hash))
+			.fetch(&key, Some(relay_hash))
 			.await?
 			.ok_or_else(|| anyhow!("parachain header not found for para id: {}", self.para_id))?;
 		let header = T::Header::decode(&mut &header.0[..])
@@ -280,6 +288,10 @@ where
 		let mut parachain_headers_with_proof =
 			BTreeMap::<H::Hash, ParachainHeaderProofs>::default();
 		let mut para_headers = vec![];
+		// With asynchronous backing several candidates can be included in the same relay chain
+		// block, so some parachain blocks are never the parachain head at any relay chain block.
+		// Such headers are proven through the first parachain head that succeeds them.
+		let mut previous_para_head = T::BlockNumber::zero();
 
 		for changes in change_set {
 			let header = self
@@ -301,8 +313,12 @@ where
 
 			let para_header: T::Header = Decode::decode(&mut &parachain_header_bytes[..])?;
 			let para_block_number = *para_header.number();
+			let requested = header_numbers
+				.iter()
+				.any(|number| *number > previous_para_head && *number <= para_block_number);
+			previous_para_head = para_block_number;
 			// skip genesis header or any unknown headers
-			if para_block_number == Zero::zero() || !header_numbers.contains(&para_block_number) {
+			if para_block_number == Zero::zero() || !requested {
 				continue
 			}
 			para_headers.push((header.hash(), para_header.clone()));
@@ -258,6 +258,16 @@ pub struct PacketState {
 	pub data: Vec<u8>,
 }
 
+/// Sequences of the packets on a channel that are still awaiting delivery
+#[derive(Clone, codec::Encode, codec::Decode, PartialEq, Eq, Ord, PartialOrd)]
+pub struct QueryUndeliveredSequencesResponse {
+	/// Sent packets that have not been acknowledged or timed out yet
+	pub send: Vec<u64>,
+	/// Received packets whose acknowledgements are still stored
+	pub recv: Vec<u64>,
+	pub height: u64,
+}
+
 #[derive(Clone, codec::Encode, codec::Decode, PartialEq, Eq, Ord, PartialOrd)]
 pub struct QueryPacketCommitmentsResponse {
 	pub commitments: Vec<PacketState>,
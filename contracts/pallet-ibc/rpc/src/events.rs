@@ -47,8 +47,7 @@ where
 				)
 				.ok()
 				.flatten()?;
-			let packet = packets.get(0)?.clone();
-			let packet: Packet = packet.into();
+			let packet: Packet = packets.into_iter().next()?.into();
 			ev.packet = packet;
 			Some(event)
 		},
@@ -65,8 +64,7 @@ where
 				)
 				.ok()
 				.flatten()?;
-			let packet = packets.get(0)?.clone();
-			let packet: Packet = packet.into();
+			let packet: Packet = packets.into_iter().next()?.into();
 			ev.packet = packet;
 			Some(event)
 		},
@@ -83,8 +81,8 @@ where
 				)
 				.ok()
 				.flatten()?;
-			let packet_info = packets.get(0)?.clone();
-			ev.ack = packet_info.ack.clone()?;
+			let mut packet_info = packets.into_iter().next()?;
+			ev.ack = packet_info.ack.take()?;
 			ev.packet = packet_info.into();
 			Some(event)
 		},
@@ -101,8 +99,7 @@ where
 				)
 				.ok()
 				.flatten()?;
-			let packet = packets.get(0)?.clone();
-			let packet: Packet = packet.into();
+			let packet: Packet = packets.into_iter().next()?.into();
 			ev.packet = packet;
 			Some(event)
 		},
@@ -119,8 +116,7 @@ where
 				)
 				.ok()
 				.flatten()?;
-			let packet = packets.get(0)?.clone();
-			let packet: Packet = packet.into();
+			let packet: Packet = packets.into_iter().next()?.into();
 			ev.packet = packet;
 			Some(event)
 		},
@@ -137,8 +133,7 @@ where
 				)
 				.ok()
 				.flatten()?;
-			let packet = packets.get(0)?.clone();
-			let packet: Packet = packet.into();
+			let packet: Packet = packets.into_iter().next()?.into();
 			ev.packet = packet;
 			Some(event)
 		},
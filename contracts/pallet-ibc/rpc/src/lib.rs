@@ -61,7 +61,7 @@ use pallet_ibc::events::IbcEvent;
 use sc_chain_spec::Properties;
 use sc_client_api::{BlockBackend, ProofProvider};
 use serde::{Deserialize, Serialize};
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
 use sp_core::{blake2_256, storage::ChildInfo};
 use sp_runtime::{
@@ -120,6 +120,17 @@ pub struct Proof {
 	pub height: ibc_proto::ibc::core::client::v1::Height,
 }
 
+/// Sequences of the packets on a channel that are still awaiting delivery
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UndeliveredSequences {
+	/// Sent packets that have not been acknowledged or timed out yet
+	pub send: Vec<u64>,
+	/// Received packets whose acknowledgements are still stored
+	pub recv: Vec<u64>,
+	/// Height at which the sequences were queried
+	pub height: Height,
+}
+
 /// Packet info
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct PacketInfo {
@@ -306,6 +317,16 @@ where
 		port_id: String,
 	) -> Result<QueryPacketAcknowledgementsResponse>;
 
+	/// Query the sequences of packets sent on a channel that are not yet acknowledged or timed
+	/// out, and of packets received on it whose acknowledgements are stored, in a single call
+	#[method(name = "ibc_queryUndeliveredSequences")]
+	fn query_undelivered_sequences(
+		&self,
+		height: u32,
+		channel_id: String,
+		port_id: String,
+	) -> Result<UndeliveredSequences>;
+
 	/// Given a list of counterparty packet commitments, the querier checks if the packet
 	/// has already been received by checking if a receipt exists on this
 	/// chain for the packet sequence. All packets that haven't been received yet
@@ -422,6 +443,13 @@ where
 	) -> Result<HashMap<String, Vec<RawIbcEvent>>>;
 }
 
+/// Error code returned when the runtime doesn't hold the queried consensus state.
+pub const CONSENSUS_STATE_NOT_FOUND_CODE: i32 = 9877;
+
+/// Error code returned when the state at the queried height is not available, because the node
+/// has pruned it.
+pub const STATE_UNAVAILABLE_CODE: i32 = 9878;
+
 /// Converts a runtime trap into an RPC error.
 fn runtime_error_into_rpc_error(e: impl std::fmt::Display) -> RpcError {
 	RpcError::Call(CallError::Custom(ErrorObject::owned(
@@ -431,6 +459,70 @@ fn runtime_error_into_rpc_error(e: impl std::fmt::Display) -> RpcError {
 	)))
 }
 
+/// Converts a missing consensus state into an RPC error.
+fn consensus_state_not_found(e: impl std::fmt::Display) -> RpcError {
+	RpcError::Call(CallError::Custom(ErrorObject::owned(
+		CONSENSUS_STATE_NOT_FOUND_CODE,
+		"Consensus state not found",
+		Some(format!("{}", e)),
+	)))
+}
+
+/// Converts a failed state access at the queried height into an RPC error.
+fn state_unavailable(e: impl std::fmt::Display) -> RpcError {
+	RpcError::Call(CallError::Custom(ErrorObject::owned(
+		STATE_UNAVAILABLE_CODE,
+		"State not available",
+		Some(format!("{}", e)),
+	)))
+}
+
+/// Returns the version of the ibc runtime api implemented at `at`.
+fn runtime_api_version<Api, Block, AssetId>(api: &Api, at: &BlockId<Block>) -> Result<u32>
+where
+	Block: BlockT,
+	Api: IbcRuntimeApi<Block, AssetId> + ApiExt<Block>,
+	AssetId: codec::Codec,
+{
+	Ok(api
+		.api_version::<dyn IbcRuntimeApi<Block, AssetId>>(at)
+		.map_err(|e| runtime_error_into_rpc_error(format!("Error getting api version: {e}")))?
+		.unwrap_or_default())
+}
+
+/// Fails if the ibc runtime api implemented at `at` is older than `version`.
+fn ensure_api_version<Api, Block, AssetId>(
+	api: &Api,
+	at: &BlockId<Block>,
+	version: u32,
+) -> Result<()>
+where
+	Block: BlockT,
+	Api: IbcRuntimeApi<Block, AssetId> + ApiExt<Block>,
+	AssetId: codec::Codec,
+{
+	if runtime_api_version(api, at)? < version {
+		return Err(runtime_error_into_rpc_error(format!(
+			"Runtime api version {version} is required for this query"
+		)))
+	}
+	Ok(())
+}
+
+/// Returns the encoded nodes proving the value stored at `key` of the state at `at`.
+fn read_proof<C, Block>(client: &C, at: &BlockId<Block>, key: &[u8]) -> Result<Vec<u8>>
+where
+	Block: BlockT,
+	C: ProofProvider<Block>,
+{
+	Ok(client
+		.read_proof(at, &mut [key].into_iter())
+		.map_err(runtime_error_into_rpc_error)?
+		.iter_nodes()
+		.collect::<Vec<_>>()
+		.encode())
+}
+
 /// An implementation of IBC specific RPC methods.
 pub struct IbcRpcHandler<C, B> {
 	client: Arc<C>,
@@ -591,7 +683,7 @@ where
 		let at = BlockId::Hash(self.client.info().best_hash);
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| runtime_error_into_rpc_error(format!("Error getting para id: {e}")))?;
 		let (update_height, update_time) = api
 			.client_update_time_and_height(
 				&at,
@@ -602,7 +694,7 @@ where
 			.ok()
 			.flatten()
 			.ok_or_else(|| {
-				runtime_error_into_rpc_error("Failed to get client update time and height")
+				consensus_state_not_found("Failed to get client update time and height")
 			})?;
 		Ok(HeightAndTimestamp {
 			height: Height { revision_number: para_id.into(), revision_height: update_height },
@@ -615,7 +707,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let child_trie_key = api
 			.child_trie_key(&at)
 			.map_err(|_| runtime_error_into_rpc_error("Failed to get child trie key"))?;
@@ -668,7 +760,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryClientStateResponse = api
 			.client_state(&at, client_id.as_bytes().to_vec())
 			.ok()
@@ -714,7 +806,7 @@ where
 		};
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryConsensusStateResponse = api
 			.client_consensus_state(
 				&at,
@@ -725,7 +817,7 @@ where
 			)
 			.ok()
 			.flatten()
-			.ok_or_else(|| runtime_error_into_rpc_error("Error querying client consensus state"))?;
+			.ok_or_else(|| consensus_state_not_found("Error querying client consensus state"))?;
 		let consensus_state = AnyConsensusState::decode_vec(&result.consensus_state)
 			.map_err(|_| runtime_error_into_rpc_error("Error querying client consensus state"))?;
 		let mut keys = vec![result.trie_key];
@@ -749,13 +841,55 @@ where
 			}),
 		})
 	}
-	// TODO: Unimplemented
-	fn query_upgraded_client(&self, _height: u32) -> Result<QueryClientStateResponse> {
-		Err(runtime_error_into_rpc_error("Unimplemented"))
+	fn query_upgraded_client(&self, height: u32) -> Result<QueryClientStateResponse> {
+		let api = self.client.runtime_api();
+		let at = BlockId::Number(height.into());
+		let para_id = api
+			.para_id(&at)
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
+		ensure_api_version(&*api, &at, 3)?;
+		let client_state = api
+			.upgraded_client_state(&at)
+			.ok()
+			.flatten()
+			.ok_or_else(|| runtime_error_into_rpc_error("No upgraded client state set"))?;
+		let client_state = AnyClientState::decode_vec(&client_state)
+			.map_err(|_| runtime_error_into_rpc_error("Failed to decode upgraded client state"))?;
+		let proof = read_proof(&*self.client, &at, pallet_ibc::CLIENT_STATE_UPGRADE_PATH)?;
+		Ok(QueryClientStateResponse {
+			client_state: Some(client_state.into()),
+			proof,
+			proof_height: Some(Height {
+				revision_number: para_id.into(),
+				revision_height: height.into(),
+			}),
+		})
 	}
 
-	fn query_upgraded_cons_state(&self, _height: u32) -> Result<QueryConsensusStateResponse> {
-		Err(runtime_error_into_rpc_error("Unimplemented"))
+	fn query_upgraded_cons_state(&self, height: u32) -> Result<QueryConsensusStateResponse> {
+		let api = self.client.runtime_api();
+		let at = BlockId::Number(height.into());
+		let para_id = api
+			.para_id(&at)
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
+		ensure_api_version(&*api, &at, 3)?;
+		let consensus_state = api
+			.upgraded_consensus_state(&at)
+			.ok()
+			.flatten()
+			.ok_or_else(|| runtime_error_into_rpc_error("No upgraded consensus state set"))?;
+		let consensus_state = AnyConsensusState::decode_vec(&consensus_state).map_err(|_| {
+			runtime_error_into_rpc_error("Failed to decode upgraded consensus state")
+		})?;
+		let proof = read_proof(&*self.client, &at, pallet_ibc::CONSENSUS_STATE_UPGRADE_PATH)?;
+		Ok(QueryConsensusStateResponse {
+			consensus_state: Some(consensus_state.into()),
+			proof,
+			proof_height: Some(Height {
+				revision_number: para_id.into(),
+				revision_height: height.into(),
+			}),
+		})
 	}
 
 	fn query_clients(&self) -> Result<Vec<IdentifiedClientState>> {
@@ -792,7 +926,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryConnectionResponse = api
 			.connection(&at, connection_id.as_bytes().to_vec())
 			.ok()
@@ -834,7 +968,7 @@ where
 			.ok_or_else(|| runtime_error_into_rpc_error("Failed to fetch connections"))?;
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| runtime_error_into_rpc_error(format!("Error getting para id: {e}")))?;
 		let connections = result
 			.connections
 			.into_iter()
@@ -910,7 +1044,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let mut result: ibc_primitives::ConnectionHandshake = api
 			.connection_handshake(&at, client_id.as_bytes().to_vec(), conn_id.as_bytes().to_vec())
 			.ok()
@@ -958,7 +1092,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryChannelResponse = api
 			.channel(&at, channel_id.as_bytes().to_vec(), port_id.as_bytes().to_vec())
 			.ok()
@@ -1022,7 +1156,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryChannelsResponse = api
 			.connection_channels(&at, connection_id.as_bytes().to_vec())
 			.ok()
@@ -1069,7 +1203,7 @@ where
 		let at = BlockId::Hash(self.client.info().best_hash);
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| runtime_error_into_rpc_error(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryChannelsResponse = api
 			.channels(&at)
 			.ok()
@@ -1120,7 +1254,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryPacketCommitmentsResponse = api
 			.packet_commitments(&at, channel_id.as_bytes().to_vec(), port_id.as_bytes().to_vec())
 			.ok()
@@ -1163,7 +1297,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryPacketAcknowledgementsResponse = api
 			.packet_acknowledgements(
 				&at,
@@ -1199,6 +1333,60 @@ where
 		})
 	}
 
+	fn query_undelivered_sequences(
+		&self,
+		height: u32,
+		channel_id: String,
+		port_id: String,
+	) -> Result<UndeliveredSequences> {
+		let api = self.client.runtime_api();
+
+		let at = BlockId::Number(height.into());
+		let para_id = api
+			.para_id(&at)
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
+		let api_version = runtime_api_version(&*api, &at)?;
+		let channel_id = channel_id.as_bytes().to_vec();
+		let port_id = port_id.as_bytes().to_vec();
+		// runtimes before version 2 only return the sequences along with the commitments
+		let (send, recv, height) = if api_version >= 2 {
+			let result: ibc_primitives::QueryUndeliveredSequencesResponse =
+				api.undelivered_sequences(&at, channel_id, port_id).ok().flatten().ok_or_else(
+					|| runtime_error_into_rpc_error("Failed to fetch undelivered sequences"),
+				)?;
+			(result.send, result.recv, result.height)
+		} else {
+			let commitments = api
+				.packet_commitments(&at, channel_id.clone(), port_id.clone())
+				.ok()
+				.flatten()
+				.ok_or_else(|| runtime_error_into_rpc_error("Failed to fetch commitments"))?;
+			let acks = api
+				.packet_acknowledgements(&at, channel_id, port_id)
+				.ok()
+				.flatten()
+				.ok_or_else(|| runtime_error_into_rpc_error("Failed to fetch acknowledgements"))?;
+			let mut send = commitments
+				.commitments
+				.into_iter()
+				.map(|packet_state| packet_state.sequence)
+				.collect::<Vec<_>>();
+			let mut recv = acks
+				.acks
+				.into_iter()
+				.map(|packet_state| packet_state.sequence)
+				.collect::<Vec<_>>();
+			send.sort();
+			recv.sort();
+			(send, recv, commitments.height)
+		};
+		Ok(UndeliveredSequences {
+			send,
+			recv,
+			height: Height { revision_number: para_id.into(), revision_height: height },
+		})
+	}
+
 	fn query_unreceived_packets(
 		&self,
 		height: u32,
@@ -1252,7 +1440,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryNextSequenceReceiveResponse = api
 			.next_seq_recv(&at, channel_id.as_bytes().to_vec(), port_id.as_bytes().to_vec())
 			.ok()
@@ -1292,7 +1480,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryPacketCommitmentResponse = api
 			.packet_commitment(
 				&at,
@@ -1337,7 +1525,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryPacketAcknowledgementResponse = api
 			.packet_acknowledgement(
 				&at,
@@ -1382,7 +1570,7 @@ where
 		let at = BlockId::Number(height.into());
 		let para_id = api
 			.para_id(&at)
-			.map_err(|_| runtime_error_into_rpc_error("Error getting para id"))?;
+			.map_err(|e| state_unavailable(format!("Error getting para id: {e}")))?;
 		let result: ibc_primitives::QueryPacketReceiptResponse = api
 			.packet_receipt(&at, channel_id.as_bytes().to_vec(), port_id.as_bytes().to_vec(), seq)
 			.ok()
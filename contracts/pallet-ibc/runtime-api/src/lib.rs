@@ -21,6 +21,10 @@ use sp_std::vec::Vec;
 
 sp_api::decl_runtime_apis! {
 	/// IBC Runtime Apis
+	///
+	/// Version 2 adds `undelivered_sequences`, version 3 adds `upgraded_client_state` and
+	/// `upgraded_consensus_state`.
+	#[api_version(3)]
 	pub trait IbcRuntimeApi<AssetId: codec::Codec> {
 		/// Get Ibc child trie prefix
 		fn child_trie_key() -> Vec<u8>;
@@ -45,6 +49,12 @@ sp_api::decl_runtime_apis! {
 		/// Return the consensus state for the given client at a height
 		fn client_consensus_state(client_id: Vec<u8>, revision_number: u64, revision_height: u64, latest_cs: bool) -> Option<QueryConsensusStateResponse>;
 
+		/// Returns the protobuf encoded client state set for client upgrades
+		fn upgraded_client_state() -> Option<Vec<u8>>;
+
+		/// Returns the protobuf encoded consensus state set for client upgrades
+		fn upgraded_consensus_state() -> Option<Vec<u8>>;
+
 		/// Returns client states for all clients on chain
 		fn clients() -> Option<Vec<(Vec<u8>, Vec<u8>)>>;
 
@@ -74,6 +84,8 @@ sp_api::decl_runtime_apis! {
 
 		fn packet_acknowledgements(channel_id: Vec<u8>, port_id: Vec<u8>) -> Option<QueryPacketAcknowledgementsResponse>;
 
+		fn undelivered_sequences(channel_id: Vec<u8>, port_id: Vec<u8>) -> Option<QueryUndeliveredSequencesResponse>;
+
 		fn unreceived_packets(channel_id: Vec<u8>, port_id: Vec<u8>, seqs: Vec<u64>) -> Option<Vec<u64>>;
 
 		fn unreceived_acknowledgements(channel_id: Vec<u8>, port_id: Vec<u8>, seqs: Vec<u64>) -> Option<Vec<u64>>;
@@ -77,4 +77,18 @@ impl<T: Config> Acknowledgements<T> {
 				None
 			})
 	}
+
+	/// Returns the sequences stored for a single channel.
+	// WARNING: too expensive to be called from an on-chain context, only here for rpc layer.
+	pub fn sequences(port_id: PortId, channel_id: ChannelId) -> impl Iterator<Item = Sequence> {
+		let prefix = format!("acks/ports/{port_id}/channels/{channel_id}/sequences/");
+		let prefix_key = apply_prefix(T::PALLET_PREFIX, vec![prefix]);
+		ChildTriePrefixIterator::<(Vec<u8>, Vec<u8>)>::with_prefix(
+			&ChildInfo::new_default(T::PALLET_PREFIX),
+			&prefix_key,
+		)
+		.filter_map(|(remaining_key, _)| {
+			String::from_utf8(remaining_key).ok()?.parse::<u64>().ok().map(Sequence::from)
+		})
+	}
 }
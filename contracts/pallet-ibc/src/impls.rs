@@ -69,7 +69,7 @@ use ibc_primitives::{
 	QueryConnectionResponse, QueryConnectionsResponse, QueryConsensusStateResponse,
 	QueryNextSequenceReceiveResponse, QueryPacketAcknowledgementResponse,
 	QueryPacketAcknowledgementsResponse, QueryPacketCommitmentResponse,
-	QueryPacketCommitmentsResponse, QueryPacketReceiptResponse,
+	QueryPacketCommitmentsResponse, QueryPacketReceiptResponse, QueryUndeliveredSequencesResponse,
 };
 use scale_info::prelude::string::ToString;
 use sp_core::{crypto::AccountId32, offchain::StorageKind};
@@ -169,6 +169,16 @@ where
 			.collect::<Vec<_>>()
 	}
 
+	/// Get the protobuf encoded client state set for client upgrades
+	pub fn upgraded_client_state() -> Option<Vec<u8>> {
+		sp_io::storage::get(CLIENT_STATE_UPGRADE_PATH)
+	}
+
+	/// Get the protobuf encoded consensus state set for client upgrades
+	pub fn upgraded_consensus_state() -> Option<Vec<u8>> {
+		sp_io::storage::get(CONSENSUS_STATE_UPGRADE_PATH)
+	}
+
 	/// Get a consensus state for client
 	pub fn consensus_state(
 		client_id: Vec<u8>,
@@ -351,6 +361,25 @@ where
 		Ok(QueryPacketAcknowledgementsResponse { acks, height: host_height::<T>() })
 	}
 
+	/// Returns the sequences of packet commitments and acknowledgements stored for the channel.
+	pub fn undelivered_sequences(
+		channel_id_bytes: Vec<u8>,
+		port_id_bytes: Vec<u8>,
+	) -> Result<QueryUndeliveredSequencesResponse, Error<T>> {
+		let channel_id =
+			channel_id_from_bytes(channel_id_bytes).map_err(|_| Error::<T>::DecodingError)?;
+		let port_id = port_id_from_bytes(port_id_bytes).map_err(|_| Error::<T>::DecodingError)?;
+		let mut send = PacketCommitment::<T>::sequences(port_id.clone(), channel_id)
+			.map(u64::from)
+			.collect::<Vec<_>>();
+		let mut recv = Acknowledgements::<T>::sequences(port_id, channel_id)
+			.map(u64::from)
+			.collect::<Vec<_>>();
+		send.sort();
+		recv.sort();
+		Ok(QueryUndeliveredSequencesResponse { send, recv, height: host_height::<T>() })
+	}
+
 	pub fn unreceived_packets(
 		channel_id: Vec<u8>,
 		port_id: Vec<u8>,
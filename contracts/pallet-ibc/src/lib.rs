@@ -73,6 +73,10 @@ pub use ibc_primitives::Timeout;
 pub use light_client_common;
 
 pub const MODULE_ID: &str = "pallet_ibc";
+/// Storage key of the protobuf encoded client state set for client upgrades
+pub const CLIENT_STATE_UPGRADE_PATH: &[u8] = b"client-state-upgrade-path";
+/// Storage key of the protobuf encoded consensus state set for client upgrades
+pub const CONSENSUS_STATE_UPGRADE_PATH: &[u8] = b"consensus-state-upgrade-path";
 
 #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
 pub struct Any {
@@ -710,9 +714,6 @@ pub mod pallet {
 		#[pallet::weight(0)]
 		pub fn upgrade_client(origin: OriginFor<T>, params: UpgradeParams) -> DispatchResult {
 			<T as Config>::AdminOrigin::ensure_origin(origin)?;
-			const CLIENT_STATE_UPGRADE_PATH: &[u8] = b"client-state-upgrade-path";
-			const CONSENSUS_STATE_UPGRADE_PATH: &[u8] = b"consensus-state-upgrade-path";
-
 			sp_io::storage::set(CLIENT_STATE_UPGRADE_PATH, &params.client_state);
 			sp_io::storage::set(CONSENSUS_STATE_UPGRADE_PATH, &params.consensus_state);
 
@@ -566,3 +566,37 @@ fn should_cleanup_offchain_packets_correctly() {
 		assert_eq!(last_removed_ack, 10);
 	});
 }
+
+#[test]
+fn should_query_undelivered_sequences_of_a_channel() {
+	new_test_ext().execute_with(|| {
+		let port_id = PortId::transfer();
+		let channel_id = ChannelId::new(0);
+		let other_channel_id = ChannelId::new(1);
+		let mut ctx = Context::<Test>::default();
+		for i in 1..=12u64 {
+			let channel_id = if i % 3 == 0 { other_channel_id } else { channel_id };
+			ctx.store_packet_commitment(
+				(port_id.clone(), channel_id, i.into()),
+				"commitment".as_bytes().to_vec().into(),
+			)
+			.unwrap();
+			if i % 2 == 0 {
+				ctx.store_packet_acknowledgement(
+					(port_id.clone(), channel_id, i.into()),
+					"acknowledgement".as_bytes().to_vec().into(),
+				)
+				.unwrap();
+			}
+		}
+
+		let sequences = Pallet::<Test>::undelivered_sequences(
+			channel_id.to_string().as_bytes().to_vec(),
+			port_id.as_bytes().to_vec(),
+		)
+		.unwrap();
+
+		assert_eq!(sequences.send, vec![1, 2, 4, 5, 7, 8, 10, 11]);
+		assert_eq!(sequences.recv, vec![2, 4, 8, 10]);
+	});
+}
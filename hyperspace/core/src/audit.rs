@@ -0,0 +1,170 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+	fs::{self, OpenOptions},
+	io::{self, BufRead, BufReader, Write},
+	path::PathBuf,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use ibc_proto::google::protobuf::Any;
+use metrics::handler::message_packet;
+use primitives::error::Error as PrimitivesError;
+use serde::{Deserialize, Serialize};
+
+/// Size in bytes after which the audit log is rotated when none is configured.
+pub const DEFAULT_AUDIT_LOG_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Number of rotated audit log files kept next to the current one.
+pub const ROTATED_AUDIT_LOGS: usize = 5;
+
+/// A message submitted by the relayer, as recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+	/// Unix timestamp, in seconds, of the submission
+	pub timestamp: u64,
+	/// Name of the chain the message was submitted to
+	pub chain: String,
+	/// Type url of the message
+	pub type_url: String,
+	/// `port/channel` the packet carried by the message was sent from
+	pub channel: Option<String>,
+	/// Sequence of the packet carried by the message
+	pub sequence: Option<u64>,
+	/// Transaction the message was included in, `None` if it wasn't included
+	pub tx_hash: Option<String>,
+	/// Result code the transaction failed with on chain, `None` if it succeeded or wasn't
+	/// included
+	pub code: Option<u32>,
+	/// Error the submission failed with
+	pub error: Option<String>,
+}
+
+impl AuditRecord {
+	/// Records the submission of `message` to `chain`, with the transaction hash it was included
+	/// in or the error it failed with.
+	pub fn new(chain: &str, message: &Any, result: &Result<String, &anyhow::Error>) -> Self {
+		let packet = message_packet(message);
+		// transactions that failed on chain were still included
+		let failed = result.as_ref().err().and_then(|e| {
+			e.chain().find_map(|e| match e.downcast_ref::<PrimitivesError>() {
+				Some(PrimitivesError::TransactionFailed { tx_hash, code, .. }) =>
+					Some((tx_hash.clone(), *code)),
+				_ => None,
+			})
+		});
+		Self {
+			timestamp: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|elapsed| elapsed.as_secs())
+				.unwrap_or_default(),
+			chain: chain.to_string(),
+			type_url: message.type_url.clone(),
+			channel: packet
+				.as_ref()
+				.map(|packet| format!("{}/{}", packet.source_port, packet.source_channel)),
+			sequence: packet.map(|packet| packet.sequence.into()),
+			tx_hash: result.as_ref().ok().cloned().or_else(|| failed.clone().map(|(hash, _)| hash)),
+			code: failed.map(|(_, code)| code),
+			error: result.as_ref().err().map(|e| e.to_string()),
+		}
+	}
+}
+
+/// Append-only JSONL file of the messages submitted by the relayer. The file is rotated to
+/// `<path>.1` once it exceeds its maximum size, older files are shifted up to
+/// `<path>.`[`ROTATED_AUDIT_LOGS`].
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+	path: PathBuf,
+	max_bytes: u64,
+}
+
+impl AuditLog {
+	pub fn new(path: PathBuf, max_bytes: u64) -> Self {
+		Self { path, max_bytes }
+	}
+
+	/// Records the submission of `messages` to `chain`. The log is written to on the blocking
+	/// thread pool, failures to write it are only logged.
+	pub async fn record_submission(
+		&self,
+		chain: &str,
+		messages: &[Any],
+		result: &Result<String, &anyhow::Error>,
+	) {
+		let records = messages
+			.iter()
+			.map(|message| AuditRecord::new(chain, message, result))
+			.collect::<Vec<_>>();
+		let audit_log = self.clone();
+		match tokio::task::spawn_blocking(move || audit_log.append(&records)).await {
+			Ok(Ok(())) => {},
+			Ok(Err(e)) =>
+				log::error!("Failed to write to the audit log {}: {:?}", self.path.display(), e),
+			Err(e) => log::error!("Audit log writer panicked: {:?}", e),
+		}
+	}
+
+	/// Appends `records` to the log, rotating it first if it is full.
+	pub fn append(&self, records: &[AuditRecord]) -> io::Result<()> {
+		if fs::metadata(&self.path).map(|metadata| metadata.len()).unwrap_or_default() >=
+			self.max_bytes
+		{
+			self.rotate()?;
+		}
+		let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+		let mut lines = vec![];
+		for record in records {
+			serde_json::to_writer(&mut lines, record)?;
+			lines.push(b'\n');
+		}
+		file.write_all(&lines)
+	}
+
+	/// Returns the records in the log, including rotated files, oldest first.
+	pub fn records(&self) -> io::Result<Vec<AuditRecord>> {
+		let mut records = vec![];
+		for index in (0..=ROTATED_AUDIT_LOGS).rev() {
+			let path = self.rotated_path(index);
+			if !path.exists() {
+				continue
+			}
+			for line in BufReader::new(fs::File::open(path)?).lines() {
+				records.push(serde_json::from_str(&line?)?);
+			}
+		}
+		Ok(records)
+	}
+
+	fn rotate(&self) -> io::Result<()> {
+		for index in (0..ROTATED_AUDIT_LOGS).rev() {
+			let path = self.rotated_path(index);
+			if path.exists() {
+				fs::rename(path, self.rotated_path(index + 1))?;
+			}
+		}
+		Ok(())
+	}
+
+	fn rotated_path(&self, index: usize) -> PathBuf {
+		if index == 0 {
+			return self.path.clone()
+		}
+		let mut path = self.path.clone().into_os_string();
+		path.push(format!(".{}", index));
+		path.into()
+	}
+}
@@ -0,0 +1,102 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use ibc::bigint::U256;
+use metrics::data::Metrics;
+use primitives::{
+	alert::{Alerts, Severity},
+	Chain,
+};
+
+/// Interval between balance checks when none is configured.
+pub const DEFAULT_BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Queries the relayer balance on `chain` and reports it to `metrics`. Returns `true`, warning
+/// about it, if the balance of the native token, see [`primitives::IbcProvider::native_denom`],
+/// is below `min_balance`. Watch-only chains have no relayer account and are skipped.
+pub async fn check_balance<C: Chain>(
+	chain: &C,
+	min_balance: Option<u128>,
+	metrics: Option<&Metrics>,
+	alerts: &Alerts,
+) -> Result<bool, anyhow::Error> {
+	if chain.is_watch_only() {
+		log::debug!("{} is watch-only, skipping the balance check", chain.name());
+		return Ok(false)
+	}
+	let coins = chain.query_ibc_balance().await?;
+	let native_denom = chain.native_denom();
+	let native = coins.iter().find(|coin| coin.denom.to_string() == native_denom);
+	let low = match (native, min_balance) {
+		(Some(coin), Some(min_balance)) => coin.amount.as_u256() < U256::from(min_balance),
+		_ => false,
+	};
+	if let (true, Some(coin)) = (low, native) {
+		log::warn!(
+			"Relayer balance on {} is {} {}, below the minimum of {}. Top up {} to keep relaying",
+			chain.name(),
+			coin.amount,
+			coin.denom,
+			min_balance.unwrap_or_default(),
+			chain.account_id(),
+		);
+		alerts
+			.alert(
+				&format!("balance/{}", chain.name()),
+				Severity::Warning,
+				format!(
+					"Relayer balance on {} is {} {}, below the minimum of {}",
+					chain.name(),
+					coin.amount,
+					coin.denom,
+					min_balance.unwrap_or_default(),
+				),
+			)
+			.await;
+	} else {
+		alerts.resolve(&format!("balance/{}", chain.name()));
+	}
+
+	if let Some(metrics) = metrics {
+		for coin in &coins {
+			// gauges are floats, precision loss on large balances is fine for monitoring
+			let amount = coin.amount.to_string().parse::<f64>().unwrap_or(f64::MAX);
+			metrics
+				.relayer_balance
+				.with_label_values(&[&coin.denom.to_string()])
+				.set(amount);
+		}
+		metrics.relayer_balance_low.set(low as u64);
+	}
+	Ok(low)
+}
+
+/// Checks the relayer balance on `chain` every `interval`, see [`check_balance`].
+pub async fn monitor_balance<C: Chain>(
+	chain: C,
+	min_balance: Option<u128>,
+	interval: Duration,
+	metrics: Option<Metrics>,
+	alerts: Alerts,
+) {
+	let mut interval = tokio::time::interval(interval);
+	loop {
+		interval.tick().await;
+		if let Err(e) = check_balance(&chain, min_balance, metrics.as_ref(), &alerts).await {
+			log::warn!("Failed to check relayer balance on {}: {:?}", chain.name(), e);
+		}
+	}
+}
@@ -0,0 +1,249 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reconciliation of the clients, connection and channels declared in the config with the ones
+//! that exist on chain.
+
+use std::{str::FromStr, time::Duration};
+
+use ibc::core::{
+	ics02_client::client_state::ClientState,
+	ics03_connection::connection::{IdentifiedConnectionEnd, State as ConnectionState},
+	ics04_channel::{
+		channel::{IdentifiedChannelEnd, Order, State as ChannelState},
+		Version,
+	},
+	ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
+};
+use pallet_ibc::light_clients::AnyClientState;
+use primitives::{
+	utils::{create_channel, create_clients, create_connection},
+	Chain,
+};
+use serde::Deserialize;
+
+use crate::{chain::Config, relay};
+
+/// Connection and channels the relayer serves, created on startup if they don't exist yet.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BootstrapConfig {
+	/// Delay period, in seconds, of the connection
+	#[serde(default)]
+	pub connection_delay_secs: u64,
+	/// Channels to serve over the connection
+	#[serde(default)]
+	pub channels: Vec<ChannelConfig>,
+}
+
+/// A channel declared in the [`BootstrapConfig`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelConfig {
+	/// Port of the channel on both chains
+	pub port_id: PortId,
+	/// Channel version
+	pub version: String,
+	/// Channel ordering, `ordered` or `unordered`
+	pub order: String,
+}
+
+/// Returns the id of an active client of `chain` on `counterparty`, one for the same chain id and
+/// of the same type as the client `chain` would create.
+pub async fn find_client(
+	chain: &impl Chain,
+	counterparty: &impl Chain,
+) -> Result<Option<ClientId>, anyhow::Error> {
+	let (client_state, _) = chain.initialize_client_state().await?;
+	let (height, ..) = counterparty.latest_height_and_timestamp().await?;
+	for client_id in counterparty.query_clients().await? {
+		let response = counterparty.query_client_state(height, client_id.clone()).await?;
+		let existing = match response.client_state.map(AnyClientState::try_from) {
+			Some(Ok(existing)) => existing,
+			_ => continue,
+		};
+		if existing.client_type() == client_state.client_type() &&
+			existing.chain_id() == client_state.chain_id() &&
+			!existing.is_frozen()
+		{
+			return Ok(Some(client_id))
+		}
+	}
+	Ok(None)
+}
+
+/// Returns the ids, on `chain_a` and `chain_b`, of an open connection with `delay_period` between
+/// the clients of both chains.
+pub async fn find_open_connection(
+	chain_a: &impl Chain,
+	chain_b: &impl Chain,
+	delay_period: Duration,
+) -> Result<Option<(ConnectionId, ConnectionId)>, anyhow::Error> {
+	let (height, ..) = chain_a.latest_height_and_timestamp().await?;
+	let connections = chain_a
+		.query_connection_using_client(
+			height.revision_height as u32,
+			chain_b.client_id().to_string(),
+		)
+		.await?;
+	for connection in connections {
+		let IdentifiedConnectionEnd { connection_id, connection_end } =
+			IdentifiedConnectionEnd::try_from(connection)?;
+		let counterparty = connection_end.counterparty();
+		match counterparty.connection_id() {
+			Some(counterparty_connection_id)
+				if *connection_end.state() == ConnectionState::Open &&
+					*counterparty.client_id() == chain_a.client_id() &&
+					connection_end.delay_period() == delay_period =>
+				return Ok(Some((connection_id, counterparty_connection_id.clone()))),
+			_ => {},
+		}
+	}
+	Ok(None)
+}
+
+/// Returns the ids, on `chain_a` and its counterparty, of an open channel over `connection_id`
+/// matching `channel`.
+pub async fn find_open_channel(
+	chain_a: &impl Chain,
+	connection_id: &ConnectionId,
+	channel: &ChannelConfig,
+) -> Result<Option<(ChannelId, ChannelId)>, anyhow::Error> {
+	let order = Order::from_str(&channel.order)?;
+	let version = Version::new(channel.version.clone());
+	let (height, ..) = chain_a.latest_height_and_timestamp().await?;
+	let channels = chain_a.query_connection_channels(height, connection_id).await?.channels;
+	for identified in channels {
+		let IdentifiedChannelEnd { port_id, channel_id, channel_end } =
+			IdentifiedChannelEnd::try_from(identified)?;
+		match channel_end.counterparty().channel_id {
+			Some(counterparty_channel_id)
+				if port_id == channel.port_id &&
+					*channel_end.state() == ChannelState::Open &&
+					*channel_end.ordering() == order &&
+					*channel_end.version() == version =>
+				return Ok(Some((channel_id, counterparty_channel_id))),
+			_ => {},
+		}
+	}
+	Ok(None)
+}
+
+/// Finds the connection and channels declared in `bootstrap` between the clients of `chain_a` and
+/// `chain_b`, creating the ones that don't exist yet. `connection_ids` are used instead of looking
+/// the connection up if set. The relay loop must be running between the chains to complete the
+/// handshakes.
+pub async fn reconcile_channels(
+	chain_a: &impl Chain,
+	chain_b: &impl Chain,
+	connection_ids: Option<(ConnectionId, ConnectionId)>,
+	bootstrap: &BootstrapConfig,
+) -> Result<((ConnectionId, ConnectionId), Vec<(ChannelId, ChannelId)>), anyhow::Error> {
+	let delay = Duration::from_secs(bootstrap.connection_delay_secs);
+	let (connection_a, connection_b) = match connection_ids {
+		Some(connection_ids) => connection_ids,
+		None => match find_open_connection(chain_a, chain_b, delay).await? {
+			Some(connection_ids) => connection_ids,
+			None => create_connection(chain_a, chain_b, delay).await?,
+		},
+	};
+	log::info!("Serving connection {} to {}", connection_a, connection_b);
+
+	let mut channels = vec![];
+	for channel in &bootstrap.channels {
+		let (channel_a, channel_b) =
+			match find_open_channel(chain_a, &connection_a, channel).await? {
+				Some(channel_ids) => channel_ids,
+				None =>
+					create_channel(
+						chain_a,
+						chain_b,
+						connection_a.clone(),
+						channel.port_id.clone(),
+						channel.version.clone(),
+						Order::from_str(&channel.order)?,
+					)
+					.await?,
+			};
+		log::info!("Serving channel {}/{} to {}", channel.port_id, channel_a, channel_b);
+		channels.push((channel_a, channel_b));
+	}
+	Ok(((connection_a, connection_b), channels))
+}
+
+/// Creates the clients, connection and channels declared in the config that don't exist yet,
+/// reusing the configured and open ones, and fills their ids into the config. Clients that aren't
+/// configured are looked up on the counterparty before creating new ones.
+pub async fn reconcile(config: &mut Config) -> Result<(), anyhow::Error> {
+	let bootstrap = match config.core.bootstrap.clone() {
+		Some(bootstrap) => bootstrap,
+		None => return Ok(()),
+	};
+
+	if config.chain_a.client_id().is_none() || config.chain_b.client_id().is_none() {
+		let chain_a = config.chain_a.clone().into_client().await?;
+		let chain_b = config.chain_b.clone().into_client().await?;
+		let client_id_a_on_b = match config.chain_a.client_id() {
+			Some(client_id) => Some(client_id),
+			None => find_client(&chain_a, &chain_b).await?,
+		};
+		let client_id_b_on_a = match config.chain_b.client_id() {
+			Some(client_id) => Some(client_id),
+			None => find_client(&chain_b, &chain_a).await?,
+		};
+		let (client_id_a_on_b, client_id_b_on_a) = match (client_id_a_on_b, client_id_b_on_a) {
+			(Some(client_id_a_on_b), Some(client_id_b_on_a)) => {
+				log::info!(
+					"Reusing client {} on {} and {} on {}",
+					client_id_a_on_b,
+					chain_b.name(),
+					client_id_b_on_a,
+					chain_a.name()
+				);
+				(client_id_a_on_b, client_id_b_on_a)
+			},
+			_ => {
+				let (client_id_a_on_b, client_id_b_on_a) =
+					create_clients(&chain_a, &chain_b).await?;
+				log::info!(
+					"Created client {} on {} and {} on {}, add them to the config to reuse them",
+					client_id_a_on_b,
+					chain_b.name(),
+					client_id_b_on_a,
+					chain_a.name()
+				);
+				(client_id_a_on_b, client_id_b_on_a)
+			},
+		};
+		config.chain_a.set_client_id(client_id_a_on_b);
+		config.chain_b.set_client_id(client_id_b_on_a);
+	}
+
+	let chain_a = config.chain_a.clone().into_client().await?;
+	let chain_b = config.chain_b.clone().into_client().await?;
+	// handshakes are driven by the relay loop
+	let handle = tokio::spawn(relay(chain_a.clone(), chain_b.clone(), None, None));
+	let connection_ids = match (config.chain_a.connection_id(), config.chain_b.connection_id()) {
+		(Some(connection_a), Some(connection_b)) => Some((connection_a, connection_b)),
+		_ => None,
+	};
+	let result = reconcile_channels(&chain_a, &chain_b, connection_ids, &bootstrap).await;
+	handle.abort();
+	let ((connection_a, connection_b), channels) = result?;
+	config.chain_a.set_connection_id(connection_a);
+	config.chain_b.set_connection_id(connection_b);
+	for (channel, (channel_a, channel_b)) in bootstrap.channels.iter().zip(channels) {
+		config.chain_a.whitelist_channel(channel_a, channel.port_id.clone());
+		config.chain_b.whitelist_channel(channel_b, channel.port_id.clone());
+	}
+	Ok(())
+}
@@ -0,0 +1,36 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Version of the running relayer build, so its activity can be attributed to it.
+
+/// Version of the relayer crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the git commit the relayer was built from.
+pub const GIT_HASH: &str = env!("HYPERSPACE_GIT_HASH");
+
+/// Version and git hash, as printed by `--version`.
+pub const LONG_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "-", env!("HYPERSPACE_GIT_HASH"));
+
+/// Cargo features the relayer was built with.
+pub fn features() -> Vec<&'static str> {
+	let mut features = vec![];
+	if cfg!(feature = "testing") {
+		features.push("testing");
+	}
+	if cfg!(feature = "build-metadata-from-ws") {
+		features.push("build-metadata-from-ws");
+	}
+	features
+}
@@ -33,6 +33,7 @@ use ibc::{
 	Height,
 };
 use ibc_proto::{
+	cosmos::base::v1beta1::Coin,
 	google::protobuf::Any,
 	ibc::core::{
 		channel::v1::{
@@ -50,14 +51,20 @@ use pallet_ibc::Timeout;
 use serde::Deserialize;
 use thiserror::Error;
 
+use crate::{bootstrap::BootstrapConfig, RelayDirection};
 use ibc::core::ics02_client::events::UpdateClient;
 use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState};
 use parachain::{config, ParachainClient};
-use primitives::{Chain, IbcProvider, KeyProvider, MisbehaviourHandler, UpdateType};
+use primitives::{
+	alert::{Alerts, WebhookSink},
+	packet_data::PacketFilter,
+	Chain, IbcProvider, KeyProvider, MisbehaviourHandler, RelayMode, TransactionCost, UpdateType,
+	UpgradePlan,
+};
 use sp_runtime::generic::Era;
-use std::{pin::Pin, time::Duration};
+use std::{path::PathBuf, pin::Pin, sync::Arc, time::Duration};
 use subxt::{
-	tx::{ExtrinsicParams, PolkadotExtrinsicParams, PolkadotExtrinsicParamsBuilder},
+	tx::{ExtrinsicParams, PlainTip, PolkadotExtrinsicParams, PolkadotExtrinsicParamsBuilder},
 	Error, OnlineClient,
 };
 
@@ -65,17 +72,35 @@ use subxt::{
 #[derive(Debug, Clone)]
 pub enum DefaultConfig {}
 
+/// Number of blocks extrinsics stay valid for, an extrinsic that isn't included by then is
+/// dropped from the transaction pool instead of blocking the nonces after it.
+const MORTAL_ERA_PERIOD: u64 = 64;
+
 #[async_trait]
 impl config::Config for DefaultConfig {
 	type AssetId = u128;
 	async fn custom_extrinsic_params(
 		client: &OnlineClient<Self>,
+		tip: u128,
 	) -> Result<
 		<Self::ExtrinsicParams as ExtrinsicParams<Self::Index, Self::Hash>>::OtherParams,
 		Error,
 	> {
-		let params =
-			PolkadotExtrinsicParamsBuilder::new().era(Era::Immortal, client.genesis_hash());
+		let header = client
+			.rpc()
+			.header(None)
+			.await?
+			.ok_or_else(|| Error::Other("Latest header not found".to_string()))?;
+		let era = Era::mortal(MORTAL_ERA_PERIOD, header.number.into());
+		let birth = era.birth(header.number.into()) as u32;
+		let checkpoint = client
+			.rpc()
+			.block_hash(Some(birth.into()))
+			.await?
+			.ok_or_else(|| Error::Other(format!("Block hash not found for {birth}")))?;
+		let params = PolkadotExtrinsicParamsBuilder::new()
+			.tip(PlainTip::new(tip))
+			.era(era, checkpoint);
 		Ok(params.into())
 	}
 }
@@ -100,7 +125,7 @@ pub struct Config {
 	pub core: CoreConfig,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AnyConfig {
 	Parachain(parachain::ParachainClientConfig),
@@ -109,6 +134,22 @@ pub enum AnyConfig {
 #[derive(Deserialize)]
 pub struct CoreConfig {
 	pub prometheus_endpoint: Option<String>,
+	/// Seconds between checks of the relayer balance on both chains.
+	pub balance_check_interval_secs: Option<u64>,
+	/// Seconds after which a packet that is neither delivered nor timed out is reported as stuck.
+	pub stuck_packet_age_secs: Option<u64>,
+	/// Path of the JSONL file every submitted message is recorded to.
+	pub audit_log: Option<PathBuf>,
+	/// Size in bytes after which the audit log is rotated.
+	pub audit_log_max_bytes: Option<u64>,
+	/// Webhook urls alerts are posted to, per severity.
+	pub alert_webhooks: Option<WebhookSink>,
+	/// Path of the JSONL file the ibc events the relayer acts on are recorded to, for replays.
+	pub event_log: Option<PathBuf>,
+	/// Connection and channels reconciled on startup.
+	pub bootstrap: Option<BootstrapConfig>,
+	/// Directions packets are relayed in, both by default.
+	pub relay_direction: Option<RelayDirection>,
 }
 
 #[derive(Clone)]
@@ -126,6 +167,14 @@ pub enum AnyTransactionId {
 	Parachain(parachain::provider::TransactionId<sp_core::H256>),
 }
 
+impl std::fmt::Display for AnyTransactionId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Parachain(tx_id) => write!(f, "{}", tx_id),
+		}
+	}
+}
+
 #[derive(Error, Debug)]
 pub enum AnyError {
 	#[error("{0}")]
@@ -150,7 +199,7 @@ impl IbcProvider for AnyChain {
 		&mut self,
 		finality_event: Self::FinalityEvent,
 		counterparty: &T,
-	) -> Result<(Any, Vec<IbcEvent>, UpdateType), anyhow::Error>
+	) -> Result<(Vec<Any>, Vec<IbcEvent>, UpdateType), anyhow::Error>
 	where
 		T: Chain,
 	{
@@ -158,9 +207,9 @@ impl IbcProvider for AnyChain {
 			AnyChain::Parachain(chain) => {
 				let finality_event = ibc::downcast!(finality_event => AnyFinalityEvent::Parachain)
 					.ok_or_else(|| AnyError::Other("Invalid finality event type".to_owned()))?;
-				let (client_msg, events, update_type) =
+				let (client_msgs, events, update_type) =
 					chain.query_latest_ibc_events(finality_event, counterparty).await?;
-				Ok((client_msg, events, update_type))
+				Ok((client_msgs, events, update_type))
 			},
 			_ => unreachable!(),
 		}
@@ -200,6 +249,28 @@ impl IbcProvider for AnyChain {
 		}
 	}
 
+	async fn query_upgraded_client_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryClientStateResponse, Self::Error> {
+		match self {
+			AnyChain::Parachain(chain) =>
+				chain.query_upgraded_client_state(upgrade_height).await.map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
+
+	async fn query_upgraded_consensus_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryConsensusStateResponse, Self::Error> {
+		match self {
+			AnyChain::Parachain(chain) =>
+				chain.query_upgraded_consensus_state(upgrade_height).await.map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
+
 	async fn query_connection_end(
 		&self,
 		at: Height,
@@ -232,6 +303,18 @@ impl IbcProvider for AnyChain {
 		}
 	}
 
+	async fn query_key_with_proof(
+		&self,
+		at: Height,
+		key: Vec<u8>,
+	) -> Result<(Option<Vec<u8>>, Vec<u8>), Self::Error> {
+		match self {
+			AnyChain::Parachain(chain) =>
+				chain.query_key_with_proof(at, key).await.map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
+
 	async fn query_packet_commitment(
 		&self,
 		at: Height,
@@ -372,6 +455,34 @@ impl IbcProvider for AnyChain {
 		}
 	}
 
+	fn add_channel_to_whitelist(&mut self, channel: (ChannelId, PortId)) {
+		match self {
+			Self::Parachain(chain) => chain.add_channel_to_whitelist(channel),
+			_ => unreachable!(),
+		}
+	}
+
+	fn packet_filter(&self) -> &PacketFilter {
+		match self {
+			Self::Parachain(chain) => chain.packet_filter(),
+			_ => unreachable!(),
+		}
+	}
+
+	fn relay_mode(&self, channel_id: ChannelId, port_id: &PortId) -> RelayMode {
+		match self {
+			Self::Parachain(chain) => chain.relay_mode(channel_id, port_id),
+			_ => unreachable!(),
+		}
+	}
+
+	fn verify_proofs(&self) -> bool {
+		match self {
+			Self::Parachain(chain) => chain.verify_proofs(),
+			_ => unreachable!(),
+		}
+	}
+
 	async fn query_connection_channels(
 		&self,
 		at: Height,
@@ -449,6 +560,13 @@ impl IbcProvider for AnyChain {
 		}
 	}
 
+	fn native_denom(&self) -> String {
+		match self {
+			Self::Parachain(chain) => chain.native_denom(),
+			_ => unreachable!(),
+		}
+	}
+
 	fn connection_prefix(&self) -> CommitmentPrefix {
 		match self {
 			AnyChain::Parachain(chain) => chain.connection_prefix(),
@@ -485,6 +603,14 @@ impl IbcProvider for AnyChain {
 		}
 	}
 
+	async fn query_timestamps_at(&self, block_numbers: Vec<u64>) -> Result<Vec<u64>, Self::Error> {
+		match self {
+			Self::Parachain(chain) =>
+				chain.query_timestamps_at(block_numbers).await.map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
+
 	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error> {
 		match self {
 			Self::Parachain(chain) => chain.query_clients().await.map_err(Into::into),
@@ -570,6 +696,20 @@ impl KeyProvider for AnyChain {
 			_ => unreachable!(),
 		}
 	}
+
+	fn signer(&self) -> Option<Arc<dyn primitives::signer::Signer>> {
+		match self {
+			AnyChain::Parachain(parachain) => parachain.signer(),
+			_ => unreachable!(),
+		}
+	}
+
+	fn is_watch_only(&self) -> bool {
+		match self {
+			AnyChain::Parachain(parachain) => parachain.is_watch_only(),
+			_ => unreachable!(),
+		}
+	}
 }
 
 #[async_trait]
@@ -588,7 +728,7 @@ impl Chain for AnyChain {
 		}
 	}
 
-	async fn estimate_weight(&self, msg: Vec<Any>) -> Result<u64, Self::Error> {
+	async fn estimate_weight(&self, msg: &[Any]) -> Result<u64, Self::Error> {
 		match self {
 			Self::Parachain(chain) => chain.estimate_weight(msg).await.map_err(Into::into),
 			_ => unreachable!(),
@@ -607,7 +747,7 @@ impl Chain for AnyChain {
 		}
 	}
 
-	async fn submit(&self, messages: Vec<Any>) -> Result<Self::TransactionId, Self::Error> {
+	async fn submit(&self, messages: &[Any]) -> Result<Self::TransactionId, Self::Error> {
 		match self {
 			Self::Parachain(chain) => chain
 				.submit(messages)
@@ -618,6 +758,14 @@ impl Chain for AnyChain {
 		}
 	}
 
+	fn transaction_cost(&self, tx_id: &Self::TransactionId) -> TransactionCost {
+		match (self, tx_id) {
+			(Self::Parachain(chain), AnyTransactionId::Parachain(tx_id)) =>
+				chain.transaction_cost(tx_id),
+			_ => unreachable!(),
+		}
+	}
+
 	async fn query_client_message(
 		&self,
 		update: UpdateClient,
@@ -627,6 +775,27 @@ impl Chain for AnyChain {
 			_ => unreachable!(),
 		}
 	}
+
+	async fn detect_scheduled_upgrade(&self) -> Option<UpgradePlan> {
+		match self {
+			Self::Parachain(chain) => chain.detect_scheduled_upgrade().await,
+			_ => unreachable!(),
+		}
+	}
+
+	fn heartbeat_interval(&self) -> Option<Duration> {
+		match self {
+			Self::Parachain(chain) => chain.heartbeat_interval(),
+			_ => unreachable!(),
+		}
+	}
+
+	fn full_scan_interval(&self) -> Option<u64> {
+		match self {
+			Self::Parachain(chain) => chain.full_scan_interval(),
+			_ => unreachable!(),
+		}
+	}
 }
 
 #[cfg(any(test, feature = "testing"))]
@@ -673,4 +842,107 @@ impl AnyConfig {
 				AnyChain::Parachain(ParachainClient::new(config).await?),
 		})
 	}
+
+	/// Native token balance below which the relayer warns about its account running out of funds.
+	pub fn min_balance(&self) -> Option<u128> {
+		match self {
+			AnyConfig::Parachain(config) => config.min_balance,
+		}
+	}
+
+	/// Makes the client sign transactions and log them instead of submitting them.
+	pub fn set_dry_run(&mut self, dry_run: bool) {
+		match self {
+			AnyConfig::Parachain(config) => config.dry_run |= dry_run,
+		}
+	}
+
+	/// Makes the client report conditions that need an operator's attention to `alerts`.
+	pub fn set_alerts(&mut self, alerts: Alerts) {
+		match self {
+			AnyConfig::Parachain(config) => config.alerts = alerts,
+		}
+	}
+
+	/// Configured light client id on the counterparty chain.
+	pub fn client_id(&self) -> Option<ClientId> {
+		match self {
+			AnyConfig::Parachain(config) => config.client_id.clone(),
+		}
+	}
+
+	pub fn set_client_id(&mut self, client_id: ClientId) {
+		match self {
+			AnyConfig::Parachain(config) => config.client_id = Some(client_id),
+		}
+	}
+
+	/// Configured connection id on this chain.
+	pub fn connection_id(&self) -> Option<ConnectionId> {
+		match self {
+			AnyConfig::Parachain(config) => config.connection_id.clone(),
+		}
+	}
+
+	pub fn set_connection_id(&mut self, connection_id: ConnectionId) {
+		match self {
+			AnyConfig::Parachain(config) => config.connection_id = Some(connection_id),
+		}
+	}
+
+	/// Adds a channel on this chain to the channel whitelist, if it isn't in it yet.
+	pub fn whitelist_channel(&mut self, channel_id: ChannelId, port_id: PortId) {
+		match self {
+			AnyConfig::Parachain(config) =>
+				if !config.channel_whitelist.contains(&(channel_id, port_id.clone())) {
+					config.channel_whitelist.push((channel_id, port_id))
+				},
+		}
+	}
+}
+
+impl AnyChain {
+	/// Replaces the signing key with the one configured in `config`.
+	pub async fn rotate_key(&self, config: &AnyConfig) -> Result<(), AnyError> {
+		match (self, config) {
+			(Self::Parachain(chain), AnyConfig::Parachain(config)) => chain
+				.rotate_key(&config.key_type, config.private_key.as_deref())
+				.await
+				.map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
+
+	/// Returns the balance escrowed by the ics20 module for transfers out of the given channel.
+	pub async fn query_escrow_balance(
+		&self,
+		port_id: &PortId,
+		channel_id: ChannelId,
+	) -> Result<Coin, AnyError> {
+		match self {
+			Self::Parachain(chain) =>
+				chain.query_escrow_balance(port_id, channel_id).await.map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
+
+	/// Returns the balance of every account the ics20 module has escrowed tokens in.
+	pub async fn query_escrow_balances(&self) -> Result<Vec<(String, Coin)>, AnyError> {
+		match self {
+			Self::Parachain(chain) => chain.query_escrow_balances().await.map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
+
+	/// Returns the account the ics20 module escrows the tokens sent out of the given channel in.
+	pub fn escrow_account(
+		&self,
+		port_id: &PortId,
+		channel_id: ChannelId,
+	) -> Result<String, AnyError> {
+		match self {
+			Self::Parachain(chain) => chain.escrow_account(port_id, channel_id).map_err(Into::into),
+			_ => unreachable!(),
+		}
+	}
 }
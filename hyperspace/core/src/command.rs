@@ -12,21 +12,45 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
 use primitives::Chain;
 use prometheus::Registry;
-use std::{path::PathBuf, str::FromStr, time::Duration};
+use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+use tokio::signal::unix::{signal, SignalKind};
 
-use crate::{chain::Config, fish, relay};
-use ibc::core::{ics04_channel::channel::Order, ics24_host::identifier::PortId};
-use metrics::{data::Metrics, handler::MetricsHandler, init_prometheus};
+use crate::{
+	audit::{AuditLog, DEFAULT_AUDIT_LOG_MAX_BYTES},
+	balance::{monitor_balance, DEFAULT_BALANCE_CHECK_INTERVAL},
+	bootstrap::reconcile,
+	build_info::{self, LONG_VERSION},
+	chain::{AnyChain, Config},
+	fish,
+	journal::Journal,
+	packets::stuck::{monitor_stuck_packets, DEFAULT_STUCK_PACKET_AGE},
+	relay, relay_in_direction,
+	replay::{replay, EventLog},
+};
+use ibc::core::{
+	ics04_channel::channel::Order,
+	ics24_host::identifier::{ChannelId, PortId},
+};
+use metrics::{
+	data::Metrics, handler::MetricsHandler, init_prometheus, register_build_info, scrape,
+};
 use primitives::{
-	utils::{create_channel, create_clients, create_connection},
-	IbcProvider,
+	alert::Alerts,
+	check_client_status,
+	error::Error as PrimitivesError,
+	utils::{
+		client_update_proposal, create_channel, create_clients, create_connection,
+		create_substitute_client,
+	},
+	IbcProvider, KeyProvider,
 };
 
 #[derive(Debug, Parser)]
+#[clap(version = LONG_VERSION)]
 pub struct Cli {
 	#[structopt(subcommand)]
 	pub subcommand: Subcommand,
@@ -48,6 +72,26 @@ pub enum Subcommand {
 	CreateConnection(Cmd),
 	#[clap(name = "create-channel", about = "Creates a channel on the specified port")]
 	CreateChannel(Cmd),
+	#[clap(
+		name = "query-escrow",
+		about = "Queries the ics20 escrow balance of the specified channel on the first chain, or of every escrow account if no channel is specified"
+	)]
+	QueryEscrow(Cmd),
+	#[clap(
+		name = "report",
+		about = "Reports the gas used and fees paid per chain and channel by the running relayer"
+	)]
+	Report(Cmd),
+	#[clap(
+		name = "replay",
+		about = "Replays a recorded event log through the relay pipeline without submitting transactions"
+	)]
+	Replay(Cmd),
+	#[clap(
+		name = "recover-client",
+		about = "Creates substitute clients for expired or frozen clients and prints the governance proposals recovering them"
+	)]
+	RecoverClient(Cmd),
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -62,12 +106,21 @@ pub struct Cmd {
 	#[clap(long)]
 	#[clap(long)]
 	delay_period: Option<u32>,
+	/// Channel id for escrow queries, every escrow account is queried if not set
+	#[clap(long)]
+	channel_id: Option<String>,
 	/// Channel order
 	#[clap(long)]
 	order: Option<String>,
 	/// Channel version
 	#[clap(long)]
 	version: Option<String>,
+	/// Sign transactions and log them instead of submitting them
+	#[clap(long)]
+	dry_run: bool,
+	/// Event log to replay
+	#[clap(long)]
+	event_log: Option<String>,
 }
 
 impl Cmd {
@@ -75,8 +128,25 @@ impl Cmd {
 	/// Run the command
 	pub async fn run(&self) -> Result<()> {
 		let path: PathBuf = self.config.parse()?;
-		let file_content = tokio::fs::read_to_string(path).await?;
-		let config: Config = toml::from_str(&file_content)?;
+		let file_content = tokio::fs::read_to_string(&path).await?;
+		let mut config: Config = toml::from_str(&file_content)?;
+		config.chain_a.set_dry_run(self.dry_run);
+		config.chain_b.set_dry_run(self.dry_run);
+		let alerts = config
+			.core
+			.alert_webhooks
+			.clone()
+			.map(|webhooks| Alerts::new(Arc::new(webhooks)))
+			.unwrap_or_default();
+		config.chain_a.set_alerts(alerts.clone());
+		config.chain_b.set_alerts(alerts.clone());
+		if self.dry_run {
+			log::info!("Dry run, skipping the reconciliation of the bootstrap config");
+		} else {
+			reconcile(&mut config).await?;
+		}
+		let (min_balance_a, min_balance_b) =
+			(config.chain_a.min_balance(), config.chain_b.min_balance());
 		let any_chain_a = config.chain_a.into_client().await?;
 		let any_chain_b = config.chain_b.into_client().await?;
 
@@ -84,6 +154,48 @@ impl Cmd {
 			Registry::new_custom(None, None).expect("this can only fail if the prefix is empty");
 		let metrics_a = Metrics::register(any_chain_a.name(), &registry)?;
 		let metrics_b = Metrics::register(any_chain_b.name(), &registry)?;
+		let features = build_info::features().join(",");
+		log::info!("Starting hyperspace {} with features [{}]", LONG_VERSION, features);
+		register_build_info(&registry, build_info::VERSION, build_info::GIT_HASH, &features)?;
+		let balance_check_interval = config
+			.core
+			.balance_check_interval_secs
+			.map(Duration::from_secs)
+			.unwrap_or(DEFAULT_BALANCE_CHECK_INTERVAL);
+		// watch-only clients have no account to fund
+		if any_chain_a.signer().is_some() {
+			tokio::spawn(monitor_balance(
+				any_chain_a.clone(),
+				min_balance_a,
+				balance_check_interval,
+				Some(metrics_a.clone()),
+				alerts.clone(),
+			));
+		}
+		if any_chain_b.signer().is_some() {
+			tokio::spawn(monitor_balance(
+				any_chain_b.clone(),
+				min_balance_b,
+				balance_check_interval,
+				Some(metrics_b.clone()),
+				alerts.clone(),
+			));
+		}
+		let stuck_packet_age = config
+			.core
+			.stuck_packet_age_secs
+			.map(Duration::from_secs)
+			.unwrap_or(DEFAULT_STUCK_PACKET_AGE);
+		tokio::spawn(monitor_stuck_packets(
+			any_chain_a.clone(),
+			stuck_packet_age,
+			Some(metrics_a.clone()),
+		));
+		tokio::spawn(monitor_stuck_packets(
+			any_chain_b.clone(),
+			stuck_packet_age,
+			Some(metrics_b.clone()),
+		));
 		let mut metrics_handler_a = MetricsHandler::new(registry.clone(), metrics_a);
 		let mut metrics_handler_b = MetricsHandler::new(registry.clone(), metrics_b);
 		metrics_handler_a.link_with_counterparty(&mut metrics_handler_b);
@@ -91,15 +203,37 @@ impl Cmd {
 		if let Some(addr) = config.core.prometheus_endpoint.map(|s| s.parse().ok()).flatten() {
 			tokio::spawn(init_prometheus(addr, registry.clone()));
 		}
+		tokio::spawn(rotate_keys_on_hangup(path, any_chain_a.clone(), any_chain_b.clone()));
 
-		relay(any_chain_a, any_chain_b, Some(metrics_handler_a), Some(metrics_handler_b)).await
+		let journal = Journal {
+			audit_log: config.core.audit_log.map(|path| {
+				AuditLog::new(
+					path,
+					config.core.audit_log_max_bytes.unwrap_or(DEFAULT_AUDIT_LOG_MAX_BYTES),
+				)
+			}),
+			event_log: config.core.event_log.map(EventLog::new),
+			alerts,
+			..Default::default()
+		};
+		relay_in_direction(
+			any_chain_a,
+			any_chain_b,
+			Some(metrics_handler_a),
+			Some(metrics_handler_b),
+			journal,
+			config.core.relay_direction.unwrap_or_default(),
+		)
+		.await
 	}
 
 	/// Run fisherman
 	pub async fn fish(&self) -> Result<()> {
 		let path: PathBuf = self.config.parse()?;
 		let file_content = tokio::fs::read_to_string(path).await?;
-		let config: Config = toml::from_str(&file_content)?;
+		let mut config: Config = toml::from_str(&file_content)?;
+		config.chain_a.set_dry_run(self.dry_run);
+		config.chain_b.set_dry_run(self.dry_run);
 		let any_chain_a = config.chain_a.into_client().await?;
 		let any_chain_b = config.chain_b.into_client().await?;
 
@@ -109,12 +243,17 @@ impl Cmd {
 	pub async fn create_clients(&self) -> Result<()> {
 		let path: PathBuf = self.config.parse()?;
 		let file_content = tokio::fs::read_to_string(path).await?;
-		let config: Config = toml::from_str(&file_content)?;
+		let mut config: Config = toml::from_str(&file_content)?;
+		config.chain_a.set_dry_run(self.dry_run);
+		config.chain_b.set_dry_run(self.dry_run);
 		let any_chain_a = config.chain_a.into_client().await?;
 		let any_chain_b = config.chain_b.into_client().await?;
 
 		let (client_id_a_on_b, client_id_b_on_a) =
-			create_clients(&any_chain_a, &any_chain_b).await?;
+			match stop_on_dry_run(create_clients(&any_chain_a, &any_chain_b).await)? {
+				Some(client_ids) => client_ids,
+				None => return Ok(()),
+			};
 		log::info!(
 			"ClientId for Chain {} on Chain {}: {}",
 			any_chain_b.name(),
@@ -137,7 +276,9 @@ impl Cmd {
 		let delay = Duration::from_secs(delay.into());
 		let path: PathBuf = self.config.parse()?;
 		let file_content = tokio::fs::read_to_string(path).await?;
-		let config: Config = toml::from_str(&file_content)?;
+		let mut config: Config = toml::from_str(&file_content)?;
+		config.chain_a.set_dry_run(self.dry_run);
+		config.chain_b.set_dry_run(self.dry_run);
 		let any_chain_a = config.chain_a.into_client().await?;
 		let any_chain_b = config.chain_b.into_client().await?;
 
@@ -148,7 +289,13 @@ impl Cmd {
 		});
 
 		let (connection_id_a, connection_id_b) =
-			create_connection(&any_chain_a, &any_chain_b, delay).await?;
+			match stop_on_dry_run(create_connection(&any_chain_a, &any_chain_b, delay).await)? {
+				Some(connection_ids) => connection_ids,
+				None => {
+					handle.abort();
+					return Ok(())
+				},
+			};
 		log::info!("ConnectionId on Chain {}: {}", any_chain_a.name(), connection_id_a);
 		log::info!("ConnectionId on Chain {}: {}", any_chain_b.name(), connection_id_b);
 		handle.abort();
@@ -171,7 +318,9 @@ impl Cmd {
 		let order = self.order.as_ref().expect("order must be specified when creating a channel, expected one of 'ordered' or 'unordered'").as_str();
 		let path: PathBuf = self.config.parse()?;
 		let file_content = tokio::fs::read_to_string(path).await?;
-		let config: Config = toml::from_str(&file_content)?;
+		let mut config: Config = toml::from_str(&file_content)?;
+		config.chain_a.set_dry_run(self.dry_run);
+		config.chain_b.set_dry_run(self.dry_run);
 		let any_chain_a = config.chain_a.into_client().await?;
 		let any_chain_b = config.chain_b.into_client().await?;
 
@@ -182,7 +331,7 @@ impl Cmd {
 		});
 
 		let order = Order::from_str(order).expect("Expected one of 'ordered' or 'unordered'");
-		let (channel_id_a, channel_id_b) = create_channel(
+		let channel_ids = create_channel(
 			&any_chain_a,
 			&any_chain_b,
 			any_chain_a.connection_id(),
@@ -190,10 +339,168 @@ impl Cmd {
 			version,
 			order,
 		)
-		.await?;
+		.await;
+		let (channel_id_a, channel_id_b) = match stop_on_dry_run(channel_ids)? {
+			Some(channel_ids) => channel_ids,
+			None => {
+				handle.abort();
+				return Ok(())
+			},
+		};
 		log::info!("ChannelId on Chain {}: {}", any_chain_a.name(), channel_id_a);
 		log::info!("ChannelId on Chain {}: {}", any_chain_b.name(), channel_id_b);
 		handle.abort();
 		Ok(())
 	}
+	pub async fn query_escrow(&self) -> Result<()> {
+		let path: PathBuf = self.config.parse()?;
+		let file_content = tokio::fs::read_to_string(path).await?;
+		let config: Config = toml::from_str(&file_content)?;
+		let any_chain_a = config.chain_a.into_client().await?;
+
+		let channel_id = match self.channel_id.as_ref() {
+			Some(channel_id) => ChannelId::from_str(channel_id).expect("Channel id was invalid"),
+			None => {
+				for (account, balance) in any_chain_a.query_escrow_balances().await? {
+					log::info!(
+						"Escrow balance on Chain {} in {account}: {} {}",
+						any_chain_a.name(),
+						balance.amount,
+						balance.denom
+					);
+				}
+				return Ok(())
+			},
+		};
+		let port_id = PortId::from_str(
+			self.port_id
+				.as_ref()
+				.expect("port_id must be specified when querying an escrow"),
+		)
+		.expect("Port id was invalid");
+		let account = any_chain_a.escrow_account(&port_id, channel_id)?;
+		let balance = any_chain_a.query_escrow_balance(&port_id, channel_id).await?;
+		log::info!(
+			"Escrow balance of {port_id}/{channel_id} on Chain {} in {account}: {} {}",
+			any_chain_a.name(),
+			balance.amount,
+			balance.denom
+		);
+		Ok(())
+	}
+
+	/// Prints the gas used and fees paid per chain and channel since the relayer using the same
+	/// config was started, as exported on its prometheus endpoint.
+	pub async fn report(&self) -> Result<()> {
+		let path: PathBuf = self.config.parse()?;
+		let file_content = tokio::fs::read_to_string(path).await?;
+		let config: Config = toml::from_str(&file_content)?;
+		let addr =
+			config.core.prometheus_endpoint.and_then(|s| s.parse().ok()).ok_or_else(|| {
+				anyhow!("A valid prometheus_endpoint is required to report costs")
+			})?;
+
+		let metrics = scrape(addr).await?;
+		for line in metrics.lines().filter(|line| {
+			!line.starts_with('#') &&
+				(line.contains("_gas_used_by_channel") || line.contains("_fees_paid_by_channel"))
+		}) {
+			log::info!("{}", line);
+		}
+		Ok(())
+	}
+
+	/// Feeds the events recorded in the event log through the relay pipeline. Both chains are
+	/// switched to dry-run mode, so the resulting transactions are logged instead of submitted.
+	pub async fn replay(&self) -> Result<()> {
+		let event_log: PathBuf = self
+			.event_log
+			.as_ref()
+			.ok_or_else(|| anyhow!("event_log must be specified when replaying"))?
+			.parse()?;
+		let path: PathBuf = self.config.parse()?;
+		let file_content = tokio::fs::read_to_string(path).await?;
+		let mut config: Config = toml::from_str(&file_content)?;
+		config.chain_a.set_dry_run(true);
+		config.chain_b.set_dry_run(true);
+		let mut any_chain_a = config.chain_a.into_client().await?;
+		let mut any_chain_b = config.chain_b.into_client().await?;
+
+		let event_log = EventLog::new(event_log);
+		let records = tokio::task::spawn_blocking(move || event_log.records()).await??;
+		replay(records, &mut any_chain_a, &mut any_chain_b).await
+	}
+
+	/// Creates a substitute for every expired or frozen client between both chains and prints the
+	/// `ClientUpdateProposal` that recovers it, to be submitted through governance. The relayer
+	/// resumes relaying over the client once the proposal has been enacted.
+	pub async fn recover_client(&self) -> Result<()> {
+		let path: PathBuf = self.config.parse()?;
+		let file_content = tokio::fs::read_to_string(path).await?;
+		let mut config: Config = toml::from_str(&file_content)?;
+		config.chain_a.set_dry_run(self.dry_run);
+		config.chain_b.set_dry_run(self.dry_run);
+		let any_chain_a = config.chain_a.into_client().await?;
+		let any_chain_b = config.chain_b.into_client().await?;
+
+		for (source, sink) in [(&any_chain_a, &any_chain_b), (&any_chain_b, &any_chain_a)] {
+			let err = match check_client_status(source, sink, &Alerts::default()).await {
+				Ok(()) => continue,
+				Err(err) => err,
+			};
+			match err.downcast_ref::<PrimitivesError>() {
+				Some(
+					PrimitivesError::ClientExpired { .. } | PrimitivesError::ClientFrozen { .. },
+				) => {},
+				_ => return Err(err),
+			}
+			log::warn!("{}", err);
+			let substitute_client_id =
+				match stop_on_dry_run(create_substitute_client(source, sink).await)? {
+					Some(client_id) => client_id,
+					None => return Ok(()),
+				};
+			let proposal =
+				client_update_proposal(sink.name(), &source.client_id(), &substitute_client_id);
+			log::info!(
+				"Submit this ClientUpdateProposal on {}: {}",
+				sink.name(),
+				serde_json::to_string_pretty(&proposal)?
+			);
+		}
+		Ok(())
+	}
+}
+
+/// Returns `None` if `result` failed only because the transaction wasn't submitted in dry run
+/// mode, as the steps after it depend on its outcome.
+fn stop_on_dry_run<T>(result: Result<T>) -> Result<Option<T>> {
+	match result {
+		Ok(value) => Ok(Some(value)),
+		Err(err) if PrimitivesError::is_dry_run(&err) => {
+			log::info!("{}, stopping as the next steps depend on its result", err);
+			Ok(None)
+		},
+		Err(err) => Err(err),
+	}
+}
+
+/// Rotates the signing keys of both chains to the ones in the config file whenever the relayer
+/// receives SIGHUP, so keys can be replaced without restarting it.
+async fn rotate_keys_on_hangup(path: PathBuf, chain_a: AnyChain, chain_b: AnyChain) -> Result<()> {
+	let mut hangups = signal(SignalKind::hangup())?;
+	while hangups.recv().await.is_some() {
+		let rotated = async {
+			let file_content = tokio::fs::read_to_string(&path).await?;
+			let config: Config = toml::from_str(&file_content)?;
+			chain_a.rotate_key(&config.chain_a).await?;
+			chain_b.rotate_key(&config.chain_b).await?;
+			Ok::<_, anyhow::Error>(())
+		}
+		.await;
+		if let Err(e) = rotated {
+			log::error!("Failed to rotate signing keys: {:?}", e);
+		}
+	}
+	Ok(())
 }
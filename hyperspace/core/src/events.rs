@@ -12,7 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::packets::query_ready_and_timed_out_packets;
+use crate::packets::{
+	connection_delay::DelaySchedule,
+	query_ready_and_timed_out_packets,
+	utils::{get_key_path, verify_proof_locally, KeyPathType},
+};
 #[cfg(feature = "testing")]
 use crate::send_packet_relay::packet_relay_status;
 use codec::Encode;
@@ -20,7 +24,7 @@ use ibc::{
 	core::{
 		ics02_client::client_state::ClientState as ClientStateT,
 		ics03_connection::{
-			connection::{ConnectionEnd, Counterparty},
+			connection::{ConnectionEnd, Counterparty, State as ConnectionState},
 			handler::verify::ConsensusProofwithHostConsensusStateProof,
 			msgs::{
 				conn_open_ack::MsgConnectionOpenAck, conn_open_confirm::MsgConnectionOpenConfirm,
@@ -28,7 +32,8 @@ use ibc::{
 			},
 		},
 		ics04_channel::{
-			channel::{ChannelEnd, Counterparty as ChannelCounterparty},
+			channel::{ChannelEnd, Counterparty as ChannelCounterparty, State as ChannelState},
+			commitment::compute_packet_commitment,
 			msgs::{
 				acknowledgement::MsgAcknowledgement, chan_close_confirm::MsgChannelCloseConfirm,
 				chan_open_ack::MsgChannelOpenAck, chan_open_confirm::MsgChannelOpenConfirm,
@@ -36,6 +41,7 @@ use ibc::{
 			},
 		},
 		ics23_commitment::commitment::{CommitmentPrefix, CommitmentProofBytes},
+		ics24_host::identifier::{ChannelId, ConnectionId, PortId},
 	},
 	events::{IbcEvent, IbcEventType},
 	proofs::{ConsensusProof, Proofs},
@@ -44,7 +50,11 @@ use ibc::{
 };
 use ibc_proto::{google::protobuf::Any, ibc::core::client::v1::QueryConsensusStateResponse};
 use pallet_ibc::light_clients::AnyClientState;
-use primitives::{error::Error, mock::LocalClientTypes, Chain};
+use primitives::{
+	error::Error, is_ica_port, is_icq_port, mock::LocalClientTypes,
+	packet_data::describe_packet_data, Chain,
+};
+use sp_core::hashing::sha2_256;
 use tendermint_proto::Protobuf;
 
 /// Connection proof type
@@ -57,18 +67,35 @@ pub struct ConnectionProof {
 /// This parses events coming from a source chain
 /// Returns a tuple of messages, with the first item being packets that are ready to be sent to the
 /// sink chain. And the second item being packet timeouts that should be sent to the source.
+/// With `full_scan`, the whitelisted channels are also scanned for packets that are ready to be
+/// sent or timed out, including the ones whose events were missed or held by a connection delay.
+/// The schedule is replaced with the packets the connection delay still holds back.
 pub async fn parse_events(
 	source: &mut impl Chain,
 	sink: &mut impl Chain,
 	events: Vec<IbcEvent>,
+	full_scan: Option<&mut DelaySchedule>,
 ) -> Result<(Vec<Any>, Vec<Any>), anyhow::Error> {
 	let mut messages = vec![];
+	// packets and acknowledgements delivered by other relayers in the meantime are skipped
+	let (sink_height, sink_timestamp) = sink.latest_height_and_timestamp().await?;
 	// 1. translate events to messages
 	for event in events {
 		match event {
 			IbcEvent::OpenInitConnection(open_init) => {
 				if let Some(connection_id) = open_init.connection_id() {
 					let connection_id = connection_id.clone();
+					let (source_height, ..) = source.latest_height_and_timestamp().await?;
+					if !connection_in_state(
+						source,
+						source_height,
+						&connection_id,
+						ConnectionState::Init,
+					)
+					.await?
+					{
+						continue
+					}
 					// Get connection end with proof
 					let connection_response = source
 						.query_connection_end(open_init.height(), connection_id.clone())
@@ -159,6 +186,18 @@ pub async fn parse_events(
 						})?,
 					)?;
 					let counterparty = connection_end.counterparty();
+					if let Some(sink_connection_id) = counterparty.connection_id() {
+						if !connection_in_state(
+							sink,
+							sink_height,
+							sink_connection_id,
+							ConnectionState::Init,
+						)
+						.await?
+						{
+							continue
+						}
+					}
 
 					let connection_proof =
 						CommitmentProofBytes::try_from(connection_response.proof)?;
@@ -241,6 +280,18 @@ pub async fn parse_events(
 						})?,
 					)?;
 					let counterparty = connection_end.counterparty();
+					if let Some(sink_connection_id) = counterparty.connection_id() {
+						if !connection_in_state(
+							sink,
+							sink_height,
+							sink_connection_id,
+							ConnectionState::TryOpen,
+						)
+						.await?
+						{
+							continue
+						}
+					}
 
 					let connection_proof =
 						CommitmentProofBytes::try_from(connection_response.proof)?;
@@ -271,6 +322,18 @@ pub async fn parse_events(
 			},
 			IbcEvent::OpenInitChannel(open_init) => {
 				if let Some(channel_id) = open_init.channel_id {
+					let (source_height, ..) = source.latest_height_and_timestamp().await?;
+					if !channel_in_state(
+						source,
+						source_height,
+						channel_id,
+						&open_init.port_id,
+						ChannelState::Init,
+					)
+					.await?
+					{
+						continue
+					}
 					let channel_response = source
 						.query_channel_end(
 							open_init.height(),
@@ -334,6 +397,19 @@ pub async fn parse_events(
 						})?)
 						.expect("Channel end decoding should not fail");
 					let counterparty = channel_end.counterparty();
+					if let Some(sink_channel_id) = counterparty.channel_id {
+						if !channel_in_state(
+							sink,
+							sink_height,
+							sink_channel_id,
+							&counterparty.port_id,
+							ChannelState::Init,
+						)
+						.await?
+						{
+							continue
+						}
+					}
 					let channel_proof = CommitmentProofBytes::try_from(channel_response.proof)?;
 
 					let proof_height = channel_response.proof_height.expect(
@@ -376,14 +452,48 @@ pub async fn parse_events(
 					let proof_height =
 						Height::new(proof_height.revision_number, proof_height.revision_height);
 
+					let sink_channel_id =
+						counterparty.channel_id.expect("Expect channel id to be set");
 					let msg = MsgChannelOpenConfirm {
 						port_id: counterparty.port_id.clone(),
 						proofs: Proofs::new(channel_proof, None, None, None, proof_height)?,
-						channel_id: counterparty.channel_id.expect("Expect channel id to be set"),
+						channel_id: sink_channel_id,
 
 						signer: sink.account_id(),
 					};
 
+					// Interchain account and query channels are opened by applications at runtime,
+					// and interchain account channels are reopened under a new id after a timeout
+					// closes them, so they can't be whitelisted in the config.
+					if [&open_ack.port_id, &counterparty.port_id]
+						.into_iter()
+						.any(|port_id| is_ica_port(port_id) || is_icq_port(port_id))
+					{
+						log::info!(
+							"Whitelisting interchain account or query channel {}/{}",
+							channel_id,
+							open_ack.port_id
+						);
+						source.add_channel_to_whitelist((channel_id, open_ack.port_id.clone()));
+						sink.add_channel_to_whitelist((
+							sink_channel_id,
+							counterparty.port_id.clone(),
+						));
+					}
+					// checked after whitelisting, which is needed even if another relayer has
+					// already confirmed the channel
+					if !channel_in_state(
+						sink,
+						sink_height,
+						sink_channel_id,
+						&counterparty.port_id,
+						ChannelState::TryOpen,
+					)
+					.await?
+					{
+						continue
+					}
+
 					let value = msg.encode_vec();
 					let msg = Any { value, type_url: msg.type_url() };
 					messages.push(msg)
@@ -401,6 +511,19 @@ pub async fn parse_events(
 						))
 					})?)?;
 				let counterparty = channel_end.counterparty();
+				if let Some(sink_channel_id) = counterparty.channel_id {
+					if !channel_in_state(
+						sink,
+						sink_height,
+						sink_channel_id,
+						&counterparty.port_id,
+						ChannelState::Open,
+					)
+					.await?
+					{
+						continue
+					}
+				}
 				let channel_proof = CommitmentProofBytes::try_from(channel_response.proof)?;
 
 				let proof_height =
@@ -431,6 +554,27 @@ pub async fn parse_events(
 				// 3. otherwise skip.
 				let port_id = send_packet.packet.source_port.clone();
 				let channel_id = send_packet.packet.source_channel.clone();
+				if !source.relay_mode(channel_id, &port_id).relays_packets() {
+					continue
+				}
+				if let Some(reason) = source.packet_filter().denial_reason(&send_packet.packet) {
+					log::warn!(
+						"Skipping packet {port_id}/{channel_id}/{} from {}: {reason}",
+						send_packet.packet.sequence,
+						source.name()
+					);
+					continue
+				}
+				// expired packets can't be received, they are timed out on the source once its
+				// client of the sink has caught up with the timeout
+				if send_packet.packet.timed_out(&sink_timestamp, sink_height) {
+					log::info!(
+						"Packet {port_id}/{channel_id}/{} has timed out on {}, skipping delivery",
+						send_packet.packet.sequence,
+						sink.name()
+					);
+					continue
+				}
 				let channel_response = source
 					.query_channel_end(send_packet.height, channel_id, port_id.clone())
 					.await?;
@@ -457,6 +601,21 @@ pub async fn parse_events(
 				}
 				let seq = u64::from(send_packet.packet.sequence);
 				let packet = send_packet.packet;
+				let unreceived = sink
+					.query_unreceived_packets(
+						sink_height,
+						packet.destination_channel,
+						packet.destination_port.clone(),
+						vec![seq],
+					)
+					.await?;
+				if unreceived.is_empty() {
+					log::debug!(
+						"Packet {port_id}/{channel_id}/{seq} was already received on {}",
+						sink.name()
+					);
+					continue
+				}
 				let packet_commitment_response = source
 					.query_packet_commitment(send_packet.height, &port_id, &channel_id, seq)
 					.await?;
@@ -468,8 +627,33 @@ pub async fn parse_events(
 					.expect("Proof height should be present");
 				let proof_height =
 					Height::new(proof_height.revision_number, proof_height.revision_height);
+				let path = get_key_path(KeyPathType::CommitmentPath, &packet);
+				let commitment = compute_packet_commitment(
+					packet.data.clone(),
+					packet.timeout_height,
+					packet.timeout_timestamp,
+					|value| sha2_256(&value).to_vec(),
+				)
+				.into_vec();
+				if !verify_proof_locally(
+					source,
+					sink,
+					proof_height,
+					path,
+					Some(commitment),
+					&commitment_proof,
+				)
+				.await
+				{
+					continue
+				}
+				log::debug!(
+					"Relaying packet {port_id}/{channel_id}/{seq} to {}: {}",
+					sink.name(),
+					describe_packet_data(&packet)
+				);
 				let msg = MsgRecvPacket {
-					packet: packet.clone(),
+					packet,
 					proofs: Proofs::new(commitment_proof, None, None, None, proof_height)?,
 					signer: sink.account_id(),
 				};
@@ -479,6 +663,15 @@ pub async fn parse_events(
 				messages.push(msg);
 			},
 			IbcEvent::WriteAcknowledgement(write_ack) => {
+				if !source
+					.relay_mode(
+						write_ack.packet.destination_channel,
+						&write_ack.packet.destination_port,
+					)
+					.relays_acks()
+				{
+					continue
+				}
 				let port_id = &write_ack.packet.source_port.clone();
 				let channel_id = &write_ack.packet.source_channel.clone();
 				let channel_response = source
@@ -507,6 +700,21 @@ pub async fn parse_events(
 				}
 				let seq = u64::from(write_ack.packet.sequence);
 				let packet = write_ack.packet;
+				let unreceived = sink
+					.query_unreceived_acknowledgements(
+						sink_height,
+						packet.source_channel,
+						packet.source_port.clone(),
+						vec![seq],
+					)
+					.await?;
+				if unreceived.is_empty() {
+					log::debug!(
+						"Acknowledgement of {port_id}/{channel_id}/{seq} was already received on {}",
+						sink.name()
+					);
+					continue
+				}
 				let packet_acknowledgement_response = source
 					.query_packet_acknowledgement(write_ack.height, &port_id, &channel_id, seq)
 					.await?;
@@ -519,6 +727,20 @@ pub async fn parse_events(
 					.expect("Proof height should be present");
 				let proof_height =
 					Height::new(proof_height.revision_number, proof_height.revision_height);
+				let path = get_key_path(KeyPathType::AcksPath, &packet);
+				let commitment = sha2_256(&acknowledgement).to_vec();
+				if !verify_proof_locally(
+					source,
+					sink,
+					proof_height,
+					path,
+					Some(commitment),
+					&commitment_proof,
+				)
+				.await
+				{
+					continue
+				}
 				let msg = MsgAcknowledgement {
 					packet,
 					acknowledgement: acknowledgement.into(),
@@ -535,14 +757,64 @@ pub async fn parse_events(
 		}
 	}
 
+	let schedule = match full_scan {
+		Some(schedule) => schedule,
+		None => return Ok((messages, vec![])),
+	};
+
 	// 2. query packets that can now be sent, at this sink height because of connection delay.
 	let (ready_packets, timed_out_packets) =
-		query_ready_and_timed_out_packets(source, sink).await?;
+		query_ready_and_timed_out_packets(source, sink, schedule).await?;
 	messages.extend(ready_packets);
 
 	Ok((messages, timed_out_packets))
 }
 
+/// Checks that a connection is still in the state the next handshake step starts from, so steps
+/// another relayer has already completed are skipped.
+async fn connection_in_state(
+	chain: &impl Chain,
+	at: Height,
+	connection_id: &ConnectionId,
+	expected: ConnectionState,
+) -> Result<bool, anyhow::Error> {
+	let response = chain.query_connection_end(at, connection_id.clone()).await?;
+	let connection_end = ConnectionEnd::try_from(response.connection.ok_or_else(|| {
+		Error::Custom(format!("ConnectionEnd not found for {:?}", connection_id))
+	})?)?;
+	let state = *connection_end.state();
+	if state != expected {
+		log::info!(
+			"Connection {connection_id} on {} is {state:?} instead of {expected:?}, skipping handshake step",
+			chain.name()
+		);
+	}
+	Ok(state == expected)
+}
+
+/// Checks that a channel is still in the state the next handshake step starts from, so steps
+/// another relayer has already completed are skipped.
+async fn channel_in_state(
+	chain: &impl Chain,
+	at: Height,
+	channel_id: ChannelId,
+	port_id: &PortId,
+	expected: ChannelState,
+) -> Result<bool, anyhow::Error> {
+	let response = chain.query_channel_end(at, channel_id, port_id.clone()).await?;
+	let channel_end = ChannelEnd::try_from(response.channel.ok_or_else(|| {
+		Error::Custom(format!("ChannelEnd not found for {:?}/{:?}", channel_id, port_id))
+	})?)?;
+	let state = *channel_end.state();
+	if state != expected {
+		log::info!(
+			"Channel {port_id}/{channel_id} on {} is {state:?} instead of {expected:?}, skipping handshake step",
+			chain.name()
+		);
+	}
+	Ok(state == expected)
+}
+
 /// Fetch the connection proof for the sink chain.
 async fn query_consensus_proof(
 	sink: &impl Chain,
@@ -0,0 +1,144 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resumption of connection and channel handshakes left half-open, e.g. by a relayer restart
+//! between two handshake steps.
+
+use anyhow::anyhow;
+use ibc::{
+	core::{
+		ics02_client::client_state::ClientState as ClientStateT,
+		ics03_connection::{
+			connection::{IdentifiedConnectionEnd, State as ConnectionState},
+			events::{self as connection_events, Attributes},
+		},
+		ics04_channel::{
+			channel::{ChannelEnd, State as ChannelState},
+			events as channel_events,
+		},
+	},
+	events::IbcEvent,
+};
+use pallet_ibc::light_clients::AnyClientState;
+use primitives::Chain;
+
+use crate::{events::parse_events, journal::Journal, queue};
+
+/// Returns the events of the handshake steps completed on `source` whose next step, on `sink`,
+/// may not have been taken: an event for every connection with `sink` and every channel on those
+/// connections that is in the `INIT` or `TRYOPEN` state.
+///
+/// The events are placed at the latest height of the `source` client on `sink`, so the proofs
+/// built from them can be verified without a client update. Handshakes started after that height
+/// are left to the relay loop.
+pub async fn pending_handshake_events(
+	source: &impl Chain,
+	sink: &impl Chain,
+) -> Result<Vec<IbcEvent>, anyhow::Error> {
+	let client_id = source.client_id();
+	let (sink_height, ..) = sink.latest_height_and_timestamp().await?;
+	let client_state = sink
+		.query_client_state(sink_height, client_id.clone())
+		.await?
+		.client_state
+		.ok_or_else(|| anyhow!("Client state for {client_id} not found on {}", sink.name()))?;
+	let height = AnyClientState::try_from(client_state)
+		.map_err(|_| anyhow!("Failed to decode client state for {client_id}"))?
+		.latest_height();
+
+	let mut events = vec![];
+	let mut connection_ids = vec![];
+	let connections = source
+		.query_connection_using_client(height.revision_height as u32, sink.client_id().to_string())
+		.await?;
+	for connection in connections {
+		let IdentifiedConnectionEnd { connection_id, connection_end } =
+			IdentifiedConnectionEnd::try_from(connection)?;
+		let attributes = Attributes {
+			height,
+			connection_id: Some(connection_id.clone()),
+			client_id: connection_end.client_id().clone(),
+			counterparty_connection_id: connection_end.counterparty().connection_id().cloned(),
+			counterparty_client_id: connection_end.counterparty().client_id().clone(),
+		};
+		match connection_end.state() {
+			ConnectionState::Init => events
+				.push(IbcEvent::OpenInitConnection(connection_events::OpenInit::from(attributes))),
+			ConnectionState::TryOpen => events
+				.push(IbcEvent::OpenTryConnection(connection_events::OpenTry::from(attributes))),
+			_ => {},
+		}
+		connection_ids.push(connection_id);
+	}
+
+	for (channel_id, port_id) in source.query_channels().await? {
+		let channel_end =
+			match source.query_channel_end(height, channel_id, port_id.clone()).await?.channel {
+				Some(channel) => ChannelEnd::try_from(channel)?,
+				// created after the height the client is at
+				None => continue,
+			};
+		let connection_id = match channel_end.connection_hops.first() {
+			Some(connection_id) if connection_ids.contains(connection_id) => connection_id.clone(),
+			_ => continue,
+		};
+		let counterparty = channel_end.counterparty();
+		match channel_end.state {
+			ChannelState::Init =>
+				events.push(IbcEvent::OpenInitChannel(channel_events::OpenInit {
+					height,
+					port_id,
+					channel_id: Some(channel_id),
+					connection_id,
+					counterparty_port_id: counterparty.port_id.clone(),
+					counterparty_channel_id: counterparty.channel_id,
+				})),
+			ChannelState::TryOpen =>
+				events.push(IbcEvent::OpenTryChannel(channel_events::OpenTry {
+					height,
+					port_id,
+					channel_id: Some(channel_id),
+					connection_id,
+					counterparty_port_id: counterparty.port_id.clone(),
+					counterparty_channel_id: counterparty.channel_id,
+				})),
+			_ => {},
+		}
+	}
+	Ok(events)
+}
+
+/// Takes the next step of the handshakes left half-open on `source`, submitting it to `sink`.
+/// Steps the counterparty has already taken are skipped by [`parse_events`].
+pub async fn resume_handshakes(
+	source: &mut impl Chain,
+	sink: &mut impl Chain,
+	journal: &Journal,
+) -> Result<(), anyhow::Error> {
+	let events = pending_handshake_events(source, sink).await?;
+	if events.is_empty() {
+		return Ok(())
+	}
+	log::info!(
+		"Resuming {} half-open handshakes from {} to {}",
+		events.len(),
+		source.name(),
+		sink.name()
+	);
+	let (messages, _) = parse_events(source, sink, events, Some(&mut Default::default())).await?;
+	if !messages.is_empty() {
+		queue::flush_message_batch(messages, None, journal, sink).await?;
+	}
+	Ok(())
+}
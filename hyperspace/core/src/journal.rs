@@ -0,0 +1,31 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use primitives::alert::Alerts;
+
+use crate::{audit::AuditLog, queue::SubmissionFailures, replay::EventLog};
+
+/// Records the relayer keeps of what it relays and the alerts it raises, passed to the relay loop
+/// along with the chains. Nothing is recorded or alerted on by default.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+	/// Log of the messages submitted to either chain
+	pub audit_log: Option<AuditLog>,
+	/// Log of the events either chain was relayed from, see [`crate::replay`]
+	pub event_log: Option<EventLog>,
+	/// Where conditions that need an operator's attention are reported
+	pub alerts: Alerts,
+	/// Consecutive failed submissions to either chain
+	pub submission_failures: SubmissionFailures,
+}
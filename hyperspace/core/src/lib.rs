@@ -14,45 +14,142 @@
 
 #![warn(unused_variables)]
 
-use futures::{future::ready, StreamExt};
+use futures::{future::ready, Stream, StreamExt};
 use primitives::Chain;
+use serde::Deserialize;
+use std::time::Instant;
 
+pub mod audit;
+pub mod balance;
+pub mod bootstrap;
+pub mod build_info;
 pub mod chain;
 pub mod command;
 pub mod events;
+pub mod handshake;
+pub mod journal;
 pub mod logging;
 mod macros;
 pub mod packets;
 pub mod queue;
+pub mod replay;
+pub mod upgrade;
 
 use events::{has_packet_events, parse_events};
 use ibc::events::IbcEvent;
+use journal::Journal;
 use metrics::handler::MetricsHandler;
+use packets::connection_delay::{wait_until, DelaySchedule};
+
+/// Directions in which [`relay_in_direction`] forwards events between its two chains.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayDirection {
+	/// Relay in both directions
+	#[default]
+	Both,
+	/// Only update the client of chain A on chain B and relay the events of chain A
+	AToB,
+	/// Only update the client of chain B on chain A and relay the events of chain B
+	BToA,
+}
+
+impl RelayDirection {
+	/// Whether the events of chain A are relayed to chain B.
+	pub fn from_a(&self) -> bool {
+		matches!(self, RelayDirection::Both | RelayDirection::AToB)
+	}
+
+	/// Whether the events of chain B are relayed to chain A.
+	pub fn from_b(&self) -> bool {
+		matches!(self, RelayDirection::Both | RelayDirection::BToA)
+	}
+}
 
 /// Core relayer loop, waits for new finality events and forwards any new [`ibc::IbcEvents`]
 /// to the counter party chain.
 pub async fn relay<A, B>(
+	chain_a: A,
+	chain_b: B,
+	chain_a_metrics: Option<MetricsHandler>,
+	chain_b_metrics: Option<MetricsHandler>,
+) -> Result<(), anyhow::Error>
+where
+	A: Chain,
+	B: Chain,
+{
+	relay_in_direction(
+		chain_a,
+		chain_b,
+		chain_a_metrics,
+		chain_b_metrics,
+		Journal::default(),
+		RelayDirection::Both,
+	)
+	.await
+}
+
+/// Like [`relay`], but only relays in `direction`. Chains whose events aren't relayed in
+/// `direction` are not followed at all.
+pub async fn relay_in_direction<A, B>(
 	mut chain_a: A,
 	mut chain_b: B,
 	mut chain_a_metrics: Option<MetricsHandler>,
 	mut chain_b_metrics: Option<MetricsHandler>,
+	journal: Journal,
+	direction: RelayDirection,
 ) -> Result<(), anyhow::Error>
 where
 	A: Chain,
 	B: Chain,
 {
-	let (mut chain_a_finality, mut chain_b_finality) =
-		(chain_a.finality_notifications().await, chain_b.finality_notifications().await);
+	let mut chain_a_finality = match direction.from_a() {
+		true => Some(chain_a.finality_notifications().await),
+		false => None,
+	};
+	let mut chain_b_finality = match direction.from_b() {
+		true => Some(chain_b.finality_notifications().await),
+		false => None,
+	};
+	// handshake steps missed while the relayer wasn't running have no events to react to
+	if direction.from_a() {
+		if let Err(e) = handshake::resume_handshakes(&mut chain_a, &mut chain_b, &journal).await {
+			log::error!("Failed to resume half-open handshakes: {:?}", e);
+		}
+	}
+	if direction.from_b() {
+		if let Err(e) = handshake::resume_handshakes(&mut chain_b, &mut chain_a, &journal).await {
+			log::error!("Failed to resume half-open handshakes: {:?}", e);
+		}
+	}
+	// time of the last submission to each chain, for heartbeat client updates
+	let (mut chain_a_last_submission, mut chain_b_last_submission) =
+		(Instant::now(), Instant::now());
+	// height of the last full scan of each chain's channels for packets to relay
+	let (mut chain_a_last_scan, mut chain_b_last_scan) = (None, None);
+	// packets of each chain held back by the connection delay
+	let (mut chain_a_delayed, mut chain_b_delayed) =
+		(DelaySchedule::default(), DelaySchedule::default());
 	// loop forever
 	loop {
+		let (chain_a_ready, chain_b_ready) =
+			(chain_a_delayed.next_ready(), chain_b_delayed.next_ready());
 		tokio::select! {
 			// new finality event from chain A
-			result = chain_a_finality.next() => {
-				process_finality_event!(chain_a, chain_b, chain_a_metrics, result)
+			result = next_finality(&mut chain_a_finality) => {
+				process_finality_event!(chain_a, chain_b, chain_a_metrics, journal, result, chain_b_last_submission, chain_a_last_scan, chain_a_delayed)
 			}
 			// new finality event from chain B
-			result = chain_b_finality.next() => {
-				process_finality_event!(chain_b, chain_a, chain_b_metrics, result)
+			result = next_finality(&mut chain_b_finality) => {
+				process_finality_event!(chain_b, chain_a, chain_b_metrics, journal, result, chain_a_last_submission, chain_b_last_scan, chain_b_delayed)
+			}
+			// connection delay of packets from chain A has passed
+			_ = wait_until(chain_a_ready) => {
+				relay_delayed_packets(&chain_a, &chain_b, chain_a_metrics.as_ref(), &journal, &mut chain_a_delayed).await;
+			}
+			// connection delay of packets from chain B has passed
+			_ = wait_until(chain_b_ready) => {
+				relay_delayed_packets(&chain_b, &chain_a, chain_b_metrics.as_ref(), &journal, &mut chain_b_delayed).await;
 			}
 		}
 	}
@@ -60,6 +157,77 @@ where
 	Ok(())
 }
 
+/// Waits for the next finality notification of a followed chain, forever if `finality` is `None`.
+async fn next_finality<S: Stream + Unpin>(finality: &mut Option<S>) -> Option<S::Item> {
+	match finality {
+		Some(finality) => finality.next().await,
+		None => futures::future::pending().await,
+	}
+}
+
+/// Submits the packets from `source` that the connection delay no longer holds back, as scheduled
+/// by the last scan. Their proofs are at heights the clients already have, so no client update is
+/// needed. Like finality events, nothing is relayed over an inactive client or while an upgrade is
+/// pending.
+async fn relay_delayed_packets<A: Chain, B: Chain>(
+	source: &A,
+	sink: &B,
+	metrics: Option<&MetricsHandler>,
+	journal: &Journal,
+	delayed: &mut DelaySchedule,
+) {
+	let result = async {
+		primitives::check_client_status(source, sink, &journal.alerts).await?;
+		if let Some(plan) = upgrade::pending_upgrade(source, sink).await? {
+			log::debug!(
+				"Not relaying delayed packets from {} until upgrade {} is applied",
+				source.name(),
+				plan.name
+			);
+			// rescheduled by the first full scan after the upgrade
+			delayed.clear();
+			return Ok(())
+		}
+		let (messages, timeouts) =
+			packets::query_delayed_packets(source, sink, delayed, Instant::now()).await?;
+		let timeouts = if timeouts.is_empty() {
+			timeouts
+		} else if let Err(err) =
+			primitives::check_client_status(sink, source, &journal.alerts).await
+		{
+			log::error!("Not submitting delayed timeouts to {}: {:?}", source.name(), err);
+			vec![]
+		} else {
+			packets::utils::retain_anchored_messages(sink, source, timeouts, &Default::default())
+				.await?
+		};
+		if !timeouts.is_empty() {
+			if let Some(metrics) = metrics {
+				metrics.handle_timeouts(timeouts.as_slice()).await;
+			}
+			log::info!("Submitting delayed timeout messages to {}", source.name());
+			queue::flush_message_batch(timeouts, metrics, journal, source).await?;
+		}
+		let messages =
+			packets::utils::retain_anchored_messages(source, sink, messages, &Default::default())
+				.await?;
+		if !messages.is_empty() {
+			if let Some(metrics) = metrics {
+				metrics.handle_messages(messages.as_slice()).await;
+			}
+			log::info!("Submitting delayed packet messages to {}", sink.name());
+			queue::flush_message_batch(messages, metrics, journal, sink).await?;
+		}
+		Ok::<_, anyhow::Error>(())
+	}
+	.await;
+	if let Err(e) = result {
+		// rescheduled by the next full scan
+		delayed.clear();
+		log::error!("Failed to relay delayed packets from {}: {:?}", source.name(), e);
+	}
+}
+
 pub async fn fish<A, B>(chain_a: A, chain_b: B) -> Result<(), anyhow::Error>
 where
 	A: Chain,
@@ -14,14 +14,48 @@
 
 #[macro_export]
 macro_rules! process_finality_event {
-	($source:ident, $sink:ident, $metrics:expr, $result:ident) => {
+	(
+		$source:ident,
+		$sink:ident,
+		$metrics:expr,
+		$journal:ident,
+		$result:ident,
+		$sink_last_submission:ident,
+		$source_last_scan:ident,
+		$source_delayed:ident
+	) => {
 		match $result {
 			// stream closed
 			None => break,
 			Some(finality_event) => {
 				log::info!("=======================================================");
 				log::info!("Received finality notification from {}", $source.name());
-				let (msg_update_client, events, update_type) =
+				let pending_upgrade =
+					match $crate::upgrade::pending_upgrade(&$source, &$sink).await {
+						Ok(pending_upgrade) => pending_upgrade,
+						Err(err) => {
+							log::error!(
+								"Failed to check for a pending upgrade of {} {:?}",
+								$source.name(),
+								err
+							);
+							continue
+						},
+					};
+				// a frozen or expired client rejects every message, so nothing is submitted until
+				// it is recovered
+				if let Err(err) =
+					primitives::check_client_status(&$source, &$sink, &$journal.alerts).await
+				{
+					log::error!(
+						"Not relaying from {} to {}: {:?}",
+						$source.name(),
+						$sink.name(),
+						err
+					);
+					continue
+				}
+				let (msgs_update_client, events, update_type) =
 					match $source.query_latest_ibc_events(finality_event, &$sink).await {
 						Ok(resp) => resp,
 						Err(err) => {
@@ -33,14 +67,129 @@ macro_rules! process_finality_event {
 							continue
 						},
 					};
+				if let Some(plan) = pending_upgrade {
+					// the client is first updated to the halt height, then upgraded
+					let messages = match $crate::upgrade::construct_upgrade_message(
+						&$source, &$sink, &plan,
+					)
+					.await
+					{
+						Ok(Some(message)) => {
+							log::info!(
+								"Upgrading the client of {} on {} for upgrade {}",
+								$source.name(),
+								$sink.name(),
+								plan.name
+							);
+							vec![message]
+						},
+						Ok(None) => {
+							log::warn!(
+								"{} has reached the halt height {} of upgrade {}, pausing relay until the client on {} is upgraded",
+								$source.name(),
+								plan.height,
+								plan.name,
+								$sink.name()
+							);
+							msgs_update_client
+						},
+						Err(err) => {
+							log::error!(
+								"Failed to build the client upgrade for {} {:?}",
+								$source.name(),
+								err
+							);
+							continue
+						},
+					};
+					if let Err(err) =
+						queue::flush_message_batch(messages, $metrics.as_ref(), &$journal, &$sink).await
+					{
+						log::error!(
+							"Failed to submit client upgrade messages to {} {:?}",
+							$sink.name(),
+							err
+						);
+					}
+					continue
+				}
+				if let Some(event_log) = $journal.event_log.as_ref() {
+					event_log.record_events($source.name(), &events).await;
+				}
 				if let Some(metrics) = $metrics.as_mut() {
 					if let Err(e) = metrics.handle_events(events.as_slice()).await {
 						log::error!("Failed to handle metrics for {} {:?}", $source.name(), e);
 					}
 				}
 				let event_types = events.iter().map(|ev| ev.event_type()).collect::<Vec<_>>();
-				let (mut messages, timeouts) =
-					parse_events(&mut $source, &mut $sink, events).await?;
+				// the client update proves every height events were queried at
+				let update_heights =
+					events.iter().map(|ev| ev.height()).collect::<std::collections::BTreeSet<_>>();
+				let full_scan =
+					$crate::packets::full_scan_due(&$source, &mut $source_last_scan)
+						.await
+						.unwrap_or_else(|err| {
+							log::error!(
+								"Failed to check if {} is due a full scan {:?}",
+								$source.name(),
+								err
+							);
+							true
+						});
+				let (messages, timeouts) = parse_events(
+					&mut $source,
+					&mut $sink,
+					events,
+					full_scan.then(|| &mut $source_delayed),
+				)
+				.await?;
+				let mut messages = match $crate::packets::utils::retain_anchored_messages(
+					&$source,
+					&$sink,
+					messages,
+					&update_heights,
+				)
+				.await
+				{
+					Ok(messages) => messages,
+					Err(err) => {
+						log::error!(
+							"Failed to check proof heights of messages to {} {:?}",
+							$sink.name(),
+							err
+						);
+						continue
+					},
+				};
+				let timeouts = if timeouts.is_empty() {
+					timeouts
+				} else if let Err(err) =
+					primitives::check_client_status(&$sink, &$source, &$journal.alerts).await
+				{
+					log::error!(
+						"Not submitting timeouts to {}: {:?}",
+						$source.name(),
+						err
+					);
+					vec![]
+				} else {
+					// timeouts are only built from heights the client on the source already has
+					$crate::packets::utils::retain_anchored_messages(
+						&$sink,
+						&$source,
+						timeouts,
+						&Default::default(),
+					)
+					.await
+					.unwrap_or_else(|err| {
+						log::error!(
+							"Failed to check proof heights of timeouts to {} {:?}",
+							$source.name(),
+							err
+						);
+						vec![]
+					})
+				};
 				if !timeouts.is_empty() {
 					if let Some(metrics) = $metrics.as_ref() {
 						metrics.handle_timeouts(timeouts.as_slice()).await;
@@ -48,8 +197,12 @@ macro_rules! process_finality_event {
 					let type_urls =
 						timeouts.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>();
 					log::info!("Submitting timeout messages to {}: {type_urls:#?}", $source.name());
-					queue::flush_message_batch(timeouts, $metrics.as_ref(), &$source).await?;
+					queue::flush_message_batch(timeouts, $metrics.as_ref(), &$journal, &$source).await?;
 				}
+				let heartbeat_due = $source
+					.heartbeat_interval()
+					.map(|interval| $sink_last_submission.elapsed() >= interval)
+					.unwrap_or(false);
 				// We want to send client update if packet messages exist but where not sent due to
 				// a connection delay even if client update message is optional
 				match (
@@ -57,6 +210,11 @@ macro_rules! process_finality_event {
 					has_packet_events(&event_types),
 					messages.is_empty(),
 				) {
+					(true, false, true) if heartbeat_due => log::info!(
+						"Sending heartbeat client update for {} to {}",
+						$source.name(),
+						$sink.name()
+					),
 					(true, false, true) => {
 						// skip sending ibc messages if no new events
 						log::info!(
@@ -72,15 +230,16 @@ macro_rules! process_finality_event {
 						$source.name()
 					),
 				};
-				// insert client update at first position.
-				messages.insert(0, msg_update_client);
+				// insert client updates at first position.
+				messages.splice(0..0, msgs_update_client);
 				if let Some(metrics) = $metrics.as_ref() {
 					metrics.handle_messages(messages.as_slice()).await;
 				}
 				let type_urls =
 					messages.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>();
 				log::info!("Submitting messages to {}: {type_urls:#?}", $sink.name());
-				queue::flush_message_batch(messages, $metrics.as_ref(), &$sink).await?;
+				queue::flush_message_batch(messages, $metrics.as_ref(), &$journal, &$sink).await?;
+				$sink_last_submission = std::time::Instant::now();
 			},
 		}
 	};
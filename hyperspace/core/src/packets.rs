@@ -15,9 +15,12 @@
 #[cfg(feature = "testing")]
 use crate::send_packet_relay::packet_relay_status;
 
-use crate::packets::utils::{
-	construct_ack_message, construct_recv_message, construct_timeout_message,
-	get_timeout_proof_height, verify_delay_passed, VerifyDelayOn,
+use crate::packets::{
+	connection_delay::{DelaySchedule, DelayedMessage, DelayedPacket},
+	utils::{
+		construct_ack_message, construct_recv_message, construct_timeout_message, delay_remaining,
+		find_retained_proof_height, get_timeout_proof_height, VerifyDelayOn,
+	},
 };
 use ibc::{
 	core::{
@@ -30,19 +33,81 @@ use ibc::{
 use ibc_proto::google::protobuf::Any;
 use pallet_ibc::light_clients::AnyClientState;
 use primitives::{
-	error::Error, find_suitable_proof_height_for_client, packet_info_to_packet,
-	query_undelivered_acks, query_undelivered_sequences, Chain,
+	error::Error, find_suitable_proof_height_for_client, packet_data::describe_packet_data,
+	packet_info_to_packet, query_undelivered_acks, query_undelivered_sequences, Chain,
+};
+use std::{
+	collections::BTreeSet,
+	time::{Duration, Instant},
 };
 
 pub mod connection_delay;
+pub mod stuck;
 pub mod utils;
 
+/// Returns whether the channels of `source` are due a full scan, which is every
+/// [`Chain::full_scan_interval`] blocks after the scan at `last_scan`. The height of a due scan is
+/// recorded in `last_scan`.
+pub async fn full_scan_due(
+	source: &impl Chain,
+	last_scan: &mut Option<Height>,
+) -> Result<bool, anyhow::Error> {
+	let interval = match source.full_scan_interval() {
+		Some(interval) => interval,
+		None => return Ok(true),
+	};
+	let (height, _) = source.latest_height_and_timestamp().await?;
+	let due = match last_scan {
+		Some(last_scan) if last_scan.revision_number == height.revision_number =>
+			height.revision_height >= last_scan.revision_height + interval,
+		_ => true,
+	};
+	if due {
+		*last_scan = Some(height);
+	}
+	Ok(due)
+}
+
 /// Returns a tuple of messages, with the first item being packets that are ready to be sent to the
 /// sink chain. And the second item being packet timeouts that should be sent to the source.
+/// `schedule` is replaced with the packets that are held back by the connection delay.
 pub async fn query_ready_and_timed_out_packets(
 	source: &impl Chain,
 	sink: &impl Chain,
+	schedule: &mut DelaySchedule,
 ) -> Result<(Vec<Any>, Vec<Any>), anyhow::Error> {
+	schedule.clear();
+	query_packets(source, sink, schedule, None).await
+}
+
+/// Like [`query_ready_and_timed_out_packets`], but only queries the packets of `schedule` that are
+/// expected to be ready at `now`. Packets still held back are rescheduled at least a block later,
+/// as their delay is only re-evaluated against new blocks.
+pub async fn query_delayed_packets(
+	source: &impl Chain,
+	sink: &impl Chain,
+	schedule: &mut DelaySchedule,
+	now: Instant,
+) -> Result<(Vec<Any>, Vec<Any>), anyhow::Error> {
+	let ready = schedule.take_ready(now);
+	if ready.is_empty() {
+		return Ok((vec![], vec![]))
+	}
+	query_packets(source, sink, schedule, Some(&ready)).await
+}
+
+/// Queries the packets to relay from `source`, only the `ready` ones if set instead of every
+/// undelivered packet of the whitelisted channels.
+async fn query_packets(
+	source: &impl Chain,
+	sink: &impl Chain,
+	schedule: &mut DelaySchedule,
+	ready: Option<&BTreeSet<DelayedPacket>>,
+) -> Result<(Vec<Any>, Vec<Any>), anyhow::Error> {
+	let min_delay = match ready {
+		Some(_) => sink.expected_block_time().min(source.expected_block_time()),
+		None => Duration::ZERO,
+	};
 	let mut messages = vec![];
 	let mut timeout_messages = vec![];
 	let (source_height, source_timestamp) = source.latest_height_and_timestamp().await?;
@@ -50,6 +115,27 @@ pub async fn query_ready_and_timed_out_packets(
 	let channel_whitelist = source.channel_whitelist();
 
 	for (channel_id, port_id) in channel_whitelist {
+		// sequences of the ready packets of this channel delivered by one of `delayed_messages`
+		let ready_sequences = |delayed_messages: &[DelayedMessage]| {
+			ready.map(|ready| {
+				ready
+					.iter()
+					.filter(|(ready_port_id, ready_channel_id, _, message)| {
+						*ready_port_id == port_id &&
+							*ready_channel_id == channel_id &&
+							delayed_messages.contains(message)
+					})
+					.map(|(.., sequence, _)| u64::from(*sequence))
+					.collect::<Vec<_>>()
+			})
+		};
+		if let Some(ready) = ready {
+			if !ready.iter().any(|(ready_port_id, ready_channel_id, ..)| {
+				*ready_port_id == port_id && *ready_channel_id == channel_id
+			}) {
+				continue
+			}
+		}
 		let source_channel_response =
 			source.query_channel_end(source_height, channel_id, port_id.clone()).await?;
 		let source_channel_end =
@@ -143,17 +229,26 @@ pub async fn query_ready_and_timed_out_packets(
 		})?;
 		let latest_sink_height_on_source = sink_client_state_on_source.latest_height();
 		let latest_source_height_on_sink = source_client_state_on_sink.latest_height();
+		let relay_mode = source.relay_mode(channel_id, &port_id);
 
 		// query packets that are waiting for connection delay.
-		let seqs = query_undelivered_sequences(
-			source_height,
-			sink_height,
-			channel_id,
-			port_id.clone(),
-			source,
-			sink,
-		)
-		.await?;
+		let seqs = if !relay_mode.relays_packets() {
+			vec![]
+		} else if let Some(seqs) =
+			ready_sequences(&[DelayedMessage::RecvPacket, DelayedMessage::Timeout])
+		{
+			seqs
+		} else {
+			query_undelivered_sequences(
+				source_height,
+				sink_height,
+				channel_id,
+				port_id.clone(),
+				source,
+				sink,
+			)
+			.await?
+		};
 
 		let send_packets = source.query_send_packets(channel_id, port_id.clone(), seqs).await?;
 		for send_packet in send_packets {
@@ -181,7 +276,7 @@ pub async fn query_ready_and_timed_out_packets(
 				};
 
 				// given this maximum height, has the connection delay been satisfied?
-				if !verify_delay_passed(
+				match delay_remaining(
 					source,
 					sink,
 					source_timestamp,
@@ -194,7 +289,18 @@ pub async fn query_ready_and_timed_out_packets(
 				)
 				.await?
 				{
-					continue
+					Some(delay) if delay.is_zero() => {},
+					Some(delay) => {
+						schedule.schedule(
+							port_id.clone(),
+							channel_id,
+							packet.sequence,
+							DelayedMessage::Timeout,
+							delay.max(min_delay),
+						);
+						continue
+					},
+					None => continue,
 				}
 
 				// lets construct the timeout message to be sent to the source
@@ -207,7 +313,9 @@ pub async fn query_ready_and_timed_out_packets(
 					proof_height,
 				)
 				.await?;
-				timeout_messages.push(msg);
+				if let Some(msg) = msg {
+					timeout_messages.push(msg);
+				}
 				continue
 			}
 
@@ -224,12 +332,27 @@ pub async fn query_ready_and_timed_out_packets(
 				continue
 			}
 
+			if let Some(reason) = source.packet_filter().denial_reason(&packet) {
+				log::debug!(
+					"Skipping packet {port_id}/{channel_id}/{} from {}: {reason}",
+					packet.sequence,
+					source.name()
+				);
+				continue
+			}
+
 			// Check if packet is ready to be sent to sink
 			// If sink does not have a client height that is equal to or greater than the packet
 			// creation height, we can't send it yet, packet_info.height should represent the packet
 			// creation height on source chain
 			if send_packet.height > latest_source_height_on_sink.revision_height {
 				// Sink does not have client update required to prove recv packet message
+				log::trace!(
+					"Packet {port_id}/{channel_id}/{} is waiting for a client update on {}: {}",
+					packet.sequence,
+					sink.name(),
+					describe_packet_data(&packet)
+				);
 				continue
 			}
 
@@ -248,7 +371,22 @@ pub async fn query_ready_and_timed_out_packets(
 				continue
 			};
 
-			if !verify_delay_passed(
+			// re-anchor the proof if the sink has pruned the consensus state at that height
+			let proof_height = if let Some(proof_height) = find_retained_proof_height(
+				sink,
+				sink_height,
+				source.client_id(),
+				proof_height,
+				latest_source_height_on_sink,
+			)
+			.await?
+			{
+				proof_height
+			} else {
+				continue
+			};
+
+			match delay_remaining(
 				source,
 				sink,
 				source_timestamp,
@@ -261,28 +399,48 @@ pub async fn query_ready_and_timed_out_packets(
 			)
 			.await?
 			{
-				continue
+				Some(delay) if delay.is_zero() => {},
+				Some(delay) => {
+					schedule.schedule(
+						port_id.clone(),
+						channel_id,
+						packet.sequence,
+						DelayedMessage::RecvPacket,
+						delay.max(min_delay),
+					);
+					continue
+				},
+				None => continue,
 			}
 
-			let msg = construct_recv_message(source, sink, packet, proof_height).await?;
-			messages.push(msg)
+			if let Some(msg) = construct_recv_message(source, sink, packet, proof_height).await? {
+				messages.push(msg)
+			}
+		}
+
+		if !relay_mode.relays_acks() {
+			continue
 		}
 
 		// query acknowledgements that are waiting for connection delay.
-		let acks = query_undelivered_acks(
-			source_height,
-			sink_height,
-			channel_id,
-			port_id.clone(),
-			source,
-			sink,
-		)
-		.await?;
+		let acks = match ready_sequences(&[DelayedMessage::Acknowledgement]) {
+			Some(acks) => acks,
+			None =>
+				query_undelivered_acks(
+					source_height,
+					sink_height,
+					channel_id,
+					port_id.clone(),
+					source,
+					sink,
+				)
+				.await?,
+		};
 		// Get acknowledgement messages
 		if source_channel_end.state == State::Closed {
 			continue
 		}
-		let acknowledgements = source.query_recv_packets(channel_id, port_id, acks).await?;
+		let acknowledgements = source.query_recv_packets(channel_id, port_id.clone(), acks).await?;
 		for acknowledgement in acknowledgements {
 			let packet = packet_info_to_packet(&acknowledgement);
 			let ack = if let Some(ack) = acknowledgement.ack {
@@ -316,7 +474,22 @@ pub async fn query_ready_and_timed_out_packets(
 				continue
 			};
 
-			if !verify_delay_passed(
+			// re-anchor the proof if the sink has pruned the consensus state at that height
+			let proof_height = if let Some(proof_height) = find_retained_proof_height(
+				sink,
+				sink_height,
+				source.client_id(),
+				proof_height,
+				latest_source_height_on_sink,
+			)
+			.await?
+			{
+				proof_height
+			} else {
+				continue
+			};
+
+			match delay_remaining(
 				source,
 				sink,
 				source_timestamp,
@@ -329,12 +502,25 @@ pub async fn query_ready_and_timed_out_packets(
 			)
 			.await?
 			{
-				continue
+				Some(delay) if delay.is_zero() => {},
+				Some(delay) => {
+					schedule.schedule(
+						port_id.clone(),
+						channel_id,
+						packet.sequence,
+						DelayedMessage::Acknowledgement,
+						delay.max(min_delay),
+					);
+					continue
+				},
+				None => continue,
 			}
 
-			let msg = construct_ack_message(source, sink, packet, ack, proof_height).await?;
-
-			messages.push(msg)
+			if let Some(msg) =
+				construct_ack_message(source, sink, packet, ack, proof_height).await?
+			{
+				messages.push(msg)
+			}
 		}
 	}
 
@@ -12,9 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ibc::{timestamp::Timestamp, Height};
+use ibc::{
+	core::{
+		ics04_channel::packet::Sequence,
+		ics24_host::identifier::{ChannelId, PortId},
+	},
+	timestamp::Timestamp,
+	Height,
+};
 use primitives::error::Error;
-use std::time::Duration;
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	time::{Duration, Instant},
+};
+
+/// Earliest host time and height at which a proof at a client update made at
+/// `client_update_time` and `client_update_height` is accepted under the connection delay.
+pub fn delay_deadline(
+	client_update_time: Timestamp,
+	client_update_height: Height,
+	delay_period_time: Duration,
+	delay_period_blocks: u64,
+) -> Result<(Timestamp, Height), anyhow::Error> {
+	let earliest_time = (client_update_time + delay_period_time)
+		.map_err(|_| Error::Custom("Timestamp overflow".to_string()))?;
+	let earliest_height = client_update_height.add(delay_period_blocks);
+	Ok((earliest_time, earliest_height))
+}
 
 /// Verify the time and height delays
 pub fn has_delay_elapsed(
@@ -25,16 +49,111 @@ pub fn has_delay_elapsed(
 	delay_period_time: Duration,
 	delay_period_blocks: u64,
 ) -> Result<bool, anyhow::Error> {
-	let earliest_time = (client_update_time + delay_period_time)
-		.map_err(|_| Error::Custom("Timestamp overflow".to_string()))?;
+	let (earliest_time, earliest_height) = delay_deadline(
+		client_update_time,
+		client_update_height,
+		delay_period_time,
+		delay_period_blocks,
+	)?;
 	if !(current_time == earliest_time || current_time.after(&earliest_time)) {
 		return Ok(false)
 	}
 
-	let earliest_height = client_update_height.add(delay_period_blocks);
 	if current_height < earliest_height {
 		return Ok(false)
 	}
 
 	Ok(true)
 }
+
+/// Estimated time until the host reaches `deadline`, given its current time and height and its
+/// block time. Zero once the deadline has been reached.
+pub fn time_until_deadline(
+	current_time: Timestamp,
+	current_height: Height,
+	deadline: (Timestamp, Height),
+	block_time: Duration,
+) -> Duration {
+	let (earliest_time, earliest_height) = deadline;
+	let time_delay = earliest_time.duration_since(&current_time).unwrap_or_default();
+	let blocks = earliest_height.revision_height.saturating_sub(current_height.revision_height);
+	let height_delay = block_time.saturating_mul(blocks.try_into().unwrap_or(u32::MAX));
+	time_delay.max(height_delay)
+}
+
+/// Message that delivers a packet held back by the connection delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DelayedMessage {
+	RecvPacket,
+	Acknowledgement,
+	Timeout,
+}
+
+/// Packet held back by the connection delay and the message that delivers it.
+pub type DelayedPacket = (PortId, ChannelId, Sequence, DelayedMessage);
+
+/// Packets held back by the connection delay, with the instant their proofs are expected to be
+/// accepted, so they are submitted as soon as possible instead of on the next finality
+/// notification.
+#[derive(Debug, Default)]
+pub struct DelaySchedule {
+	packets: BTreeMap<DelayedPacket, Instant>,
+}
+
+impl DelaySchedule {
+	/// Schedules `message` for the packet to be submitted after `delay`.
+	pub fn schedule(
+		&mut self,
+		port_id: PortId,
+		channel_id: ChannelId,
+		sequence: Sequence,
+		message: DelayedMessage,
+		delay: Duration,
+	) {
+		log::debug!(
+			"{message:?} for packet {port_id}/{channel_id}/{sequence} is delayed by {delay:?}"
+		);
+		self.packets
+			.insert((port_id, channel_id, sequence, message), Instant::now() + delay);
+	}
+
+	/// Earliest instant a scheduled packet is expected to be ready.
+	pub fn next_ready(&self) -> Option<Instant> {
+		self.packets.values().min().copied()
+	}
+
+	/// Number of scheduled packets.
+	pub fn len(&self) -> usize {
+		self.packets.len()
+	}
+
+	/// Returns true if no packet is scheduled.
+	pub fn is_empty(&self) -> bool {
+		self.packets.is_empty()
+	}
+
+	/// Removes all scheduled packets.
+	pub fn clear(&mut self) {
+		self.packets.clear()
+	}
+
+	/// Removes and returns the packets expected to be ready at `now`.
+	pub fn take_ready(&mut self, now: Instant) -> BTreeSet<DelayedPacket> {
+		let ready = self
+			.packets
+			.iter()
+			.filter(|(_, ready_at)| **ready_at <= now)
+			.map(|(packet, _)| packet.clone())
+			.collect::<BTreeSet<_>>();
+		self.packets.retain(|packet, _| !ready.contains(packet));
+		ready
+	}
+}
+
+/// Waits until `ready_at`, or forever if it is `None`.
+pub async fn wait_until(ready_at: Option<Instant>) {
+	match ready_at {
+		Some(ready_at) => tokio::time::sleep_until(ready_at.into()).await,
+		None => futures::future::pending().await,
+	}
+}
@@ -0,0 +1,123 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	time::Duration,
+};
+
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
+use metrics::data::Metrics;
+use primitives::Chain;
+
+/// Age after which a packet commitment is reported as stuck when none is configured.
+pub const DEFAULT_STUCK_PACKET_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// Interval between two checks for stuck packets.
+pub const STUCK_PACKET_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tracks when the packets committed on a chain were sent, to find packets that are neither
+/// delivered nor timed out.
+#[derive(Default)]
+pub struct StuckPacketTracker {
+	/// Timestamps in nanoseconds of the blocks the packets were sent in, by sequence
+	sent_at: HashMap<(ChannelId, PortId), BTreeMap<u64, u64>>,
+}
+
+impl StuckPacketTracker {
+	/// Queries the packet commitments on the whitelisted channels of `chain`, returning the
+	/// sequences on each channel whose commitment has existed for at least `max_age`. The age of
+	/// a commitment is counted from the timestamp of the block its packet was sent in, commitments
+	/// whose packet isn't found on chain are not reported.
+	pub async fn check<C: Chain>(
+		&mut self,
+		chain: &C,
+		max_age: Duration,
+	) -> Result<Vec<((ChannelId, PortId), Vec<u64>)>, anyhow::Error> {
+		let (latest_height, latest_timestamp) = chain.latest_height_and_timestamp().await?;
+		let now = latest_timestamp.nanoseconds();
+		let mut stuck = vec![];
+		for (channel_id, port_id) in chain.channel_whitelist() {
+			let commitments = chain
+				.query_packet_commitments(latest_height, channel_id, port_id.clone())
+				.await?;
+			let previously_sent =
+				self.sent_at.remove(&(channel_id, port_id.clone())).unwrap_or_default();
+			let mut sent_at = commitments
+				.iter()
+				.filter_map(|seq| previously_sent.get(seq).map(|sent| (*seq, *sent)))
+				.collect::<BTreeMap<_, _>>();
+			let unknown = commitments
+				.into_iter()
+				.filter(|seq| !sent_at.contains_key(seq))
+				.collect::<Vec<_>>();
+			if !unknown.is_empty() {
+				let packets =
+					chain.query_send_packets(channel_id, port_id.clone(), unknown).await?;
+				let timestamps = chain
+					.query_timestamps_at(packets.iter().map(|packet| packet.height).collect())
+					.await?;
+				sent_at.extend(packets.iter().map(|packet| packet.sequence).zip(timestamps));
+			}
+			let seqs = sent_at
+				.iter()
+				.filter(|(_, sent)| Duration::from_nanos(now.saturating_sub(**sent)) >= max_age)
+				.map(|(seq, _)| *seq)
+				.collect();
+			self.sent_at.insert((channel_id, port_id.clone()), sent_at);
+			stuck.push(((channel_id, port_id), seqs));
+		}
+		Ok(stuck)
+	}
+}
+
+/// Checks the whitelisted channels of `chain` for packets stuck for longer than `max_age`,
+/// warning about them and reporting their number per channel to `metrics`.
+pub async fn monitor_stuck_packets<C: Chain>(
+	chain: C,
+	max_age: Duration,
+	metrics: Option<Metrics>,
+) {
+	let mut tracker = StuckPacketTracker::default();
+	let mut interval = tokio::time::interval(STUCK_PACKET_CHECK_INTERVAL);
+	loop {
+		interval.tick().await;
+		let stuck = match tracker.check(&chain, max_age).await {
+			Ok(stuck) => stuck,
+			Err(e) => {
+				log::warn!("Failed to check for stuck packets on {}: {:?}", chain.name(), e);
+				continue
+			},
+		};
+		for ((channel_id, port_id), seqs) in stuck {
+			if !seqs.is_empty() {
+				log::warn!(
+					"{} packets on {}/{} of {} are stuck for more than {:?}, sequences: {:?}",
+					seqs.len(),
+					port_id,
+					channel_id,
+					chain.name(),
+					max_age,
+					seqs
+				);
+			}
+			if let Some(metrics) = metrics.as_ref() {
+				metrics
+					.number_of_stuck_packets
+					.with_label_values(&[&format!("{}/{}", port_id, channel_id)])
+					.set(seqs.len() as u64);
+			}
+		}
+	}
+}
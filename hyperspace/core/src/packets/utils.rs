@@ -12,22 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::packets::connection_delay::has_delay_elapsed;
+use crate::packets::connection_delay::{delay_deadline, time_until_deadline};
+use anyhow::anyhow;
+use codec::Encode;
 use ibc::{
 	core::{
-		ics02_client::client_state::ClientState as ClientStateT,
+		ics02_client::{
+			client_consensus::ConsensusState as ConsensusStateT,
+			client_state::ClientState as ClientStateT,
+		},
 		ics04_channel::{
 			channel::{ChannelEnd, Order, State},
+			commitment::compute_packet_commitment,
 			context::calculate_block_delay,
 			msgs::{
-				acknowledgement::MsgAcknowledgement, recv_packet::MsgRecvPacket,
-				timeout::MsgTimeout, timeout_on_close::MsgTimeoutOnClose,
+				acknowledgement::{self, MsgAcknowledgement},
+				recv_packet::{self, MsgRecvPacket},
+				timeout::{self, MsgTimeout},
+				timeout_on_close::{self, MsgTimeoutOnClose},
 			},
 			packet::{Packet, TimeoutVariant},
 		},
-		ics23_commitment::commitment::CommitmentProofBytes,
-		ics24_host::path::{
-			AcksPath, ChannelEndsPath, CommitmentsPath, ReceiptsPath, SeqRecvsPath,
+		ics23_commitment::commitment::{CommitmentProofBytes, CommitmentRoot},
+		ics24_host::{
+			identifier::ClientId,
+			path::{AcksPath, ChannelEndsPath, CommitmentsPath, Path, ReceiptsPath, SeqRecvsPath},
 		},
 	},
 	proofs::Proofs,
@@ -36,9 +45,15 @@ use ibc::{
 	Height,
 };
 use ibc_proto::google::protobuf::Any;
-use pallet_ibc::light_clients::AnyClientState;
-use primitives::{apply_prefix, find_suitable_proof_height_for_client, Chain};
-use std::time::Duration;
+use light_client_common::{verify_membership, verify_non_membership};
+use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState};
+use primitives::{
+	apply_prefix, error::Error as PrimitivesError, find_suitable_proof_height_for_client,
+	time::elapsed_nanos, Chain,
+};
+use sp_core::hashing::sha2_256;
+use sp_runtime::traits::BlakeTwo256;
+use std::{collections::BTreeSet, str::FromStr, time::Duration};
 use tendermint_proto::Protobuf;
 
 pub async fn get_timeout_proof_height(
@@ -81,8 +96,8 @@ pub async fn get_timeout_proof_height(
 			let height = sink_client_state.latest_height();
 			let timestamp_at_creation =
 				sink.query_timestamp_at(height.revision_height).await.ok()?;
-			let period = packet.timeout_timestamp.nanoseconds() - timestamp_at_creation;
-			let period = Duration::from_nanos(period);
+			let period =
+				elapsed_nanos(timestamp_at_creation, packet.timeout_timestamp.nanoseconds());
 			let start_height = height.revision_height +
 				calculate_block_delay(period, sink.expected_block_time()).saturating_sub(1);
 			let start_height = Height::new(sink_height.revision_number, start_height);
@@ -111,8 +126,8 @@ pub async fn get_timeout_proof_height(
 			let height = sink_client_state.latest_height();
 			let timestamp_at_creation =
 				sink.query_timestamp_at(height.revision_height).await.ok()?;
-			let period = packet.timeout_timestamp.nanoseconds() - timestamp_at_creation;
-			let period = Duration::from_nanos(period);
+			let period =
+				elapsed_nanos(timestamp_at_creation, packet.timeout_timestamp.nanoseconds());
 			let start_height = height.revision_height +
 				calculate_block_delay(period, sink.expected_block_time()).saturating_sub(1);
 			let start_height = if start_height < packet.timeout_height.revision_height {
@@ -138,7 +153,57 @@ pub enum VerifyDelayOn {
 	Sink,
 }
 
-pub async fn verify_delay_passed(
+/// Returns `proof_height` if `chain` still holds the consensus state of `client_id` at it,
+/// otherwise re-anchors the proof at the next consensus state that has not been pruned. Errors
+/// other than [`PrimitivesError::ConsensusStatePruned`] are returned.
+pub async fn find_retained_proof_height(
+	chain: &impl Chain,
+	at: Height,
+	client_id: ClientId,
+	mut proof_height: Height,
+	latest_client_height: Height,
+) -> Result<Option<Height>, anyhow::Error> {
+	loop {
+		let e = match chain
+			.query_client_update_time_and_height(client_id.clone(), proof_height)
+			.await
+		{
+			Ok(_) => return Ok(Some(proof_height)),
+			Err(e) => anyhow::Error::from(e),
+		};
+		let pruned = e.chain().any(|e| {
+			matches!(
+				e.downcast_ref::<PrimitivesError>(),
+				Some(PrimitivesError::ConsensusStatePruned { .. })
+			)
+		});
+		if !pruned {
+			return Err(e)
+		}
+		log::debug!("{e}, re-anchoring proof");
+		if proof_height >= latest_client_height {
+			return Ok(None)
+		}
+		proof_height = match find_suitable_proof_height_for_client(
+			chain,
+			at,
+			client_id.clone(),
+			proof_height.increment(),
+			None,
+			latest_client_height,
+		)
+		.await
+		{
+			Some(proof_height) => proof_height,
+			None => return Ok(None),
+		};
+	}
+}
+
+/// Returns the estimated time until a proof at `proof_height` is accepted under the connection
+/// delay by the chain selected by `verify_delay_on`, zero if it already is. `None` if the client
+/// update at `proof_height` can't be found.
+pub async fn delay_remaining(
 	source: &impl Chain,
 	sink: &impl Chain,
 	source_timestamp: Timestamp,
@@ -148,50 +213,38 @@ pub async fn verify_delay_passed(
 	connection_delay: Duration,
 	proof_height: Height,
 	verify_delay_on: VerifyDelayOn,
-) -> Result<bool, anyhow::Error> {
+) -> Result<Option<Duration>, anyhow::Error> {
 	match verify_delay_on {
 		VerifyDelayOn::Source => {
 			if let Ok((sink_client_update_height, sink_client_update_time)) =
 				source.query_client_update_time_and_height(sink.client_id(), proof_height).await
 			{
-				let block_delay =
-					calculate_block_delay(connection_delay, source.expected_block_time());
-				if !has_delay_elapsed(
-					source_timestamp,
-					source_height,
+				let block_time = source.expected_block_time();
+				let deadline = delay_deadline(
 					sink_client_update_time,
 					sink_client_update_height, // shouldn't be the latest.
 					connection_delay,
-					block_delay,
-				)? {
-					Ok(false)
-				} else {
-					Ok(true)
-				}
+					calculate_block_delay(connection_delay, block_time),
+				)?;
+				Ok(Some(time_until_deadline(source_timestamp, source_height, deadline, block_time)))
 			} else {
-				Ok(false)
+				Ok(None)
 			}
 		},
 		VerifyDelayOn::Sink => {
 			if let Ok((source_client_update_height, source_client_update_time)) =
 				sink.query_client_update_time_and_height(source.client_id(), proof_height).await
 			{
-				let block_delay =
-					calculate_block_delay(connection_delay, sink.expected_block_time());
-				if !has_delay_elapsed(
-					sink_timestamp,
-					sink_height,
+				let block_time = sink.expected_block_time();
+				let deadline = delay_deadline(
 					source_client_update_time,
 					source_client_update_height,
 					connection_delay,
-					block_delay,
-				)? {
-					Ok(false)
-				} else {
-					Ok(true)
-				}
+					calculate_block_delay(connection_delay, block_time),
+				)?;
+				Ok(Some(time_until_deadline(sink_timestamp, sink_height, deadline, block_time)))
 			} else {
-				Ok(false)
+				Ok(None)
 			}
 		},
 	}
@@ -204,17 +257,19 @@ pub async fn construct_timeout_message(
 	packet: Packet,
 	next_sequence_recv: u64,
 	proof_height: Height,
-) -> Result<Any, anyhow::Error> {
-	let key = if sink_channel_end.ordering == Order::Ordered {
-		let path = get_key_path(KeyPathType::SeqRecv, &packet);
-		apply_prefix(sink.connection_prefix().into_vec(), path)
+) -> Result<Option<Any>, anyhow::Error> {
+	let (path, value) = if sink_channel_end.ordering == Order::Ordered {
+		(get_key_path(KeyPathType::SeqRecv, &packet), Some(next_sequence_recv.encode()))
 	} else {
-		let path = get_key_path(KeyPathType::ReceiptPath, &packet);
-		apply_prefix(sink.connection_prefix().into_vec(), path)
+		(get_key_path(KeyPathType::ReceiptPath, &packet), None)
 	};
+	let key = apply_prefix(sink.connection_prefix().into_vec(), path.clone());
 
 	let proof_unreceived = sink.query_proof(proof_height, vec![key]).await?;
 	let proof_unreceived = CommitmentProofBytes::try_from(proof_unreceived)?;
+	if !verify_proof_locally(sink, source, proof_height, path, value, &proof_unreceived).await {
+		return Ok(None)
+	}
 	let msg = if sink_channel_end.state == State::Closed {
 		let path = get_key_path(KeyPathType::ChannelPath, &packet);
 		let channel_key = apply_prefix(sink.connection_prefix().into_vec(), path);
@@ -239,7 +294,7 @@ pub async fn construct_timeout_message(
 		let value = msg.encode_vec();
 		Any { value, type_url: msg.type_url() }
 	};
-	Ok(msg)
+	Ok(Some(msg))
 }
 
 pub async fn construct_recv_message(
@@ -247,12 +302,24 @@ pub async fn construct_recv_message(
 	sink: &impl Chain,
 	packet: Packet,
 	proof_height: Height,
-) -> Result<Any, anyhow::Error> {
+) -> Result<Option<Any>, anyhow::Error> {
 	let path = get_key_path(KeyPathType::CommitmentPath, &packet);
 
-	let key = apply_prefix(source.connection_prefix().into_vec(), path);
+	let key = apply_prefix(source.connection_prefix().into_vec(), path.clone());
 	let proof = source.query_proof(proof_height, vec![key]).await?;
 	let commitment_proof = CommitmentProofBytes::try_from(proof)?;
+	let commitment = compute_packet_commitment(
+		packet.data.clone(),
+		packet.timeout_height,
+		packet.timeout_timestamp,
+		|value| sha2_256(&value).to_vec(),
+	)
+	.into_vec();
+	if !verify_proof_locally(source, sink, proof_height, path, Some(commitment), &commitment_proof)
+		.await
+	{
+		return Ok(None)
+	}
 	let msg = MsgRecvPacket {
 		packet,
 		proofs: Proofs::new(commitment_proof, None, None, None, proof_height)?,
@@ -260,7 +327,7 @@ pub async fn construct_recv_message(
 	};
 	let value = msg.encode_vec();
 	let msg = Any { value, type_url: msg.type_url() };
-	Ok(msg)
+	Ok(Some(msg))
 }
 
 pub async fn construct_ack_message(
@@ -269,12 +336,18 @@ pub async fn construct_ack_message(
 	packet: Packet,
 	ack: Vec<u8>,
 	proof_height: Height,
-) -> Result<Any, anyhow::Error> {
+) -> Result<Option<Any>, anyhow::Error> {
 	let path = get_key_path(KeyPathType::AcksPath, &packet);
 
-	let key = apply_prefix(source.connection_prefix().into_vec(), path);
+	let key = apply_prefix(source.connection_prefix().into_vec(), path.clone());
 	let proof = source.query_proof(proof_height, vec![key]).await?;
 	let commitment_proof = CommitmentProofBytes::try_from(proof)?;
+	let commitment = sha2_256(&ack).to_vec();
+	if !verify_proof_locally(source, sink, proof_height, path, Some(commitment), &commitment_proof)
+		.await
+	{
+		return Ok(None)
+	}
 	let msg = MsgAcknowledgement {
 		packet,
 		proofs: Proofs::new(commitment_proof, None, None, None, proof_height)?,
@@ -283,7 +356,137 @@ pub async fn construct_ack_message(
 	};
 	let value = msg.encode_vec();
 	let msg = Any { value, type_url: msg.type_url() };
-	Ok(msg)
+	Ok(Some(msg))
+}
+
+/// Checks a proof queried from `chain` against the consensus state of its client on
+/// `counterparty` at `proof_height`, the state the proof is verified against on chain. A `value`
+/// of `None` checks that nothing is stored at `path`. Returns false only if the proof is invalid,
+/// so the message carrying it is not submitted. If the consensus state can't be fetched, e.g.
+/// because the client update proving `proof_height` hasn't landed yet, the proof is left to be
+/// checked on chain.
+pub async fn verify_proof_locally(
+	chain: &impl Chain,
+	counterparty: &impl Chain,
+	proof_height: Height,
+	path: String,
+	value: Option<Vec<u8>>,
+	proof: &CommitmentProofBytes,
+) -> bool {
+	if !chain.verify_proofs() {
+		return true
+	}
+	let root = match proven_root(chain, counterparty, proof_height).await {
+		Ok(Some(root)) => root,
+		Ok(None) => return true,
+		Err(e) => {
+			log::debug!(
+				"Not verifying proof of {path} at {proof_height} from {} locally: {e:?}",
+				chain.name()
+			);
+			return true
+		},
+	};
+	let prefix = chain.connection_prefix();
+	let key_path = match Path::from_str(&path) {
+		Ok(key_path) => key_path,
+		Err(e) => {
+			log::error!("Failed to parse key path {path}: {e:?}");
+			return true
+		},
+	};
+	let result = match value {
+		Some(value) => verify_membership::<BlakeTwo256, _>(&prefix, proof, &root, key_path, value),
+		None => verify_non_membership::<BlakeTwo256, _>(&prefix, proof, &root, key_path),
+	};
+	if let Err(e) = result {
+		log::error!(
+			"Proof of {path} at {proof_height} from {} is invalid against its client on {}: {e:?}",
+			chain.name(),
+			counterparty.name()
+		);
+		return false
+	}
+	true
+}
+
+/// Returns the state root the client of `chain` on `counterparty` holds for `proof_height`,
+/// `None` for clients whose proofs can't be checked here.
+async fn proven_root(
+	chain: &impl Chain,
+	counterparty: &impl Chain,
+	proof_height: Height,
+) -> Result<Option<CommitmentRoot>, anyhow::Error> {
+	let client_id = chain.client_id();
+	let (at, ..) = counterparty.latest_height_and_timestamp().await?;
+	let response = counterparty.query_client_consensus(at, client_id.clone(), proof_height).await?;
+	let consensus_state = response.consensus_state.ok_or_else(|| {
+		anyhow!(
+			"Consensus state of {client_id} at {proof_height} not found on {}",
+			counterparty.name()
+		)
+	})?;
+	let consensus_state = AnyConsensusState::try_from(consensus_state)
+		.map_err(|_| anyhow!("Failed to decode consensus state of {client_id}"))?;
+	// only child trie proofs of substrate chains can be checked here
+	let root = match &consensus_state {
+		AnyConsensusState::Grandpa(consensus_state) => consensus_state.root(),
+		AnyConsensusState::Beefy(consensus_state) => consensus_state.root(),
+		_ => return Ok(None),
+	};
+	Ok(Some(root.clone()))
+}
+
+/// Returns the height a packet message is proven at, `None` for any other message.
+pub fn packet_proof_height(msg: &Any) -> Option<Height> {
+	let proofs = match msg.type_url.as_str() {
+		recv_packet::TYPE_URL => MsgRecvPacket::decode_vec(&msg.value).ok()?.proofs,
+		acknowledgement::TYPE_URL => MsgAcknowledgement::decode_vec(&msg.value).ok()?.proofs,
+		timeout::TYPE_URL => MsgTimeout::decode_vec(&msg.value).ok()?.proofs,
+		timeout_on_close::TYPE_URL => MsgTimeoutOnClose::decode_vec(&msg.value).ok()?.proofs,
+		_ => return None,
+	};
+	Some(proofs.height())
+}
+
+/// Drops packet messages that `sink` can't verify because it has no consensus state of the
+/// `source` client at their proof height. A proof height must either be installed by the client
+/// update submitted along with the messages, or not exceed the latest height of the client on
+/// `sink`.
+pub async fn retain_anchored_messages(
+	source: &impl Chain,
+	sink: &impl Chain,
+	messages: Vec<Any>,
+	update_heights: &BTreeSet<Height>,
+) -> Result<Vec<Any>, anyhow::Error> {
+	if messages.iter().all(|msg| packet_proof_height(msg).is_none()) {
+		return Ok(messages)
+	}
+	let client_id = source.client_id();
+	let (sink_height, ..) = sink.latest_height_and_timestamp().await?;
+	let client_state = sink
+		.query_client_state(sink_height, client_id.clone())
+		.await?
+		.client_state
+		.ok_or_else(|| anyhow!("Client state for {client_id} not found on {}", sink.name()))?;
+	let client_height = AnyClientState::try_from(client_state)
+		.map_err(|_| anyhow!("Failed to decode client state for {client_id}"))?
+		.latest_height();
+	let messages = messages
+		.into_iter()
+		.filter(|msg| match packet_proof_height(msg) {
+			Some(height) if height > client_height && !update_heights.contains(&height) => {
+				log::error!(
+					"Dropping {} proven at {height}, client {client_id} on {} is at {client_height} and the update doesn't install it",
+					msg.type_url,
+					sink.name()
+				);
+				false
+			},
+			_ => true,
+		})
+		.collect();
+	Ok(messages)
 }
 
 pub enum KeyPathType {
@@ -12,27 +12,95 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{
+	collections::BTreeMap,
+	sync::{Arc, Mutex},
+};
+
+use crate::journal::Journal;
 use ibc_proto::google::protobuf::Any;
 use metrics::handler::MetricsHandler;
-use primitives::Chain;
+use primitives::{
+	alert::{Alerts, Severity},
+	error::Error as PrimitivesError,
+	Chain,
+};
+
+/// Number of consecutive failed submissions to a chain after which an alert is sent.
+pub const SUBMISSION_FAILURES_BEFORE_ALERT: u32 = 3;
+
+/// Number of consecutive failed submissions to each chain. Clones share the counts.
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionFailures(Arc<Mutex<BTreeMap<String, u32>>>);
+
+impl SubmissionFailures {
+	/// Counts the outcome of a submission to `chain`, `error` being the one it failed with.
+	/// Alerts once [`SUBMISSION_FAILURES_BEFORE_ALERT`] submissions in a row have failed.
+	pub async fn track(&self, chain: &str, error: Option<&anyhow::Error>, alerts: &Alerts) {
+		let key = format!("submission/{chain}");
+		let error = match error {
+			Some(error) => error,
+			None => {
+				self.0.lock().unwrap().remove(chain);
+				alerts.resolve(&key);
+				return
+			},
+		};
+		let count = {
+			let mut failures = self.0.lock().unwrap();
+			let count = failures.entry(chain.to_string()).or_default();
+			*count += 1;
+			*count
+		};
+		if count >= SUBMISSION_FAILURES_BEFORE_ALERT {
+			let message =
+				format!("{count} transactions in a row failed on {chain}, last error: {error}");
+			alerts.alert(&key, Severity::Critical, message).await;
+		}
+	}
+}
 
 /// This sends messages to the sink chain in a gas-aware manner.
 pub async fn flush_message_batch(
 	msgs: Vec<Any>,
 	metrics: Option<&MetricsHandler>,
+	journal: &Journal,
+	sink: &impl Chain,
+) -> Result<(), anyhow::Error> {
+	let result = submit_messages(&msgs, metrics, journal, sink).await;
+	if let Some(metrics) = metrics {
+		// packets of messages that weren't broadcast won't be
+		metrics.discard_proven_packets(&msgs);
+	}
+	result
+}
+
+/// Submits `msgs` to the sink chain, in chunks if they exceed its block max weight.
+async fn submit_messages(
+	msgs: &[Any],
+	metrics: Option<&MetricsHandler>,
+	journal: &Journal,
 	sink: &impl Chain,
 ) -> Result<(), anyhow::Error> {
+	if sink.is_watch_only() {
+		log::info!(
+			"{} is watch-only, skipping submission of {} messages: {:?}",
+			sink.name(),
+			msgs.len(),
+			msgs.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>(),
+		);
+		return Ok(())
+	}
 	let block_max_weight = sink.block_max_weight();
-	let batch_weight = sink.estimate_weight(msgs.clone()).await?;
+	let batch_weight = sink.estimate_weight(msgs).await?;
 
 	if let Some(metrics) = metrics {
-		metrics.handle_transaction_costs(batch_weight, &msgs).await;
+		metrics.handle_transaction_costs(batch_weight, msgs).await;
 	}
 
 	let ratio = (batch_weight / block_max_weight) as usize;
 	if ratio == 0 {
-		sink.submit(msgs).await?;
-		return Ok(())
+		return submit_batch(msgs, metrics, journal, sink).await
 	}
 
 	// whelp our batch exceeds the block max weight.
@@ -51,8 +119,35 @@ pub async fn flush_message_batch(
 	// TODO: return number of failed messages and record it to metrics
 	for batch in msgs.chunks(chunk) {
 		// send out batches.
-		sink.submit(batch.to_vec()).await?;
+		submit_batch(batch, metrics, journal, sink).await?;
 	}
 
 	Ok(())
 }
+
+/// Submits a batch of messages to the sink chain, recording it in the metrics and audit log.
+async fn submit_batch(
+	msgs: &[Any],
+	metrics: Option<&MetricsHandler>,
+	journal: &Journal,
+	sink: &impl Chain,
+) -> Result<(), anyhow::Error> {
+	let result = sink.submit(msgs).await.map_err(anyhow::Error::from);
+	if matches!(&result, Err(e) if PrimitivesError::is_dry_run(e)) {
+		// signed and logged by the sink, but not submitted
+		return Ok(())
+	}
+	let tx_hash = result.as_ref().map(|tx_id| tx_id.to_string());
+	if let Some(audit_log) = &journal.audit_log {
+		audit_log.record_submission(sink.name(), msgs, &tx_hash).await;
+	}
+	let error = tx_hash.as_ref().err().copied();
+	journal.submission_failures.track(sink.name(), error, &journal.alerts).await;
+	let tx_id = result?;
+	if let Some(metrics) = metrics {
+		metrics.handle_broadcast(msgs);
+		let cost = sink.transaction_cost(&tx_id);
+		metrics.handle_transaction_cost(cost.gas_used, cost.fee, msgs);
+	}
+	Ok(())
+}
@@ -0,0 +1,123 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording of the ibc events the relayer acts on, and replaying them through the relay
+//! pipeline so issues seen in production can be reproduced against mock or dry-run clients.
+
+use std::{
+	fs::{self, OpenOptions},
+	io::{self, BufRead, BufReader, Write},
+	path::PathBuf,
+};
+
+use ibc::events::IbcEvent;
+use primitives::Chain;
+use serde::{Deserialize, Serialize};
+
+use crate::{events::parse_events, journal::Journal, queue};
+
+/// Ibc events queried from a chain for a single finality event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRecord {
+	/// Name of the chain the events were emitted on
+	pub chain: String,
+	/// Events in the order they were emitted
+	pub events: Vec<IbcEvent>,
+}
+
+/// Append-only JSONL file of [`EventRecord`]s.
+#[derive(Debug, Clone)]
+pub struct EventLog {
+	path: PathBuf,
+}
+
+impl EventLog {
+	pub fn new(path: PathBuf) -> Self {
+		Self { path }
+	}
+
+	/// Records `events` queried from `chain`. The log is written to on the blocking thread pool,
+	/// failures to write it are only logged.
+	pub async fn record_events(&self, chain: &str, events: &[IbcEvent]) {
+		if events.is_empty() {
+			return
+		}
+		let record = EventRecord { chain: chain.to_string(), events: events.to_vec() };
+		let event_log = self.clone();
+		match tokio::task::spawn_blocking(move || event_log.append(&record)).await {
+			Ok(Ok(())) => {},
+			Ok(Err(e)) =>
+				log::error!("Failed to write to the event log {}: {:?}", self.path.display(), e),
+			Err(e) => log::error!("Event log writer panicked: {:?}", e),
+		}
+	}
+
+	/// Appends `record` to the log.
+	pub fn append(&self, record: &EventRecord) -> io::Result<()> {
+		let mut line = serde_json::to_vec(record)?;
+		line.push(b'\n');
+		OpenOptions::new().create(true).append(true).open(&self.path)?.write_all(&line)
+	}
+
+	/// Returns the records in the log, oldest first.
+	pub fn records(&self) -> io::Result<Vec<EventRecord>> {
+		BufReader::new(fs::File::open(&self.path)?)
+			.lines()
+			.map(|line| Ok(serde_json::from_str(&line?)?))
+			.collect()
+	}
+}
+
+/// Feeds recorded events through the relay pipeline in order, submitting the resulting messages
+/// to the counterparty of the chain they were recorded on and timeouts back to that chain.
+/// Records of chains other than `chain_a` and `chain_b` are skipped.
+pub async fn replay<A: Chain, B: Chain>(
+	records: Vec<EventRecord>,
+	chain_a: &mut A,
+	chain_b: &mut B,
+) -> Result<(), anyhow::Error> {
+	for record in records {
+		if record.chain == chain_a.name() {
+			replay_events(chain_a, chain_b, record.events).await?;
+		} else if record.chain == chain_b.name() {
+			replay_events(chain_b, chain_a, record.events).await?;
+		} else {
+			log::warn!("Skipping events recorded on unknown chain {}", record.chain);
+		}
+	}
+	Ok(())
+}
+
+async fn replay_events(
+	source: &mut impl Chain,
+	sink: &mut impl Chain,
+	events: Vec<IbcEvent>,
+) -> Result<(), anyhow::Error> {
+	let (messages, timeouts) =
+		parse_events(source, sink, events, Some(&mut Default::default())).await?;
+	log::info!(
+		"Replaying {} messages to {} and {} timeouts to {}",
+		messages.len(),
+		sink.name(),
+		timeouts.len(),
+		source.name()
+	);
+	if !messages.is_empty() {
+		queue::flush_message_batch(messages, None, &Journal::default(), sink).await?;
+	}
+	if !timeouts.is_empty() {
+		queue::flush_message_batch(timeouts, None, &Journal::default(), source).await?;
+	}
+	Ok(())
+}
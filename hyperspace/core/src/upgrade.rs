@@ -0,0 +1,100 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::anyhow;
+use ibc::{
+	core::ics02_client::{
+		client_state::ClientState as ClientStateT, msgs::upgrade_client::MsgUpgradeAnyClient,
+	},
+	tx_msg::Msg,
+	Height,
+};
+use ibc_proto::google::protobuf::Any;
+use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState};
+use primitives::{mock::LocalClientTypes, Chain, UpgradePlan};
+use tendermint_proto::Protobuf;
+
+/// Returns the upgrade scheduled on `source` if it has reached its halt height and the client of
+/// `source` on `sink` hasn't been upgraded yet. Packet relay from `source` is paused until then.
+pub async fn pending_upgrade(
+	source: &impl Chain,
+	sink: &impl Chain,
+) -> Result<Option<UpgradePlan>, anyhow::Error> {
+	let plan = match source.detect_scheduled_upgrade().await {
+		Some(plan) => plan,
+		None => return Ok(None),
+	};
+	let (height, ..) = source.latest_height_and_timestamp().await?;
+	if !plan.is_reached(height) {
+		return Ok(None)
+	}
+	// an upgrade moves the chain, and so the client, to a new revision
+	let client_height = client_latest_height(source, sink).await?;
+	if client_height.revision_number > plan.height.revision_number {
+		return Ok(None)
+	}
+	Ok(Some(plan))
+}
+
+/// Builds the message upgrading the client of `source` on `sink` to the states `source` committed
+/// for `plan`. Their proofs are verified against the latest consensus state of the client, so
+/// `None` is returned until the client has been updated to the halt height.
+pub async fn construct_upgrade_message(
+	source: &impl Chain,
+	sink: &impl Chain,
+	plan: &UpgradePlan,
+) -> Result<Option<Any>, anyhow::Error> {
+	let client_height = client_latest_height(source, sink).await?;
+	if client_height < plan.height {
+		return Ok(None)
+	}
+	let client_state = source.query_upgraded_client_state(client_height).await?;
+	let consensus_state = source.query_upgraded_consensus_state(client_height).await?;
+	let msg = MsgUpgradeAnyClient::<LocalClientTypes> {
+		client_id: source.client_id(),
+		client_state: AnyClientState::try_from(
+			client_state
+				.client_state
+				.ok_or_else(|| anyhow!("Upgraded client state not found"))?,
+		)
+		.map_err(|_| anyhow!("Failed to decode upgraded client state"))?,
+		consensus_state: AnyConsensusState::try_from(
+			consensus_state
+				.consensus_state
+				.ok_or_else(|| anyhow!("Upgraded consensus state not found"))?,
+		)
+		.map_err(|_| anyhow!("Failed to decode upgraded consensus state"))?,
+		proof_upgrade_client: client_state.proof,
+		proof_upgrade_consensus_state: consensus_state.proof,
+		signer: sink.account_id(),
+	};
+	Ok(Some(Any { type_url: msg.type_url(), value: msg.encode_vec() }))
+}
+
+/// Latest height of the client of `source` on `sink`.
+async fn client_latest_height(
+	source: &impl Chain,
+	sink: &impl Chain,
+) -> Result<Height, anyhow::Error> {
+	let client_id = source.client_id();
+	let (sink_height, ..) = sink.latest_height_and_timestamp().await?;
+	let client_state = sink
+		.query_client_state(sink_height, client_id.clone())
+		.await?
+		.client_state
+		.ok_or_else(|| anyhow!("Client state for {client_id} not found on {}", sink.name()))?;
+	let client_state = AnyClientState::try_from(client_state)
+		.map_err(|_| anyhow!("Failed to decode client state for {client_id}"))?;
+	Ok(client_state.latest_height())
+}
@@ -0,0 +1,74 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use primitives::alert::{AlertSink, Alerts, Severity};
+
+#[derive(Default)]
+struct RecordingSink(Mutex<Vec<(Severity, String)>>);
+
+#[async_trait]
+impl AlertSink for RecordingSink {
+	async fn send(&self, severity: Severity, message: &str) -> Result<(), anyhow::Error> {
+		self.0.lock().unwrap().push((severity, message.to_string()));
+		Ok(())
+	}
+}
+
+#[tokio::test]
+async fn repeated_alerts_are_sent_once_until_resolved() {
+	let sink = Arc::new(RecordingSink::default());
+	let alerts = Alerts::new(sink.clone());
+
+	alerts
+		.alert("balance/mock-a", Severity::Warning, "low balance".to_string())
+		.await;
+	alerts.alert("balance/mock-a", Severity::Warning, "still low".to_string()).await;
+	alerts
+		.alert("connection/mock-a", Severity::Critical, "connection lost".to_string())
+		.await;
+	alerts.resolve("balance/mock-a");
+	alerts.alert("balance/mock-a", Severity::Warning, "low again".to_string()).await;
+
+	assert_eq!(
+		*sink.0.lock().unwrap(),
+		vec![
+			(Severity::Warning, "low balance".to_string()),
+			(Severity::Critical, "connection lost".to_string()),
+			(Severity::Warning, "low again".to_string()),
+		]
+	);
+}
+
+#[tokio::test]
+async fn clones_share_the_alert_cooldown() {
+	let sink = Arc::new(RecordingSink::default());
+	let alerts = Alerts::new(sink.clone());
+
+	alerts
+		.alert("client/mock-b/10-grandpa-0", Severity::Critical, "expired".to_string())
+		.await;
+	alerts
+		.clone()
+		.alert("client/mock-b/10-grandpa-0", Severity::Critical, "expired".to_string())
+		.await;
+	// alerts without a sink are dropped
+	Alerts::default()
+		.alert("balance/mock-a", Severity::Warning, "low".to_string())
+		.await;
+
+	assert_eq!(sink.0.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,82 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::anyhow;
+use hyperspace_core::audit::{AuditLog, AuditRecord, ROTATED_AUDIT_LOGS};
+use ibc_proto::google::protobuf::Any;
+use primitives::error::Error as PrimitivesError;
+
+fn record(sequence: u64) -> AuditRecord {
+	AuditRecord {
+		timestamp: 0,
+		chain: "mock-a".to_string(),
+		type_url: "/ibc.core.channel.v1.MsgRecvPacket".to_string(),
+		channel: Some("transfer/channel-0".to_string()),
+		sequence: Some(sequence),
+		tx_hash: Some("0x01".to_string()),
+		code: None,
+		error: None,
+	}
+}
+
+#[test]
+fn audit_log_is_rotated_and_read_back_in_order() {
+	let dir = std::env::temp_dir().join(format!("hyperspace-audit-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	// every append exceeds the maximum size, so each one starts a new file
+	let audit_log = AuditLog::new(dir.join("audit.jsonl"), 1);
+	for sequence in 1..=(ROTATED_AUDIT_LOGS as u64 + 3) {
+		audit_log.append(&[record(sequence)]).unwrap();
+	}
+
+	let sequences = audit_log
+		.records()
+		.unwrap()
+		.into_iter()
+		.map(|record| record.sequence.unwrap())
+		.collect::<Vec<_>>();
+	// the oldest files were rotated out
+	assert_eq!(sequences, (3..=ROTATED_AUDIT_LOGS as u64 + 3).collect::<Vec<_>>());
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn failed_submissions_are_recorded_with_their_error() {
+	let message =
+		Any { type_url: "/ibc.core.client.v1.MsgUpdateClient".to_string(), value: vec![] };
+	let error = anyhow!("insufficient balance");
+	let record = AuditRecord::new("mock-b", &message, &Err(&error));
+	assert_eq!(record.channel, None);
+	assert_eq!(record.sequence, None);
+	assert_eq!(record.tx_hash, None);
+	assert_eq!(record.code, None);
+	assert_eq!(record.error.as_deref(), Some("insufficient balance"));
+}
+
+#[test]
+fn transactions_failed_on_chain_are_recorded_with_their_code() {
+	let message =
+		Any { type_url: "/ibc.core.client.v1.MsgUpdateClient".to_string(), value: vec![] };
+	let error = anyhow::Error::from(PrimitivesError::TransactionFailed {
+		chain: "mock-b".to_string(),
+		tx_hash: "0x02".to_string(),
+		code: 0x031a00,
+		reason: "Module".to_string(),
+	})
+	.context("Failed to submit messages");
+	let record = AuditRecord::new("mock-b", &message, &Err(&error));
+	assert_eq!(record.tx_hash.as_deref(), Some("0x02"));
+	assert_eq!(record.code, Some(0x031a00));
+	assert_eq!(record.error.as_deref(), Some("Failed to submit messages"));
+}
@@ -0,0 +1,1004 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hyperspace_core::{
+	audit::AuditLog,
+	balance::check_balance,
+	bootstrap::{find_client, find_open_channel, find_open_connection, ChannelConfig},
+	events::parse_events,
+	handshake::resume_handshakes,
+	journal::Journal,
+	packets::{
+		connection_delay::DelaySchedule,
+		full_scan_due, query_delayed_packets, query_ready_and_timed_out_packets,
+		stuck::StuckPacketTracker,
+		utils::{construct_recv_message, retain_anchored_messages},
+	},
+	relay_in_direction,
+	replay::{replay, EventLog, EventRecord},
+	RelayDirection,
+};
+use ibc::{
+	applications::transfer::{packet::PacketData, Amount, PrefixedCoin, PrefixedDenom},
+	bigint::U256,
+	core::{
+		ics02_client::client_state::ClientState,
+		ics03_connection::{
+			connection::{
+				ConnectionEnd, Counterparty as ConnectionCounterparty, State as ConnectionState,
+			},
+			version::get_compatible_versions,
+		},
+		ics04_channel::{
+			channel::{ChannelEnd, Counterparty as ChannelCounterparty, Order, State},
+			events::{OpenAck as ChannelOpenAck, SendPacket},
+			msgs::recv_packet::MsgRecvPacket,
+			packet::Packet,
+			Version,
+		},
+		ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
+	},
+	events::IbcEvent,
+	signer::Signer,
+	timestamp::Timestamp,
+	Height,
+};
+use ics10_grandpa::{
+	client_state::ClientState as GrandpaClientState,
+	consensus_state::ConsensusState as GrandpaConsensusState,
+};
+use light_client_common::RelayChain;
+use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager};
+use primitives::{
+	check_client_status,
+	error::Error,
+	mock::chain::{MockChain, MOCK_UPDATE_TYPE_URL},
+	packet_data::{AmountLimit, PacketFilter},
+	IbcProvider, RelayMode, UpgradePlan,
+};
+use std::{
+	collections::BTreeSet,
+	marker::PhantomData,
+	str::FromStr,
+	time::{Duration, Instant},
+};
+use tendermint_proto::Protobuf;
+
+const ACK: &[u8] = b"{\"result\":\"AQ==\"}";
+
+/// Two mock chains connected by an open transfer channel without connection delay.
+fn setup_chains() -> (MockChain, MockChain) {
+	let client_type = GrandpaClientState::<HostFunctionsManager>::client_type();
+	let connection_id = ConnectionId::new(0);
+	let channel_id = ChannelId::new(0);
+	let mut chain_a = MockChain::new(
+		"mock-a",
+		ClientId::from_str("10-grandpa-0").unwrap(),
+		connection_id.clone(),
+		client_type.clone(),
+	);
+	let mut chain_b = MockChain::new(
+		"mock-b",
+		ClientId::from_str("10-grandpa-1").unwrap(),
+		connection_id.clone(),
+		client_type,
+	);
+
+	for (chain, counterparty) in [(&chain_a, &chain_b), (&chain_b, &chain_a)] {
+		let connection = ConnectionEnd::new(
+			ConnectionState::Open,
+			counterparty.client_id(),
+			ConnectionCounterparty::new(
+				chain.client_id(),
+				Some(connection_id.clone()),
+				counterparty.connection_prefix(),
+			),
+			get_compatible_versions(),
+			Duration::ZERO,
+		);
+		chain.set_connection(connection_id.clone(), connection);
+		let channel = ChannelEnd::new(
+			State::Open,
+			Order::Unordered,
+			ChannelCounterparty::new(PortId::transfer(), Some(channel_id)),
+			vec![connection_id.clone()],
+			Version::ics20(),
+		);
+		chain.set_channel(PortId::transfer(), channel_id, channel);
+		update_client(chain, counterparty);
+	}
+	chain_a.set_channel_whitelist(vec![(channel_id, PortId::transfer())]);
+	chain_b.set_channel_whitelist(vec![(channel_id, PortId::transfer())]);
+
+	(chain_a, chain_b)
+}
+
+/// Updates the client of `counterparty` on `chain` to the latest height of `counterparty`.
+fn update_client(chain: &MockChain, counterparty: &MockChain) {
+	let height = counterparty.height();
+	let (client_state, consensus_state) = grandpa_states(height, counterparty.timestamp());
+	chain.set_client_state(counterparty.client_id(), client_state);
+	chain.set_consensus_state(counterparty.client_id(), height, consensus_state);
+}
+
+/// Grandpa client and consensus states of a chain at `height`.
+fn grandpa_states(height: Height, timestamp: Timestamp) -> (AnyClientState, AnyConsensusState) {
+	let client_state = GrandpaClientState::<HostFunctionsManager> {
+		relay_chain: RelayChain::Rococo,
+		latest_relay_height: height.revision_height as u32,
+		latest_relay_hash: Default::default(),
+		frozen_height: None,
+		latest_para_height: height.revision_height as u32,
+		para_id: height.revision_number as u32,
+		current_set_id: 0,
+		current_authorities: vec![],
+		_phantom: PhantomData,
+	};
+	let consensus_state = GrandpaConsensusState::new(
+		vec![0; 32],
+		timestamp.into_tm_time().expect("Mock timestamp is set"),
+	);
+	(AnyClientState::Grandpa(client_state), AnyConsensusState::Grandpa(consensus_state))
+}
+
+fn transfer_packet(sequence: u64, timeout_height: Height) -> Packet {
+	Packet {
+		sequence: sequence.into(),
+		source_port: PortId::transfer(),
+		source_channel: ChannelId::new(0),
+		destination_port: PortId::transfer(),
+		destination_channel: ChannelId::new(0),
+		data: vec![1, 2, 3],
+		timeout_height,
+		timeout_timestamp: Timestamp::none(),
+	}
+}
+
+fn token_transfer_packet(sequence: u64, denom: &str, amount: u64) -> Packet {
+	let data = PacketData {
+		token: PrefixedCoin {
+			denom: PrefixedDenom::from_str(denom).unwrap(),
+			amount: Amount::from(amount),
+		},
+		sender: Signer::from_str("alice").unwrap(),
+		receiver: Signer::from_str("bob").unwrap(),
+	};
+	Packet {
+		data: serde_json::to_vec(&data).unwrap(),
+		..transfer_packet(sequence, Height::new(0, 1000))
+	}
+}
+
+fn type_urls(messages: &[ibc_proto::google::protobuf::Any]) -> Vec<&str> {
+	messages.iter().map(|msg| msg.type_url.as_str()).collect()
+}
+
+#[tokio::test]
+async fn recv_packet_waits_for_client_update() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.produce_block();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+
+	// the client of chain a on chain b is behind the packet height
+	let (messages, timeouts) =
+		query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut Default::default())
+			.await
+			.unwrap();
+	assert!(messages.is_empty());
+	assert!(timeouts.is_empty());
+
+	update_client(&chain_b, &chain_a);
+	let (messages, timeouts) =
+		query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut Default::default())
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+	assert!(timeouts.is_empty());
+}
+
+#[tokio::test]
+async fn packet_held_by_connection_delay_is_scheduled() {
+	let (chain_a, chain_b) = setup_chains();
+	for (chain, counterparty) in [(&chain_a, &chain_b), (&chain_b, &chain_a)] {
+		let connection = ConnectionEnd::new(
+			ConnectionState::Open,
+			counterparty.client_id(),
+			ConnectionCounterparty::new(
+				chain.client_id(),
+				Some(ConnectionId::new(0)),
+				counterparty.connection_prefix(),
+			),
+			get_compatible_versions(),
+			Duration::from_secs(60),
+		);
+		chain.set_connection(ConnectionId::new(0), connection);
+	}
+	chain_a.produce_block();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+
+	let mut schedule = DelaySchedule::default();
+	let (messages, _) = query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut schedule)
+		.await
+		.unwrap();
+	assert!(messages.is_empty());
+	assert_eq!(schedule.len(), 1);
+	let ready_in = schedule.next_ready().unwrap() - std::time::Instant::now();
+	assert!(ready_in > Duration::from_secs(50) && ready_in <= Duration::from_secs(60));
+
+	// the sink's block time is six seconds
+	for _ in 0..10 {
+		chain_b.produce_block();
+	}
+	let (messages, _) = query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut schedule)
+		.await
+		.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+	assert!(schedule.is_empty());
+}
+
+#[tokio::test]
+async fn delayed_pass_only_queries_scheduled_packets() {
+	let (chain_a, chain_b) = setup_chains();
+	for (chain, counterparty) in [(&chain_a, &chain_b), (&chain_b, &chain_a)] {
+		let connection = ConnectionEnd::new(
+			ConnectionState::Open,
+			counterparty.client_id(),
+			ConnectionCounterparty::new(
+				chain.client_id(),
+				Some(ConnectionId::new(0)),
+				counterparty.connection_prefix(),
+			),
+			get_compatible_versions(),
+			Duration::from_secs(60),
+		);
+		chain.set_connection(ConnectionId::new(0), connection);
+	}
+	chain_a.produce_block();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+
+	let mut schedule = DelaySchedule::default();
+	query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut schedule)
+		.await
+		.unwrap();
+	assert_eq!(schedule.len(), 1);
+	// sent after the scan, only a full scan picks it up
+	chain_a.send_packet(transfer_packet(2, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+
+	// nothing is due yet
+	let now = Instant::now();
+	let (messages, _) =
+		query_delayed_packets(&chain_a, &chain_b, &mut schedule, now).await.unwrap();
+	assert!(messages.is_empty());
+	assert_eq!(schedule.len(), 1);
+
+	// the sink hasn't produced a block since, the packet waits at least another block
+	let due = schedule.next_ready().unwrap() + Duration::from_secs(1);
+	let (messages, _) =
+		query_delayed_packets(&chain_a, &chain_b, &mut schedule, due).await.unwrap();
+	assert!(messages.is_empty());
+	assert_eq!(schedule.len(), 1);
+	assert!(schedule.next_ready().unwrap() >= Instant::now() + Duration::from_secs(5));
+
+	for _ in 0..10 {
+		chain_b.produce_block();
+	}
+	let due = schedule.next_ready().unwrap();
+	let (messages, _) =
+		query_delayed_packets(&chain_a, &chain_b, &mut schedule, due).await.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+	let msg = MsgRecvPacket::decode_vec(&messages[0].value).unwrap();
+	assert_eq!(u64::from(msg.packet.sequence), 1);
+	assert!(schedule.is_empty());
+}
+
+#[tokio::test]
+async fn proof_is_reanchored_above_pruned_consensus_state() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.produce_block();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	let pruned_height = chain_a.height();
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	chain_b.prune_client_update(chain_a.client_id(), pruned_height);
+
+	let (messages, _) =
+		query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut Default::default())
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+	let msg = MsgRecvPacket::decode_vec(&messages[0].value).unwrap();
+	assert_eq!(msg.proofs.height(), chain_a.height());
+}
+
+#[tokio::test]
+async fn unanchored_packet_messages_are_dropped() {
+	let (chain_a, chain_b) = setup_chains();
+	let packet = transfer_packet(1, Height::new(0, 1000));
+	chain_a.send_packet(packet.clone());
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	let client_height = chain_a.height();
+	chain_a.produce_block();
+	let update_height = chain_a.height();
+
+	let anchored = construct_recv_message(&chain_a, &chain_b, packet.clone(), client_height)
+		.await
+		.unwrap()
+		.unwrap();
+	let unanchored = construct_recv_message(&chain_a, &chain_b, packet, update_height)
+		.await
+		.unwrap()
+		.unwrap();
+	let messages = vec![anchored.clone(), unanchored];
+
+	let retained = retain_anchored_messages(&chain_a, &chain_b, messages.clone(), &BTreeSet::new())
+		.await
+		.unwrap();
+	assert_eq!(retained, vec![anchored]);
+
+	// the height is proven by the client update sent along with the messages
+	let update_heights = BTreeSet::from([update_height]);
+	let retained = retain_anchored_messages(&chain_a, &chain_b, messages.clone(), &update_heights)
+		.await
+		.unwrap();
+	assert_eq!(retained, messages);
+}
+
+#[tokio::test]
+async fn received_packet_is_acknowledged() {
+	let (chain_a, chain_b) = setup_chains();
+	let packet = transfer_packet(1, Height::new(0, 1000));
+	chain_a.send_packet(packet.clone());
+	chain_a.produce_block();
+	chain_b.receive_packet(packet, ACK.to_vec());
+	chain_b.produce_block();
+	update_client(&chain_b, &chain_a);
+	update_client(&chain_a, &chain_b);
+
+	// nothing left to deliver to chain b
+	let (messages, timeouts) =
+		query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut Default::default())
+			.await
+			.unwrap();
+	assert!(messages.is_empty());
+	assert!(timeouts.is_empty());
+
+	// the acknowledgement goes back to chain a
+	let (messages, timeouts) =
+		query_ready_and_timed_out_packets(&chain_b, &chain_a, &mut Default::default())
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgAcknowledgement"]);
+	assert!(timeouts.is_empty());
+
+	chain_a.clear_packet_commitment(&transfer_packet(1, Height::new(0, 1000)));
+	let (messages, _) =
+		query_ready_and_timed_out_packets(&chain_b, &chain_a, &mut Default::default())
+			.await
+			.unwrap();
+	assert!(messages.is_empty());
+}
+
+#[tokio::test]
+async fn expired_packet_is_timed_out() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 2)));
+	chain_a.produce_block();
+	for _ in 0..3 {
+		chain_b.produce_block();
+		update_client(&chain_a, &chain_b);
+	}
+	update_client(&chain_b, &chain_a);
+
+	let (messages, timeouts) =
+		query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut Default::default())
+			.await
+			.unwrap();
+	assert!(messages.is_empty());
+	assert_eq!(type_urls(&timeouts), vec!["/ibc.core.channel.v1.MsgTimeout"]);
+}
+
+#[tokio::test]
+async fn expired_send_packet_event_is_timed_out() {
+	let (mut chain_a, mut chain_b) = setup_chains();
+	let packet = transfer_packet(1, Height::new(0, 2));
+	chain_a.send_packet(packet.clone());
+	chain_a.produce_block();
+	let event = IbcEvent::SendPacket(SendPacket { height: chain_a.height(), packet });
+	for _ in 0..3 {
+		chain_b.produce_block();
+		update_client(&chain_a, &chain_b);
+	}
+	update_client(&chain_b, &chain_a);
+
+	let (messages, timeouts) =
+		parse_events(&mut chain_a, &mut chain_b, vec![event], Some(&mut Default::default()))
+			.await
+			.unwrap();
+	assert!(messages.is_empty());
+	assert_eq!(type_urls(&timeouts), vec!["/ibc.core.channel.v1.MsgTimeout"]);
+}
+
+#[tokio::test]
+async fn missed_send_packet_is_found_by_full_scan() {
+	let (mut chain_a, mut chain_b) = setup_chains();
+	chain_a.produce_block();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+
+	// the send packet event was missed
+	let (messages, _) = parse_events(&mut chain_a, &mut chain_b, vec![], None).await.unwrap();
+	assert!(messages.is_empty());
+	let (messages, _) =
+		parse_events(&mut chain_a, &mut chain_b, vec![], Some(&mut Default::default()))
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+}
+
+#[tokio::test]
+async fn full_scan_is_due_every_interval() {
+	let (mut chain_a, _) = setup_chains();
+	let mut last_scan = None;
+	assert!(full_scan_due(&chain_a, &mut last_scan).await.unwrap());
+	assert!(full_scan_due(&chain_a, &mut last_scan).await.unwrap());
+
+	chain_a.set_full_scan_interval(Some(2));
+	let mut last_scan = None;
+	assert!(full_scan_due(&chain_a, &mut last_scan).await.unwrap());
+	chain_a.produce_block();
+	assert!(!full_scan_due(&chain_a, &mut last_scan).await.unwrap());
+	chain_a.produce_block();
+	assert!(full_scan_due(&chain_a, &mut last_scan).await.unwrap());
+	assert!(!full_scan_due(&chain_a, &mut last_scan).await.unwrap());
+}
+
+#[tokio::test]
+async fn completed_handshake_step_is_skipped() {
+	let (mut chain_a, mut chain_b) = setup_chains();
+	let channel_id = ChannelId::new(0);
+	let event = IbcEvent::OpenAckChannel(ChannelOpenAck {
+		height: chain_a.height(),
+		port_id: PortId::transfer(),
+		channel_id: Some(channel_id),
+		counterparty_channel_id: Some(channel_id),
+		connection_id: ConnectionId::new(0),
+		counterparty_port_id: PortId::transfer(),
+	});
+
+	// another relayer has already confirmed the channel on chain b
+	let (messages, _) = parse_events(
+		&mut chain_a,
+		&mut chain_b,
+		vec![event.clone()],
+		Some(&mut Default::default()),
+	)
+	.await
+	.unwrap();
+	assert!(messages.is_empty());
+
+	let channel = ChannelEnd::new(
+		State::TryOpen,
+		Order::Unordered,
+		ChannelCounterparty::new(PortId::transfer(), Some(channel_id)),
+		vec![ConnectionId::new(0)],
+		Version::ics20(),
+	);
+	chain_b.set_channel(PortId::transfer(), channel_id, channel);
+	let (messages, _) =
+		parse_events(&mut chain_a, &mut chain_b, vec![event], Some(&mut Default::default()))
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgChannelOpenConfirm"]);
+}
+
+#[tokio::test]
+async fn denied_transfers_are_not_relayed() {
+	let (mut chain_a, chain_b) = setup_chains();
+	chain_a.set_packet_filter(PacketFilter {
+		denied_denoms: vec!["uatom".to_string()],
+		denied_addresses: vec![],
+		amount_limits: vec![
+			AmountLimit {
+				port_id: Some("transfer".to_string()),
+				denom: "UNIT".to_string(),
+				max_amount: U256::from(1_000),
+			},
+			AmountLimit {
+				port_id: Some("other".to_string()),
+				denom: "uosmo".to_string(),
+				max_amount: U256::zero(),
+			},
+		],
+	});
+	chain_a.send_packet(token_transfer_packet(1, "uatom", 10));
+	chain_a.send_packet(token_transfer_packet(2, "UNIT", 10_000));
+	chain_a.send_packet(token_transfer_packet(3, "transfer/channel-0/UNIT", 10));
+	// limits of other denoms or ports don't apply
+	chain_a.send_packet(token_transfer_packet(4, "uosmo", 10_000));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+
+	let (messages, _) =
+		query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut Default::default())
+			.await
+			.unwrap();
+	assert_eq!(
+		type_urls(&messages),
+		vec!["/ibc.core.channel.v1.MsgRecvPacket", "/ibc.core.channel.v1.MsgRecvPacket"]
+	);
+}
+
+#[tokio::test]
+async fn one_directional_relay_ignores_the_reverse_direction() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_b.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	chain_b.produce_block();
+	update_client(&chain_b, &chain_a);
+	update_client(&chain_a, &chain_b);
+	chain_a.end_finality();
+
+	relay_in_direction(
+		chain_a.clone(),
+		chain_b.clone(),
+		None,
+		None,
+		Journal::default(),
+		RelayDirection::AToB,
+	)
+	.await
+	.unwrap();
+
+	let submitted = chain_b.submitted_messages().concat();
+	assert!(type_urls(&submitted).contains(&"/ibc.core.channel.v1.MsgRecvPacket"));
+	assert!(chain_a.submitted_messages().is_empty());
+}
+
+#[tokio::test]
+async fn nothing_is_relayed_over_an_expired_client() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	// the latest consensus state of the client is just older than its trusting period
+	let trusting_period = RelayChain::Rococo.trusting_period();
+	let stale = chain_b.timestamp().nanoseconds() - trusting_period.as_nanos() as u64 - 1;
+	let (client_state, consensus_state) =
+		grandpa_states(chain_a.height(), Timestamp::from_nanoseconds(stale).unwrap());
+	chain_b.set_client_state(chain_a.client_id(), client_state);
+	chain_b.set_consensus_state(chain_a.client_id(), chain_a.height(), consensus_state);
+	chain_a.end_finality();
+
+	let err = check_client_status(&chain_a, &chain_b, &Default::default()).await.unwrap_err();
+	assert!(matches!(err.downcast_ref::<Error>(), Some(Error::ClientExpired { .. })));
+
+	relay_in_direction(
+		chain_a.clone(),
+		chain_b.clone(),
+		None,
+		None,
+		Journal::default(),
+		RelayDirection::AToB,
+	)
+	.await
+	.unwrap();
+
+	assert!(chain_b.submitted_messages().is_empty());
+}
+
+#[tokio::test]
+async fn client_within_its_trusting_period_is_active() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.produce_block();
+	let trusting_period = RelayChain::Rococo.trusting_period();
+	let recent = chain_b.timestamp().nanoseconds() - trusting_period.as_nanos() as u64 + 1;
+	let (client_state, consensus_state) =
+		grandpa_states(chain_a.height(), Timestamp::from_nanoseconds(recent).unwrap());
+	chain_b.set_client_state(chain_a.client_id(), client_state);
+	chain_b.set_consensus_state(chain_a.client_id(), chain_a.height(), consensus_state);
+
+	check_client_status(&chain_a, &chain_b, &Default::default()).await.unwrap();
+}
+
+#[tokio::test]
+async fn heartbeat_client_update_is_sent_once_the_interval_has_passed() {
+	let (mut chain_a, chain_b) = setup_chains();
+	chain_a.set_heartbeat_interval(Some(Duration::ZERO));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	chain_a.end_finality();
+
+	relay_in_direction(
+		chain_a.clone(),
+		chain_b.clone(),
+		None,
+		None,
+		Journal::default(),
+		RelayDirection::AToB,
+	)
+	.await
+	.unwrap();
+
+	let submitted = chain_b.submitted_messages().concat();
+	assert_eq!(type_urls(&submitted), vec![MOCK_UPDATE_TYPE_URL]);
+}
+
+#[tokio::test]
+async fn heartbeat_client_update_is_skipped_before_the_interval() {
+	let (mut chain_a, chain_b) = setup_chains();
+	chain_a.set_heartbeat_interval(Some(Duration::from_secs(3600)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	chain_a.end_finality();
+
+	relay_in_direction(
+		chain_a.clone(),
+		chain_b.clone(),
+		None,
+		None,
+		Journal::default(),
+		RelayDirection::AToB,
+	)
+	.await
+	.unwrap();
+
+	assert!(chain_b.submitted_messages().is_empty());
+}
+
+#[tokio::test]
+async fn submitted_packets_are_recorded_in_the_audit_log() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	chain_a.end_finality();
+	let path = std::env::temp_dir().join(format!("hyperspace-audit-relay-{}", std::process::id()));
+	let audit_log = AuditLog::new(path.clone(), u64::MAX);
+	let journal = Journal { audit_log: Some(audit_log.clone()), ..Default::default() };
+
+	relay_in_direction(chain_a.clone(), chain_b.clone(), None, None, journal, RelayDirection::AToB)
+		.await
+		.unwrap();
+
+	let records = audit_log.records().unwrap();
+	let recv = records
+		.iter()
+		.find(|record| record.type_url == "/ibc.core.channel.v1.MsgRecvPacket")
+		.unwrap();
+	assert_eq!(recv.chain, "mock-b");
+	assert_eq!(recv.channel.as_deref(), Some("transfer/channel-0"));
+	assert_eq!(recv.sequence, Some(1));
+	assert!(recv.tx_hash.is_some());
+	assert_eq!((recv.code, recv.error.as_ref()), (None, None));
+	std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn relayed_events_are_recorded_in_the_event_log() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	chain_a.end_finality();
+	let path = std::env::temp_dir().join(format!("hyperspace-events-relay-{}", std::process::id()));
+	let event_log = EventLog::new(path.clone());
+	let journal = Journal { event_log: Some(event_log.clone()), ..Default::default() };
+
+	relay_in_direction(chain_a.clone(), chain_b.clone(), None, None, journal, RelayDirection::AToB)
+		.await
+		.unwrap();
+
+	let records = event_log.records().unwrap();
+	assert!(!records.is_empty());
+	assert!(records.iter().all(|record| record.chain == "mock-a"));
+	assert!(records
+		.iter()
+		.flat_map(|record| &record.events)
+		.any(|event| matches!(event, IbcEvent::SendPacket(_))));
+	std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn watch_only_relay_skips_submission() {
+	let (chain_a, mut chain_b) = setup_chains();
+	chain_b.set_watch_only(true);
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	chain_a.end_finality();
+
+	relay_in_direction(
+		chain_a.clone(),
+		chain_b.clone(),
+		None,
+		None,
+		Journal::default(),
+		RelayDirection::AToB,
+	)
+	.await
+	.unwrap();
+
+	assert!(chain_b.submitted_messages().is_empty());
+	assert!(!check_balance(&chain_b, Some(u128::MAX), None, &Default::default())
+		.await
+		.unwrap());
+}
+
+#[tokio::test]
+async fn client_is_upgraded_once_the_halt_height_is_reached() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	let plan = UpgradePlan { name: "v2".to_string(), height: chain_a.height() };
+	chain_a.set_upgrade_plan(Some(plan));
+	let (client_state, consensus_state) = grandpa_states(Height::new(1, 1), chain_a.timestamp());
+	chain_a.set_upgraded_states(client_state, consensus_state);
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	chain_a.end_finality();
+
+	relay_in_direction(
+		chain_a.clone(),
+		chain_b.clone(),
+		None,
+		None,
+		Journal::default(),
+		RelayDirection::AToB,
+	)
+	.await
+	.unwrap();
+
+	// packet relay stays paused while the client hasn't moved to the new revision
+	let submitted = chain_b.submitted_messages().concat();
+	assert!(!submitted.is_empty());
+	assert!(type_urls(&submitted)
+		.into_iter()
+		.all(|type_url| type_url == "/ibc.core.client.v1.MsgUpgradeClient"));
+}
+
+#[tokio::test]
+async fn relay_resumes_once_the_client_is_upgraded() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.produce_block();
+	let plan = UpgradePlan { name: "v2".to_string(), height: chain_a.height() };
+	chain_a.set_upgrade_plan(Some(plan));
+	let upgraded_height = Height::new(1, chain_a.height().revision_height);
+	let (client_state, consensus_state) = grandpa_states(upgraded_height, chain_a.timestamp());
+	chain_b.set_client_state(chain_a.client_id(), client_state);
+	chain_b.set_consensus_state(chain_a.client_id(), upgraded_height, consensus_state);
+	chain_a.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_a.produce_block();
+	chain_a.end_finality();
+
+	relay_in_direction(
+		chain_a.clone(),
+		chain_b.clone(),
+		None,
+		None,
+		Journal::default(),
+		RelayDirection::AToB,
+	)
+	.await
+	.unwrap();
+
+	let submitted = chain_b.submitted_messages().concat();
+	assert!(type_urls(&submitted).contains(&"/ibc.core.channel.v1.MsgRecvPacket"));
+	assert!(!type_urls(&submitted).contains(&"/ibc.core.client.v1.MsgUpgradeClient"));
+}
+
+#[tokio::test]
+async fn relay_mode_selects_packet_messages() {
+	let (chain_a, mut chain_b) = setup_chains();
+	let received = transfer_packet(1, Height::new(0, 1000));
+	chain_a.send_packet(received.clone());
+	chain_a.produce_block();
+	chain_b.receive_packet(received, ACK.to_vec());
+	chain_b.send_packet(transfer_packet(1, Height::new(0, 1000)));
+	chain_b.produce_block();
+	update_client(&chain_a, &chain_b);
+
+	let (channel_id, port_id) = (ChannelId::new(0), PortId::transfer());
+	chain_b.set_relay_mode(channel_id, port_id.clone(), RelayMode::RecvOnly);
+	let (messages, _) =
+		query_ready_and_timed_out_packets(&chain_b, &chain_a, &mut Default::default())
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+
+	chain_b.set_relay_mode(channel_id, port_id, RelayMode::AckOnly);
+	let (messages, _) =
+		query_ready_and_timed_out_packets(&chain_b, &chain_a, &mut Default::default())
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgAcknowledgement"]);
+}
+
+#[tokio::test]
+async fn contract_port_packets_are_relayed() {
+	let (mut chain_a, mut chain_b) = setup_chains();
+	let port_id = PortId::from_str("wasm.juno1contract").unwrap();
+	let channel_id = ChannelId::new(1);
+	for chain in [&chain_a, &chain_b] {
+		let channel = ChannelEnd::new(
+			State::Open,
+			Order::Unordered,
+			ChannelCounterparty::new(port_id.clone(), Some(channel_id)),
+			vec![ConnectionId::new(0)],
+			Version::new("counter-1".to_string()),
+		);
+		chain.set_channel(port_id.clone(), channel_id, channel);
+	}
+	chain_a.set_channel_whitelist(vec![(channel_id, port_id.clone())]);
+	chain_b.set_channel_whitelist(vec![(channel_id, port_id.clone())]);
+	chain_a.send_packet(Packet {
+		source_port: port_id.clone(),
+		source_channel: channel_id,
+		destination_port: port_id,
+		destination_channel: channel_id,
+		..transfer_packet(1, Height::new(0, 1000))
+	});
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+
+	let (messages, _) =
+		query_ready_and_timed_out_packets(&chain_a, &chain_b, &mut Default::default())
+			.await
+			.unwrap();
+	assert_eq!(type_urls(&messages), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+}
+
+#[tokio::test]
+async fn low_native_balance_is_reported() {
+	let (chain_a, _) = setup_chains();
+	let coin = |denom: &str, amount: u64| PrefixedCoin {
+		denom: PrefixedDenom::from_str(denom).unwrap(),
+		amount: Amount::from(amount),
+	};
+	// assets don't count towards the minimum, whatever their position
+	chain_a.set_balance(vec![coin("UNIT", 100), coin("transfer/channel-0/uatom", 1_000_000)]);
+
+	assert!(check_balance(&chain_a, Some(1_000), None, &Default::default()).await.unwrap());
+	assert!(!check_balance(&chain_a, Some(100), None, &Default::default()).await.unwrap());
+	assert!(!check_balance(&chain_a, None, None, &Default::default()).await.unwrap());
+}
+
+#[tokio::test]
+async fn stuck_packets_are_reported_until_delivered() {
+	let (chain_a, _) = setup_chains();
+	let packet = transfer_packet(1, Height::new(0, 1000));
+	chain_a.send_packet(packet.clone());
+	chain_a.produce_block();
+
+	let mut tracker = StuckPacketTracker::default();
+	let channel = (ChannelId::new(0), PortId::transfer());
+	let stuck = tracker.check(&chain_a, Duration::from_secs(3600)).await.unwrap();
+	assert_eq!(stuck, vec![(channel.clone(), vec![])]);
+	let stuck = tracker.check(&chain_a, Duration::ZERO).await.unwrap();
+	assert_eq!(stuck, vec![(channel.clone(), vec![1])]);
+
+	// the age comes from the block the packet was sent in, not from when it was first seen
+	let mut tracker = StuckPacketTracker::default();
+	let stuck = tracker.check(&chain_a, Duration::from_secs(6)).await.unwrap();
+	assert_eq!(stuck, vec![(channel.clone(), vec![1])]);
+	let stuck = tracker.check(&chain_a, Duration::from_secs(7)).await.unwrap();
+	assert_eq!(stuck, vec![(channel.clone(), vec![])]);
+
+	chain_a.clear_packet_commitment(&packet);
+	chain_a.produce_block();
+	let stuck = tracker.check(&chain_a, Duration::ZERO).await.unwrap();
+	assert_eq!(stuck, vec![(channel, vec![])]);
+}
+
+#[tokio::test]
+async fn recorded_events_are_replayed_to_the_counterparty() {
+	let (mut chain_a, mut chain_b) = setup_chains();
+	let packet = transfer_packet(1, Height::new(0, 1000));
+	chain_a.send_packet(packet.clone());
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+
+	let path = std::env::temp_dir().join(format!("hyperspace-events-{}", std::process::id()));
+	let _ = std::fs::remove_file(&path);
+	let event_log = EventLog::new(path.clone());
+	let events = vec![IbcEvent::SendPacket(SendPacket { height: chain_a.height(), packet })];
+	event_log.append(&EventRecord { chain: "mock-a".to_string(), events }).unwrap();
+	event_log
+		.append(&EventRecord { chain: "unknown".to_string(), events: vec![] })
+		.unwrap();
+
+	replay(event_log.records().unwrap(), &mut chain_a, &mut chain_b).await.unwrap();
+	let submitted = chain_b.submitted_messages().concat();
+	assert_eq!(type_urls(&submitted), vec!["/ibc.core.channel.v1.MsgRecvPacket"]);
+	assert!(chain_a.submitted_messages().is_empty());
+	std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn half_open_channel_handshake_is_resumed() {
+	let (mut chain_a, mut chain_b) = setup_chains();
+	let channel = ChannelEnd::new(
+		State::Init,
+		Order::Unordered,
+		ChannelCounterparty::new(PortId::transfer(), None),
+		vec![ConnectionId::new(0)],
+		Version::ics20(),
+	);
+	chain_a.set_channel(PortId::transfer(), ChannelId::new(1), channel);
+
+	resume_handshakes(&mut chain_a, &mut chain_b, &Journal::default())
+		.await
+		.unwrap();
+	let submitted = chain_b.submitted_messages().concat();
+	assert_eq!(type_urls(&submitted), vec!["/ibc.core.channel.v1.MsgChannelOpenTry"]);
+
+	// chain b has no half-open handshakes of its own
+	resume_handshakes(&mut chain_b, &mut chain_a, &Journal::default())
+		.await
+		.unwrap();
+	assert!(chain_a.submitted_messages().is_empty());
+}
+
+#[tokio::test]
+async fn existing_client_is_reused() {
+	let (chain_a, chain_b) = setup_chains();
+	chain_a.produce_block();
+	update_client(&chain_b, &chain_a);
+	let (client_state, consensus_state) = grandpa_states(chain_a.height(), chain_a.timestamp());
+	chain_a.set_initial_client_state(client_state, consensus_state);
+	assert_eq!(find_client(&chain_a, &chain_b).await.unwrap(), Some(chain_a.client_id()));
+
+	// a client of another chain doesn't match
+	let (client_state, consensus_state) = grandpa_states(Height::new(2, 1), chain_a.timestamp());
+	chain_a.set_initial_client_state(client_state, consensus_state);
+	assert_eq!(find_client(&chain_a, &chain_b).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn open_connection_and_channel_are_reused() {
+	let (chain_a, chain_b) = setup_chains();
+	let connection_id = ConnectionId::new(0);
+	assert_eq!(
+		find_open_connection(&chain_a, &chain_b, Duration::ZERO).await.unwrap(),
+		Some((connection_id.clone(), connection_id.clone()))
+	);
+	// a connection with another delay has to be created
+	assert_eq!(
+		find_open_connection(&chain_a, &chain_b, Duration::from_secs(60)).await.unwrap(),
+		None
+	);
+
+	let mut channel = ChannelConfig {
+		port_id: PortId::transfer(),
+		version: Version::ics20().to_string(),
+		order: "unordered".to_string(),
+	};
+	assert_eq!(
+		find_open_channel(&chain_a, &connection_id, &channel).await.unwrap(),
+		Some((ChannelId::new(0), ChannelId::new(0)))
+	);
+	// a channel with another ordering has to be created
+	channel.order = "ordered".to_string();
+	assert_eq!(find_open_channel(&chain_a, &connection_id, &channel).await.unwrap(), None);
+}
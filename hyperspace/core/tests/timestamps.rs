@@ -0,0 +1,56 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use primitives::time::{
+	elapsed_nanos, millis_to_nanos, secs_to_nanos, timestamp_from_millis, NANOS_PER_MILLI,
+};
+use std::time::Duration;
+
+/// 2038-01-19T03:14:08Z, the first second that doesn't fit in a signed 32 bit unix timestamp.
+const Y2038_SECS: u64 = 1 << 31;
+
+#[test]
+fn timestamps_past_2038_are_converted() {
+	let millis = Y2038_SECS * 1000 + 1;
+	let timestamp = timestamp_from_millis(millis).unwrap();
+	assert_eq!(timestamp.nanoseconds(), Y2038_SECS * 1_000_000_000 + NANOS_PER_MILLI);
+	let datetime = timestamp.into_datetime().unwrap();
+	assert_eq!(datetime.unix_timestamp(), Y2038_SECS as i64);
+	assert_eq!(datetime.millisecond(), 1);
+	assert!(timestamp.into_tm_time().is_some());
+	assert_eq!(secs_to_nanos(Y2038_SECS), Some(Y2038_SECS * 1_000_000_000));
+}
+
+#[test]
+fn overflowing_timestamps_are_rejected() {
+	let max_millis = u64::MAX / NANOS_PER_MILLI;
+	assert_eq!(millis_to_nanos(max_millis), Some(max_millis * NANOS_PER_MILLI));
+	assert!(timestamp_from_millis(max_millis).is_some());
+	assert_eq!(millis_to_nanos(max_millis + 1), None);
+	assert!(timestamp_from_millis(max_millis + 1).is_none());
+	assert_eq!(secs_to_nanos(u64::MAX / 1_000_000_000 + 1), None);
+}
+
+#[test]
+fn zero_timestamp_is_unset() {
+	assert!(timestamp_from_millis(0).is_none());
+}
+
+#[test]
+fn elapsed_time_keeps_nanosecond_precision() {
+	assert_eq!(elapsed_nanos(1, 2), Duration::from_nanos(1));
+	assert_eq!(elapsed_nanos(u64::MAX - 1, u64::MAX), Duration::from_nanos(1));
+	// timeouts before the reference time have no time left
+	assert_eq!(elapsed_nanos(2, 1), Duration::ZERO);
+}
@@ -92,6 +92,8 @@ impl LightClientMetrics {
 pub struct Metrics {
 	/// Total number of "send packet" events received.
 	pub number_of_received_send_packets: Counter<U64>,
+	/// Total number of "send packet" events received, labelled by source port.
+	pub number_of_received_send_packets_by_port: CounterVec<U64>,
 	/// Total number of "receive packet" events received.
 	pub number_of_received_receive_packets: Counter<U64>,
 	/// Total number of "acknowledge packet" events received.
@@ -115,10 +117,20 @@ pub struct Metrics {
 	pub number_of_undelivered_packets: Gauge<U64>,
 	/// Number of undelivered acknowledgements over time.
 	pub number_of_undelivered_acknowledgements: Gauge<U64>,
+	/// Number of packets stuck for longer than the configured age, labelled by channel.
+	pub number_of_stuck_packets: GaugeVec<U64>,
 	/// Gas cost for every sent tx bundle.
 	pub gas_cost_for_sent_tx_bundle: Histogram,
 	/// Transaction length (in bytes) for every sent tx bundle.
 	pub transaction_length_for_sent_tx_bundle: Histogram,
+	/// Total gas used by submitted transactions, labelled by the source channel of the packets.
+	pub gas_used_by_channel: CounterVec<F64>,
+	/// Total fees paid for submitted transactions, labelled by the source channel of the packets.
+	pub fees_paid_by_channel: CounterVec<F64>,
+	/// Balance of the relayer account, labelled by denom.
+	pub relayer_balance: GaugeVec<F64>,
+	/// Whether the relayer balance is below its configured minimum.
+	pub relayer_balance_low: Gauge<U64>,
 
 	/// Light client height.
 	pub light_client_height: HashMap<ClientId, LightClientMetrics>,
@@ -137,6 +149,8 @@ pub struct Metrics {
 	pub sent_timeout_packet_time: Histogram,
 	/// Average time between client updates.
 	pub sent_update_client_time: Histogram,
+	/// Time spent in each stage of relaying a packet, labelled by source channel and stage.
+	pub packet_relay_stage_time: HistogramVec,
 
 	/// Metrics prefix.
 	pub prefix: String,
@@ -152,6 +166,16 @@ impl Metrics {
 				))?,
 				registry,
 			)?,
+			number_of_received_send_packets_by_port: register(
+				CounterVec::new(
+					Opts::new(
+						&format!("hyperspace_{}_number_of_send_packet_events_by_port", prefix),
+						"Total number of 'send packet' events by source port.",
+					),
+					&["port"],
+				)?,
+				registry,
+			)?,
 			number_of_received_receive_packets: register(
 				Counter::with_opts(Opts::new(
 					&format!("hyperspace_{}_number_of_receive_packet_events", prefix),
@@ -210,6 +234,16 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			number_of_stuck_packets: register(
+				GaugeVec::new(
+					Opts::new(
+						&format!("hyperspace_{}_number_of_stuck_packets", prefix),
+						"Number of packets stuck for longer than the configured age by channel",
+					),
+					&["channel"],
+				)?,
+				registry,
+			)?,
 			gas_cost_for_sent_tx_bundle: register(
 				Histogram::with_opts(
 					HistogramOpts::new(
@@ -230,6 +264,43 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			gas_used_by_channel: register(
+				CounterVec::new(
+					Opts::new(
+						&format!("hyperspace_{}_gas_used_by_channel", prefix),
+						"Total gas used by submitted transactions by packet source channel",
+					),
+					&["channel"],
+				)?,
+				registry,
+			)?,
+			fees_paid_by_channel: register(
+				CounterVec::new(
+					Opts::new(
+						&format!("hyperspace_{}_fees_paid_by_channel", prefix),
+						"Total fees paid for submitted transactions by packet source channel",
+					),
+					&["channel"],
+				)?,
+				registry,
+			)?,
+			relayer_balance: register(
+				GaugeVec::new(
+					Opts::new(
+						&format!("hyperspace_{}_relayer_balance", prefix),
+						"Balance of the relayer account by denom",
+					),
+					&["denom"],
+				)?,
+				registry,
+			)?,
+			relayer_balance_low: register(
+				Gauge::new(
+					&format!("hyperspace_{}_relayer_balance_low", prefix),
+					"Whether the relayer balance is below its configured minimum",
+				)?,
+				registry,
+			)?,
 			light_client_height: HashMap::new(),
 			send_packet_event_time: register(
 				Histogram::with_opts(
@@ -301,6 +372,17 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			packet_relay_stage_time: register(
+				HistogramVec::new(
+					HistogramOpts::new(
+						&format!("hyperspace_{}_packet_relay_stage_time", prefix),
+						"Time it takes to query proofs for, broadcast and commit a packet",
+					)
+					.buckets(vec![1.0, 10.0, 100.0, 1000.0, 10000.0, 100000.0, 1000000.0]),
+					&["channel", "stage"],
+				)?,
+				registry,
+			)?,
 			prefix: prefix.to_string(),
 		})
 	}
@@ -17,6 +17,12 @@ use ibc::{
 	core::{
 		ics04_channel::{
 			events::{TimeoutOnClosePacket, TimeoutPacket},
+			msgs::{
+				acknowledgement::{MsgAcknowledgement, TYPE_URL as ACKNOWLEDGEMENT_TYPE_URL},
+				recv_packet::{MsgRecvPacket, TYPE_URL as RECV_PACKET_TYPE_URL},
+				timeout::{MsgTimeout, TYPE_URL as TIMEOUT_TYPE_URL},
+				timeout_on_close::{MsgTimeoutOnClose, TYPE_URL as TIMEOUT_ON_CLOSE_TYPE_URL},
+			},
 			packet::{Packet, Sequence},
 		},
 		ics24_host::identifier::{ChannelId, PortId},
@@ -31,6 +37,7 @@ use std::{
 	sync::{Arc, Mutex},
 	time::Instant,
 };
+use tendermint_proto::Protobuf;
 
 #[derive(Eq, PartialEq, Hash)]
 pub struct PacketId {
@@ -59,10 +66,13 @@ pub struct MetricsHandler {
 	last_sent_acknowledgment_time: PacketMap,
 	last_sent_timeout_packet_time: PacketMap,
 	last_update_client_time: Arc<Mutex<Option<Instant>>>,
+	last_proven_packet_time: PacketMap,
+	last_broadcast_packet_time: PacketMap,
 
 	counterparty_last_sent_packet_time: Option<PacketMap>,
 	counterparty_last_sent_acknowledgment_time: Option<PacketMap>,
 	counterparty_last_sent_timeout_packet_time: Option<PacketMap>,
+	counterparty_last_broadcast_packet_time: Option<PacketMap>,
 }
 
 impl MetricsHandler {
@@ -74,9 +84,12 @@ impl MetricsHandler {
 			last_sent_acknowledgment_time: Arc::new(Mutex::new(HashMap::new())),
 			last_sent_timeout_packet_time: Arc::new(Mutex::new(HashMap::new())),
 			last_update_client_time: Arc::new(Mutex::new(None)),
+			last_proven_packet_time: Arc::new(Mutex::new(HashMap::new())),
+			last_broadcast_packet_time: Arc::new(Mutex::new(HashMap::new())),
 			counterparty_last_sent_packet_time: None,
 			counterparty_last_sent_acknowledgment_time: None,
 			counterparty_last_sent_timeout_packet_time: None,
+			counterparty_last_broadcast_packet_time: None,
 		}
 	}
 
@@ -85,6 +98,10 @@ impl MetricsHandler {
 			match event {
 				IbcEvent::SendPacket(packet) => {
 					self.metrics.number_of_received_send_packets.inc();
+					self.metrics
+						.number_of_received_send_packets_by_port
+						.with_label_values(&[packet.packet.source_port.as_str()])
+						.inc();
 					let packet_id = packet.packet.clone().into();
 					self.last_sent_packet_time.lock().unwrap().insert(packet_id, Instant::now());
 				},
@@ -95,6 +112,11 @@ impl MetricsHandler {
 						&self.counterparty_last_sent_packet_time,
 						&self.metrics.sent_packet_time,
 					);
+					let broadcast_time = self
+						.counterparty_last_broadcast_packet_time
+						.as_ref()
+						.and_then(|map| map.lock().unwrap().remove(&packet.packet.clone().into()));
+					self.observe_packet_stage(&packet.packet, "commit", broadcast_time);
 				},
 				IbcEvent::WriteAcknowledgement(packet) => {
 					let packet_id = packet.packet.clone().into();
@@ -152,6 +174,16 @@ impl MetricsHandler {
 							self.metrics.counterparty_number_of_received_packets().get(),
 					);
 					self.metrics.number_of_sent_packets.inc();
+					if let Ok(msg) = MsgRecvPacket::decode_vec(&message.value) {
+						let packet_id = PacketId::from(msg.packet.clone());
+						let sent_time =
+							self.last_sent_packet_time.lock().unwrap().get(&packet_id).copied();
+						self.observe_packet_stage(&msg.packet, "proof_query", sent_time);
+						self.last_proven_packet_time
+							.lock()
+							.unwrap()
+							.insert(packet_id, Instant::now());
+					}
 				},
 				_ => (),
 			}
@@ -172,6 +204,37 @@ impl MetricsHandler {
 			Some(self.last_sent_acknowledgment_time.clone());
 		counterparty.counterparty_last_sent_timeout_packet_time =
 			Some(self.last_sent_timeout_packet_time.clone());
+
+		self.counterparty_last_broadcast_packet_time =
+			Some(counterparty.last_broadcast_packet_time.clone());
+		counterparty.counterparty_last_broadcast_packet_time =
+			Some(self.last_broadcast_packet_time.clone());
+	}
+
+	/// Records that the packets received by `messages` were submitted to the counterparty.
+	pub fn handle_broadcast(&self, messages: &[Any]) {
+		for message in messages.iter().filter(|msg| msg.type_url == RECV_PACKET_TYPE_URL) {
+			if let Ok(msg) = MsgRecvPacket::decode_vec(&message.value) {
+				let packet_id = PacketId::from(msg.packet.clone());
+				let proven_time = self.last_proven_packet_time.lock().unwrap().remove(&packet_id);
+				self.observe_packet_stage(&msg.packet, "broadcast", proven_time);
+				self.last_broadcast_packet_time
+					.lock()
+					.unwrap()
+					.insert(packet_id, Instant::now());
+			}
+		}
+	}
+
+	/// Forgets when the packets received by `messages` were proven, for messages that won't be
+	/// broadcast.
+	pub fn discard_proven_packets(&self, messages: &[Any]) {
+		let mut proven_times = self.last_proven_packet_time.lock().unwrap();
+		for message in messages.iter().filter(|msg| msg.type_url == RECV_PACKET_TYPE_URL) {
+			if let Ok(msg) = MsgRecvPacket::decode_vec(&message.value) {
+				proven_times.remove(&PacketId::from(msg.packet));
+			}
+		}
 	}
 
 	pub async fn handle_timeouts(&self, timeouts: &[Any]) {
@@ -191,6 +254,36 @@ impl MetricsHandler {
 		self.metrics.transaction_length_for_sent_tx_bundle.observe(batch_size as f64);
 	}
 
+	/// Splits the gas used and fee paid by a submitted transaction evenly between its messages,
+	/// accounting them to the source channel of their packet.
+	pub fn handle_transaction_cost(&self, gas_used: u64, fee: u128, messages: &[Any]) {
+		if messages.is_empty() {
+			return
+		}
+		let share = messages.len() as f64;
+		for message in messages {
+			let channel = packet_source_channel(message).unwrap_or_else(|| "none".to_string());
+			self.metrics
+				.gas_used_by_channel
+				.with_label_values(&[&channel])
+				.inc_by(gas_used as f64 / share);
+			self.metrics
+				.fees_paid_by_channel
+				.with_label_values(&[&channel])
+				.inc_by(fee as f64 / share);
+		}
+	}
+
+	/// Observes the time `packet` spent in `stage`, which started at `started`.
+	fn observe_packet_stage(&self, packet: &Packet, stage: &str, started: Option<Instant>) {
+		if let Some(started) = started {
+			self.metrics
+				.packet_relay_stage_time
+				.with_label_values(&[&packet.source_channel.to_string(), stage])
+				.observe(started.elapsed().as_millis() as f64);
+		}
+	}
+
 	pub fn observe_last_packet_time(
 		&self,
 		packet: &Packet,
@@ -211,6 +304,24 @@ impl MetricsHandler {
 	}
 }
 
+/// Returns the source `port/channel` of the packet carried by a packet message.
+fn packet_source_channel(message: &Any) -> Option<String> {
+	let packet = message_packet(message)?;
+	Some(format!("{}/{}", packet.source_port, packet.source_channel))
+}
+
+/// Returns the packet carried by a packet message, `None` for any other message.
+pub fn message_packet(message: &Any) -> Option<Packet> {
+	let packet = match message.type_url.as_str() {
+		RECV_PACKET_TYPE_URL => MsgRecvPacket::decode_vec(&message.value).ok()?.packet,
+		ACKNOWLEDGEMENT_TYPE_URL => MsgAcknowledgement::decode_vec(&message.value).ok()?.packet,
+		TIMEOUT_TYPE_URL => MsgTimeout::decode_vec(&message.value).ok()?.packet,
+		TIMEOUT_ON_CLOSE_TYPE_URL => MsgTimeoutOnClose::decode_vec(&message.value).ok()?.packet,
+		_ => return None,
+	};
+	Some(packet)
+}
+
 fn observe_delta_time(maybe_time: &mut Option<Instant>, time_metrics: &Histogram) {
 	let now = Instant::now();
 	if let Some(last_time) = maybe_time {
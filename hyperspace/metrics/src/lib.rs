@@ -41,6 +41,27 @@ pub fn register<T: Clone + Collector + 'static>(
 	Ok(metric)
 }
 
+/// Registers the constant `hyperspace_build_info` gauge, labelled with the version, git hash and
+/// features of the running relayer.
+pub fn register_build_info(
+	registry: &Registry,
+	version: &str,
+	git_hash: &str,
+	features: &str,
+) -> Result<(), PrometheusError> {
+	let build_info = register(
+		Gauge::<U64>::with_opts(
+			Opts::new("hyperspace_build_info", "Version of the running relayer build")
+				.const_label("version", version)
+				.const_label("git_hash", git_hash)
+				.const_label("features", features),
+		)?,
+		registry,
+	)?;
+	build_info.set(1);
+	Ok(())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
 	/// Hyper internal error.
@@ -112,3 +133,13 @@ async fn init_prometheus_with_listener(
 
 	result
 }
+
+/// Fetches the metrics exposed by the prometheus server at `prometheus_addr`.
+pub async fn scrape(prometheus_addr: SocketAddr) -> Result<String, Error> {
+	let uri = format!("http://{}/metrics", prometheus_addr)
+		.parse::<hyper::Uri>()
+		.map_err(hyper::http::Error::from)?;
+	let response = hyper::Client::new().get(uri).await?;
+	let body = hyper::body::to_bytes(response.into_body()).await?;
+	Ok(String::from_utf8_lossy(&body).into_owned())
+}
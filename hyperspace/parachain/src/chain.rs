@@ -18,7 +18,7 @@ use std::{collections::BTreeMap, fmt::Display, pin::Pin, time::Duration};
 
 use beefy_gadget_rpc::BeefyApiClient;
 use finality_grandpa::BlockNumberOps;
-use futures::{Stream, StreamExt, TryFutureExt};
+use futures::{Stream, StreamExt};
 use grandpa_light_client_primitives::{FinalityProof, ParachainHeaderProofs};
 use ibc_proto::google::protobuf::Any;
 use sp_runtime::{
@@ -30,18 +30,18 @@ use subxt::tx::{BaseExtrinsicParamsBuilder, ExtrinsicParams};
 use transaction_payment_rpc::TransactionPaymentApiClient;
 use transaction_payment_runtime_api::RuntimeDispatchInfo;
 
-use primitives::{Chain, IbcProvider, MisbehaviourHandler};
+use primitives::{Chain, IbcProvider, MisbehaviourHandler, TransactionCost, UpgradePlan};
 
-use super::{error::Error, signer::ExtrinsicSigner, ParachainClient};
+use super::{error::Error, signer::ExtrinsicSigner, ParachainClient, SigningKey};
 use crate::{
 	config,
+	finality_protocol::FinalityEvent,
 	parachain::{
 		api,
 		api::runtime_types::{frame_system::Phase, pallet_ibc::Any as RawAny},
 		UncheckedExtrinsic,
 	},
-	provider::TransactionId,
-	utils::MetadataIbcEventWrapper,
+	utils::{coalesce_within, MetadataIbcEventWrapper},
 	FinalityProtocol,
 };
 use finality_grandpa_rpc::GrandpaApiClient;
@@ -102,25 +102,25 @@ where
 		self.max_extrinsic_weight
 	}
 
-	async fn estimate_weight(&self, messages: Vec<Any>) -> Result<u64, Self::Error> {
+	async fn estimate_weight(&self, messages: &[Any]) -> Result<u64, Self::Error> {
 		let extrinsic = {
 			// todo: put this in utils
-			let signer = ExtrinsicSigner::<T, Self>::new(
-				self.key_store.clone(),
-				self.key_type_id.clone(),
-				self.public_key.clone(),
-			);
+			let SigningKey { public_key, signer } = self.signing_key()?;
+			let signer = ExtrinsicSigner::<T, Self>::new(public_key, signer);
 
 			let messages = messages
-				.into_iter()
-				.map(|msg| RawAny { type_url: msg.type_url.as_bytes().to_vec(), value: msg.value })
+				.iter()
+				.map(|msg| RawAny {
+					type_url: msg.type_url.as_bytes().to_vec(),
+					value: msg.value.clone(),
+				})
 				.collect::<Vec<_>>();
 
 			let tx_params = PolkadotExtrinsicParamsBuilder::new()
 				.tip(PlainTip::new(100_000))
 				.era(Era::Immortal, self.para_client.genesis_hash());
 			let call = api::tx().ibc().deliver(messages);
-			self.para_client.tx().create_signed(&call, &signer, tx_params.into()).await?
+			signer.create_signed(&self.para_client, &call, tx_params.into()).await?
 		};
 		let dispatch_info =
 			TransactionPaymentApiClient::<sp_core::H256, RuntimeDispatchInfo<u128>>::query_info(
@@ -136,7 +136,9 @@ where
 	async fn finality_notifications(
 		&self,
 	) -> Pin<Box<dyn Stream<Item = <Self as IbcProvider>::FinalityEvent> + Send + Sync>> {
-		match self.finality_protocol {
+		let stream: Pin<Box<dyn Stream<Item = Self::FinalityEvent> + Send + Sync>> = match self
+			.finality_protocol
+		{
 			FinalityProtocol::Grandpa => {
 				let subscription =
 					GrandpaApiClient::<JustificationNotification, sp_core::H256, u32>::subscribe_justifications(
@@ -200,19 +202,36 @@ where
 
 				Box::pin(Box::new(stream))
 			},
+		};
+
+		match self.finality_batch_window {
+			// the latest notification proves everything before it, except that beefy commitments
+			// signed by an outgoing validator set have to be relayed before the next set's
+			Some(window) =>
+				Box::pin(coalesce_within(stream, window, |previous, next| match (previous, next) {
+					(FinalityEvent::Beefy(previous), FinalityEvent::Beefy(next)) =>
+						previous.commitment.validator_set_id == next.commitment.validator_set_id,
+					_ => true,
+				})),
+			None => stream,
 		}
 	}
 
-	async fn submit(&self, messages: Vec<Any>) -> Result<Self::TransactionId, Error> {
+	async fn submit(&self, messages: &[Any]) -> Result<Self::TransactionId, Error> {
 		let messages = messages
-			.into_iter()
-			.map(|msg| RawAny { type_url: msg.type_url.as_bytes().to_vec(), value: msg.value })
+			.iter()
+			.map(|msg| RawAny {
+				type_url: msg.type_url.as_bytes().to_vec(),
+				value: msg.value.clone(),
+			})
 			.collect::<Vec<_>>();
 
 		let call = api::tx().ibc().deliver(messages);
-		let (ext_hash, block_hash) = self.submit_call(call).await?;
+		self.submit_call(call).await
+	}
 
-		Ok(TransactionId { ext_hash, block_hash })
+	fn transaction_cost(&self, tx_id: &Self::TransactionId) -> TransactionCost {
+		tx_id.cost
 	}
 
 	async fn query_client_message(&self, update: UpdateClient) -> Result<AnyClientMessage, Error> {
@@ -319,6 +338,20 @@ where
 
 		Err(Error::from("No client message found".to_owned()))
 	}
+
+	async fn detect_scheduled_upgrade(&self) -> Option<UpgradePlan> {
+		// Parachain runtime upgrades are enacted without halting block production and leave the
+		// counterparty light client untouched, so there is never a halt height to wait for.
+		None
+	}
+
+	fn heartbeat_interval(&self) -> Option<Duration> {
+		self.heartbeat_interval
+	}
+
+	fn full_scan_interval(&self) -> Option<u64> {
+		self.full_scan_interval
+	}
 }
 
 #[async_trait::async_trait]
@@ -432,15 +465,20 @@ where
 						second_finality_proof: trusted_finality_proof,
 					});
 
-					counterparty
-						.submit(vec![MsgUpdateAnyClient::<LocalClientTypes>::new(
+					let result = counterparty
+						.submit(&[MsgUpdateAnyClient::<LocalClientTypes>::new(
 							self.client_id(),
 							AnyClientMessage::Grandpa(misbehaviour.clone()),
 							counterparty.account_id(),
 						)
 						.to_any()])
-						.map_err(|e| anyhow!("Failed to submit misbehaviour report: {:?}", e))
-						.await?;
+						.await
+						.map_err(anyhow::Error::from);
+					match result {
+						Ok(_) => {},
+						Err(e) if primitives::error::Error::is_dry_run(&e) => {},
+						Err(e) => return Err(e.context("Failed to submit misbehaviour report")),
+					}
 				}
 			},
 			_ => {},
@@ -24,10 +24,21 @@ pub type CustomExtrinsicParams<T> = <<T as subxt::Config>::ExtrinsicParams as Ex
 /// runtimes into the transactions signed by this crate.
 #[async_trait]
 pub trait Config: subxt::Config + Sized {
-	/// Asset Id type used by the parachain runtime
-	type AssetId: codec::Codec + serde::Serialize + Send + Sync + 'static;
-	/// use the subxt client to fetch any neccessary data needed for the extrinsic metadata.
+	/// Asset Id type used by the parachain runtime, convertible to the `u128` asset ids of the
+	/// generated runtime api
+	type AssetId: codec::Codec
+		+ serde::Serialize
+		+ Ord
+		+ Clone
+		+ From<u128>
+		+ Into<u128>
+		+ Send
+		+ Sync
+		+ 'static;
+	/// use the subxt client to fetch any neccessary data needed for the extrinsic metadata,
+	/// `tip` is the configured tip to add to the extrinsic.
 	async fn custom_extrinsic_params(
 		client: &OnlineClient<Self>,
+		tip: u128,
 	) -> Result<CustomExtrinsicParams<Self>, subxt::Error>;
 }
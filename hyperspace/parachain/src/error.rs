@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use ibc::{core::ics02_client, timestamp::ParseTimestampError};
+use sp_core::H256;
 use sp_runtime::traits::BlakeTwo256;
 use sp_trie::TrieError;
 use std::num::ParseIntError;
@@ -78,8 +79,20 @@ pub enum Error {
 	/// Some error in relation to handling metadata
 	#[error("Metadat error: {0}")]
 	MetadataError(#[from] MetadataError),
+	/// The verification node reports a different relay chain header than the primary node
+	#[error("Relay chain block {number} is {primary:?} on the primary node but {verified:?} on the verification node")]
+	HeaderMismatch { number: u32, primary: H256, verified: H256 },
 	#[error("Jsonrpsee error: {0}")]
 	JosnrpseeError(#[from] jsonrpsee::core::Error),
+	/// The node has pruned the state at the queried height
+	#[error("State at height {0} has been pruned by the node")]
+	HeightPruned(u64),
+	/// Relayer primitives error
+	#[error("{0}")]
+	Primitives(#[from] primitives::error::Error),
+	/// The client was configured without a signing key
+	#[error("{0} is watch-only, no signing key is configured")]
+	NoSigner(String),
 }
 
 impl From<String> for Error {
@@ -24,9 +24,14 @@ use grandpa_light_client_primitives::{
 	ParachainHeadersWithFinalityProof,
 };
 use ibc::{
-	core::ics02_client::{client_state::ClientState as _, msgs::update_client::MsgUpdateAnyClient},
+	core::{
+		ics02_client::{client_state::ClientState as _, msgs::update_client::MsgUpdateAnyClient},
+		ics24_host::identifier::ClientId,
+	},
 	events::IbcEvent,
+	signer::Signer,
 	tx_msg::Msg,
+	Height,
 };
 use ibc_proto::google::protobuf::Any;
 use ibc_rpc::{BlockNumberOrHash, IbcApiClient};
@@ -75,7 +80,7 @@ impl FinalityProtocol {
 		source: &mut ParachainClient<T>,
 		finality_event: FinalityEvent,
 		counterparty: &C,
-	) -> Result<(Any, Vec<IbcEvent>, UpdateType), anyhow::Error>
+	) -> Result<(Vec<Any>, Vec<IbcEvent>, UpdateType), anyhow::Error>
 	where
 		T: config::Config + Send + Sync,
 		C: Chain,
@@ -114,7 +119,7 @@ pub async fn query_latest_ibc_events_with_beefy<T, C>(
 	source: &mut ParachainClient<T>,
 	finality_event: FinalityEvent,
 	counterparty: &C,
-) -> Result<(Any, Vec<IbcEvent>, UpdateType), anyhow::Error>
+) -> Result<(Vec<Any>, Vec<IbcEvent>, UpdateType), anyhow::Error>
 where
 	T: config::Config + Send + Sync,
 	C: Chain,
@@ -290,6 +295,8 @@ where
 	let mmr_update = source
 		.query_beefy_mmr_update_proof(signed_commitment, &beefy_client_state)
 		.await?;
+	let (parent_number, parent_hash) = mmr_update.latest_mmr_leaf.parent_number_and_hash;
+	source.verify_relay_header(parent_number, parent_hash).await?;
 
 	let update_header = {
 		let msg = MsgUpdateAnyClient::<LocalClientTypes> {
@@ -304,7 +311,7 @@ where
 		Any { value, type_url: msg.type_url() }
 	};
 
-	Ok((update_header, events, update_type))
+	Ok((vec![update_header], events, update_type))
 }
 
 /// Query the latest events that have been finalized by the GRANDPA finality protocol.
@@ -312,7 +319,7 @@ pub async fn query_latest_ibc_events_with_grandpa<T, C>(
 	source: &mut ParachainClient<T>,
 	finality_event: FinalityEvent,
 	counterparty: &C,
-) -> Result<(Any, Vec<IbcEvent>, UpdateType), anyhow::Error>
+) -> Result<(Vec<Any>, Vec<IbcEvent>, UpdateType), anyhow::Error>
 where
 	T: config::Config + Send + Sync,
 	C: Chain,
@@ -425,7 +432,7 @@ where
 		})
 		.collect::<BTreeSet<_>>();
 
-	let events: Vec<IbcEvent> = events.into_values().flatten().collect();
+	let mut events: Vec<IbcEvent> = events.into_values().flatten().collect();
 
 	if timeout_update_required {
 		let max_height_for_timeouts = max_height_for_timeouts.unwrap();
@@ -439,7 +446,7 @@ where
 		headers_with_events.insert(T::BlockNumber::from(latest_finalized_block));
 	}
 
-	let cs = grandpa_light_client_primitives::ClientState::<T::Hash> {
+	let mut cs = grandpa_light_client_primitives::ClientState::<T::Hash> {
 		current_authorities: client_state.current_authorities.clone(),
 		current_set_id: client_state.current_set_id,
 		latest_relay_hash: T::Hash::from(client_state.latest_relay_hash.as_fixed_bytes().clone()),
@@ -447,27 +454,91 @@ where
 		latest_para_height: client_state.latest_para_height,
 		para_id: client_state.para_id,
 	};
-	let ParachainHeadersWithFinalityProof { finality_proof, parachain_headers } = prover
-		.query_finalized_parachain_headers_with_proof(
-			&cs,
-			justification.commit.target_number.into(),
-			headers_with_events.into_iter().collect(),
-		)
-		.await?;
+	let headers_with_events = headers_with_events.into_iter().collect::<Vec<_>>();
+	// parachain heights proven by the client updates
+	let mut proven_heights = BTreeSet::new();
+
+	// The prover stops at the first authority set change, since justifications of later sets
+	// can't be verified before the counterparty rotates its authorities. When the client is
+	// behind by several set changes, the updates up to each change are submitted first.
+	let mut update_headers = vec![];
+	let (finality_proof, parachain_headers, target) = loop {
+		let ParachainHeadersWithFinalityProof { finality_proof, parachain_headers } = prover
+			.query_finalized_parachain_headers_with_proof::<T::Header>(
+				&cs,
+				justification.commit.target_number.into(),
+				headers_with_events.clone(),
+			)
+			.await?;
 
-	let target =
+		let target =
+			source.relay_client.rpc().header(Some(finality_proof.block)).await?.ok_or_else(
+				|| {
+					Error::from(
+						"Could not find relay chain header for justification target".to_string(),
+					)
+				},
+			)?;
 		source
-			.relay_client
-			.rpc()
-			.header(Some(finality_proof.block))
-			.await?
-			.ok_or_else(|| {
-				Error::from(
-					"Could not find relay chain header for justification target".to_string(),
-				)
-			})?;
-
-	let authority_set_changed_scheduled = find_scheduled_change(&target).is_some();
+			.verify_relay_header(u32::from(*target.number()), target.hash().into())
+			.await?;
+
+		let mut para_heights = vec![];
+		for relay_hash in parachain_headers.keys() {
+			let para_header = prover.query_parachain_head_at(*relay_hash).await?;
+			para_heights.push(u32::from(*para_header.number()));
+		}
+		proven_heights.extend(para_heights.iter().map(|height| *height as u64));
+
+		let target_number = u32::from(*target.number());
+		let scheduled_change = match find_scheduled_change(&target) {
+			Some(scheduled_change) if target_number < justification.commit.target_number =>
+				scheduled_change,
+			_ => break (finality_proof, parachain_headers, target),
+		};
+
+		log::info!(
+			"Updating client of {} on {} up to the authority set change at relay block {}",
+			source.name(),
+			counterparty.name(),
+			target_number
+		);
+		let grandpa_header = GrandpaHeader {
+			finality_proof: finality_proof.into(),
+			parachain_headers: parachain_headers.into(),
+		};
+		update_headers.push(grandpa_update_message(
+			source.client_id(),
+			grandpa_header,
+			counterparty.account_id(),
+		));
+
+		// mirror the state transition of the counterparty's light client
+		cs.latest_relay_hash = target.hash();
+		cs.latest_relay_height = target_number;
+		if let Some(max_height) = para_heights.into_iter().max() {
+			cs.latest_para_height = max_height;
+		}
+		cs.current_set_id += 1;
+		cs.current_authorities = scheduled_change.next_authorities;
+	};
+
+	// With asynchronous backing, parachain blocks that were never the parachain head are proven
+	// through the first parachain head that succeeds them, so their events must be proven at the
+	// height of that head.
+	for event in events.iter_mut() {
+		if matches!(event, IbcEvent::AppModule(_) | IbcEvent::Empty(_) | IbcEvent::ChainError(_)) {
+			continue
+		}
+		let height = event.height();
+		if let Some(proven_height) = proven_heights.range(height.revision_height..).next() {
+			event.set_height(Height::new(height.revision_number, *proven_height));
+		}
+	}
+
+	// the updates up to earlier set changes must be submitted too
+	let authority_set_changed_scheduled =
+		find_scheduled_change(&target).is_some() || !update_headers.is_empty();
 	// if validator set has changed this is a mandatory update
 	let update_type =
 		match authority_set_changed_scheduled || timeout_update_required || is_update_required {
@@ -479,16 +550,26 @@ where
 		finality_proof: finality_proof.into(),
 		parachain_headers: parachain_headers.into(),
 	};
+	update_headers.push(grandpa_update_message(
+		source.client_id(),
+		grandpa_header,
+		counterparty.account_id(),
+	));
 
-	let update_header = {
-		let msg = MsgUpdateAnyClient::<LocalClientTypes> {
-			client_id: source.client_id(),
-			client_message: AnyClientMessage::Grandpa(ClientMessage::Header(grandpa_header)),
-			signer: counterparty.account_id(),
-		};
-		let value = msg.encode_vec();
-		Any { value, type_url: msg.type_url() }
-	};
+	Ok((update_headers, events, update_type))
+}
 
-	Ok((update_header, events, update_type))
+/// Wraps the GRANDPA header in a message updating the client with the given id.
+fn grandpa_update_message(
+	client_id: ClientId,
+	grandpa_header: GrandpaHeader,
+	signer: Signer,
+) -> Any {
+	let msg = MsgUpdateAnyClient::<LocalClientTypes> {
+		client_id,
+		client_message: AnyClientMessage::Grandpa(ClientMessage::Header(grandpa_header)),
+		signer,
+	};
+	let value = msg.encode_vec();
+	Any { value, type_url: msg.type_url() }
 }
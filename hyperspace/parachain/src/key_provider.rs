@@ -12,10 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use sp_core::crypto::Ss58Codec;
-use sp_runtime::traits::IdentifyAccount;
+use sp_runtime::{traits::IdentifyAccount, AccountId32};
 
 use crate::config;
 use primitives::KeyProvider;
@@ -24,12 +24,22 @@ use super::ParachainClient;
 
 impl<T: config::Config> KeyProvider for ParachainClient<T> {
 	fn account_id(&self) -> ibc::signer::Signer {
-		let hex_string = self
-			.public_key
-			.clone()
-			.into_account()
-			.to_ss58check_with_version(self.ss58_version);
+		// messages built by a watch-only client are never submitted, the zero account stands in
+		// for the signer
+		let account = match self.signing_key() {
+			Ok(signing_key) => signing_key.public_key.into_account(),
+			Err(_) => AccountId32::new([0; 32]),
+		};
+		let hex_string = account.to_ss58check_with_version(self.ss58_version);
 
 		ibc::signer::Signer::from_str(&hex_string).expect("Account Id should be valid")
 	}
+
+	fn signer(&self) -> Option<Arc<dyn primitives::signer::Signer>> {
+		self.signing_key().ok().map(|signing_key| signing_key.signer)
+	}
+
+	fn is_watch_only(&self) -> bool {
+		self.signing_key().is_err()
+	}
 }
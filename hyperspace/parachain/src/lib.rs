@@ -14,7 +14,12 @@
 
 #![allow(clippy::all)]
 
-use std::{collections::BTreeMap, str::FromStr, sync::Arc, time::Duration};
+use std::{
+	collections::BTreeMap,
+	str::FromStr,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
 
 pub mod chain;
 pub mod config;
@@ -35,10 +40,23 @@ use serde::Deserialize;
 
 use beefy_light_client_primitives::{ClientState, MmrUpdateProof};
 use beefy_prover::Prover;
-use ibc::core::ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId};
+use ibc::{
+	applications::transfer::PrefixedDenom,
+	core::ics24_host::{
+		identifier::{ChannelId, ClientId, ConnectionId, PortId},
+		path::ClientStatePath,
+	},
+	Height,
+};
+use ibc_rpc::{IbcApiClient, UndeliveredSequences};
 use ics11_beefy::client_message::ParachainHeader;
 use pallet_mmr_primitives::BatchProof;
-use sp_core::{ecdsa, ed25519, sr25519, Bytes, Pair, H256};
+use sp_core::{
+	crypto::{AccountId32, Ss58Codec},
+	ecdsa, ed25519, sr25519,
+	storage::ChildInfo,
+	Bytes, Pair, H256,
+};
 use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
 use sp_runtime::{
 	traits::{IdentifyAccount, Verify},
@@ -49,26 +67,43 @@ use subxt::ext::sp_runtime::{traits::Header as HeaderT, MultiSigner};
 
 use crate::{
 	parachain::api,
-	utils::{fetch_max_extrinsic_weight, unsafe_cast_to_jsonrpsee_client},
+	utils::{
+		channel_escrow_account, dispatch_error_code, fetch_max_extrinsic_weight,
+		fetch_token_metadata, follow_runtime_upgrades, query_error_at, rpc_error_code,
+		unsafe_cast_to_jsonrpsee_client, TokenMetadata,
+	},
 };
 use codec::Decode;
+use ibc_proto::cosmos::base::v1beta1::Coin;
 use ics10_grandpa::consensus_state::ConsensusState as GrandpaConsensusState;
 use ics11_beefy::{
 	client_state::ClientState as BeefyClientState,
 	consensus_state::ConsensusState as BeefyConsensusState,
 };
-use primitives::KeyProvider;
+use jsonrpsee::{core::client::ClientT, rpc_params, types::error::METHOD_NOT_FOUND_CODE};
+use primitives::{
+	alert::{Alerts, Severity},
+	apply_prefix,
+	packet_data::PacketFilter,
+	time::timestamp_from_millis,
+	KeyProvider, RelayMode, TransactionCost,
+};
 
-use crate::{finality_protocol::FinalityProtocol, signer::ExtrinsicSigner};
+use crate::{
+	finality_protocol::FinalityProtocol,
+	provider::TransactionId,
+	signer::{ExtrinsicSigner, KeystoreSigner},
+};
 use grandpa_light_client_primitives::{FinalityProof, ParachainHeaderProofs};
 use grandpa_prover::GrandpaProver;
-use ibc::timestamp::Timestamp;
 use ics10_grandpa::client_state::ClientState as GrandpaClientState;
 use jsonrpsee_ws_client::WsClientBuilder;
 use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager};
 use sp_keystore::testing::KeyStore;
 use sp_runtime::traits::One;
 use subxt::tx::TxPayload;
+use transaction_payment_rpc::TransactionPaymentApiClient;
+use transaction_payment_runtime_api::RuntimeDispatchInfo;
 
 /// Implements the [`crate::Chain`] trait for parachains.
 /// This is responsible for:
@@ -93,26 +128,91 @@ pub struct ParachainClient<T: config::Config> {
 	pub beefy_activation_block: Option<u32>,
 	/// Light client id on counterparty chain
 	pub client_id: Option<ClientId>,
+	/// Light client ids on counterparty chain used by specific connections, these take precedence
+	/// over `client_id`
+	pub connection_clients: BTreeMap<ConnectionId, ClientId>,
 	/// Connection Id
 	pub connection_id: Option<ConnectionId>,
 	/// ICS-23 provable store commitment prefix
 	pub commitment_prefix: Vec<u8>,
-	/// Public key for relayer on chain
-	pub public_key: MultiSigner,
+	/// Key extrinsics are signed with, replaced by [`ParachainClient::rotate_key`]. Unset for
+	/// watch-only clients.
+	pub signing_key: Arc<Mutex<Option<SigningKey>>>,
 	/// Reference to keystore
 	pub key_store: SyncCryptoStorePtr,
-	/// Key type Id
-	pub key_type_id: KeyTypeId,
 	/// used for encoding relayer address.
 	pub ss58_version: Ss58AddressFormat,
 	/// the maximum extrinsic weight allowed by this client
 	pub max_extrinsic_weight: u64,
+	/// Symbol and decimals of the native token, for logging balances
+	pub token_metadata: TokenMetadata,
 	/// Channels cleared for packet relay
 	pub channel_whitelist: Vec<(ChannelId, PortId)>,
+	/// Token transfers from this chain that must not be relayed
+	pub packet_filter: PacketFilter,
+	/// Packet messages relayed for specific channels of this chain, all of them for other channels
+	pub relay_modes: BTreeMap<(ChannelId, PortId), RelayMode>,
 	/// Finality protocol to use, eg Beefy, Grandpa
 	pub finality_protocol: FinalityProtocol,
+	/// Block timestamps in nanoseconds already fetched, keyed by block number
+	pub timestamp_cache: Arc<Mutex<BTreeMap<u64, u64>>>,
+	/// Configured block time, takes precedence over the block time measured from
+	/// `timestamp_cache`
+	pub expected_block_time: Option<Duration>,
+	/// Window over which finality notifications are batched into a single relay cycle
+	pub finality_batch_window: Option<Duration>,
+	/// Check proofs queried from this chain before submitting them to the counterparty
+	pub verify_proofs: bool,
+	/// Tip added to submitted extrinsics
+	pub tip: u128,
+	/// Maximum fee, tip included, the relayer pays for a single extrinsic
+	pub max_fee: Option<u128>,
+	/// Sign extrinsics and log them instead of submitting them
+	pub dry_run: bool,
+	/// Interval after which the counterparty client is updated even without new events
+	pub heartbeat_interval: Option<Duration>,
+	/// Number of blocks between full scans for packets to relay, every finality notification
+	/// if not set
+	pub full_scan_interval: Option<u64>,
+	/// Relay chain rpc client of an independent node, relay chain headers used for client updates
+	/// must match the ones it reports
+	pub verification_relay_client: Option<subxt::OnlineClient<T>>,
+	/// Where conditions that need an operator's attention are reported
+	pub alerts: Alerts,
+	/// Secret uri of the faucet account topping up the relayer account
+	#[cfg(any(test, feature = "testing"))]
+	pub faucet_key: Option<String>,
+	/// Amount transferred from the faucet on top of the missing fee
+	#[cfg(any(test, feature = "testing"))]
+	pub faucet_top_up: u128,
+	/// Ibc denoms of assets configured by the operator, keyed by asset id
+	pub asset_denoms: BTreeMap<T::AssetId, PrefixedDenom>,
+	/// Nonce of the next extrinsic submitted by the relayer, tracked locally so several
+	/// extrinsics can be in flight at once. Unset until queried from the chain.
+	pub nonce: Arc<tokio::sync::Mutex<Option<T::Index>>>,
+	/// Undelivered sequences last queried, with the height, channel and port they were queried for
+	pub undelivered_sequences:
+		Arc<Mutex<Option<((Height, ChannelId, PortId), UndeliveredSequences)>>>,
 }
 
+/// Key the relayer signs extrinsics with
+#[derive(Clone)]
+pub struct SigningKey {
+	/// Public key for relayer on chain
+	pub public_key: MultiSigner,
+	/// Signs on behalf of `public_key`
+	pub signer: Arc<dyn primitives::signer::Signer>,
+}
+
+/// Maximum number of block timestamps kept in [`ParachainClient::timestamp_cache`].
+pub const MAX_CACHED_TIMESTAMPS: usize = 1024;
+
+/// Ibc denom of the native token of the parachain.
+pub const NATIVE_DENOM: &str = "UNIT";
+
+/// Asset id the ics20 module transfers the native token under.
+pub const NATIVE_ASSET_ID: u128 = 1;
+
 enum KeyType {
 	Sr25519,
 	Ed25519,
@@ -142,8 +242,41 @@ impl FromStr for KeyType {
 	}
 }
 
+/// Adds the key derived from `private_key` to the keystore
+fn insert_key(
+	key_store: &SyncCryptoStorePtr,
+	key_type: &str,
+	private_key: &str,
+) -> Result<SigningKey, Error> {
+	let key_type = KeyType::from_str(key_type)?;
+	let key_type_id = key_type.to_key_type_id();
+
+	let public_key: MultiSigner = match key_type {
+		KeyType::Sr25519 => sr25519::Pair::from_string_with_seed(private_key, None)
+			.map_err(|_| Error::Custom("invalid key".to_owned()))?
+			.0
+			.public()
+			.into(),
+		KeyType::Ed25519 => ed25519::Pair::from_string_with_seed(private_key, None)
+			.map_err(|_| Error::Custom("invalid key".to_owned()))?
+			.0
+			.public()
+			.into(),
+		KeyType::Ecdsa => ecdsa::Pair::from_string_with_seed(private_key, None)
+			.map_err(|_| Error::Custom("invalid key".to_owned()))?
+			.0
+			.public()
+			.into(),
+	};
+
+	SyncCryptoStore::insert_unknown(&**key_store, key_type_id, private_key, public_key.as_ref())
+		.map_err(|_| Error::Custom("Failed to insert key into the keystore".to_owned()))?;
+	let signer = Arc::new(KeystoreSigner::new(key_store.clone(), key_type_id, public_key.clone()));
+	Ok(SigningKey { public_key, signer })
+}
+
 /// config options for [`ParachainClient`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ParachainClientConfig {
 	/// Chain name
 	pub name: String,
@@ -155,22 +288,83 @@ pub struct ParachainClientConfig {
 	pub relay_chain_rpc_url: String,
 	/// Light client id on counterparty chain
 	pub client_id: Option<ClientId>,
+	/// Light client ids on counterparty chain to use for specific connections
+	#[serde(default)]
+	pub connection_clients: Vec<(ConnectionId, ClientId)>,
 	/// Connection Id
 	pub connection_id: Option<ConnectionId>,
 	/// Beefy activation block
 	pub beefy_activation_block: Option<u32>,
 	/// Commitment prefix
 	pub commitment_prefix: Bytes,
-	/// Raw private key for signing transactions
-	pub private_key: String,
+	/// Raw private key for signing transactions, a client without one can't submit transactions
+	#[serde(default)]
+	pub private_key: Option<String>,
 	/// used for encoding relayer address.
 	pub ss58_version: u8,
 	/// Channels cleared for packet relay
 	pub channel_whitelist: Vec<(ChannelId, PortId)>,
+	/// Token transfers from this chain that must not be relayed
+	#[serde(default)]
+	pub packet_filter: PacketFilter,
+	/// Packet messages relayed for specific channels of this chain
+	#[serde(default)]
+	pub relay_modes: Vec<(ChannelId, PortId, RelayMode)>,
 	/// Finality protocol
 	pub finality_protocol: FinalityProtocol,
 	/// Digital signature scheme
 	pub key_type: String,
+	/// Expected block time in milliseconds, measured from recent blocks if not set
+	#[serde(default)]
+	pub expected_block_time_ms: Option<u64>,
+	/// Finality notifications received within this many milliseconds of each other are
+	/// relayed together
+	#[serde(default)]
+	pub finality_batch_window_ms: Option<u64>,
+	/// Check proofs locally before submitting them, defaults to true
+	#[serde(default)]
+	pub verify_proofs: Option<bool>,
+	/// Tip added to submitted extrinsics, raising their priority in the transaction pool
+	#[serde(default)]
+	pub tip: u128,
+	/// Extrinsics whose estimated fee, tip included, exceeds this amount are not submitted
+	#[serde(default)]
+	pub max_fee: Option<u128>,
+	/// Native token balance below which the relayer warns that its account needs funding
+	#[serde(default)]
+	pub min_balance: Option<u128>,
+	/// Secret uri of the account the relayer account is topped up from when it can't pay the fee
+	/// of the next extrinsic
+	#[cfg(any(test, feature = "testing"))]
+	#[serde(default)]
+	pub faucet_key: Option<String>,
+	/// Amount transferred from the faucet on top of the missing fee
+	#[cfg(any(test, feature = "testing"))]
+	#[serde(default)]
+	pub faucet_top_up: Option<u128>,
+	/// Ibc denoms of assets, keyed by asset id. Assets not listed are resolved on chain
+	#[serde(default)]
+	pub asset_denoms: Vec<(u128, String)>,
+	/// Seconds a websocket connection may be lost for before an alert is sent
+	#[serde(default)]
+	pub connection_loss_alert_secs: Option<u64>,
+	/// Where conditions that need an operator's attention are reported, set by the relayer
+	#[serde(skip)]
+	pub alerts: Alerts,
+	/// Sign extrinsics and log them instead of submitting them
+	#[serde(default)]
+	pub dry_run: bool,
+	/// Seconds after which the counterparty client is updated even without new events
+	#[serde(default)]
+	pub heartbeat_interval_secs: Option<u64>,
+	/// Number of blocks between full scans for packets to relay, every finality notification
+	/// if not set
+	#[serde(default)]
+	pub full_scan_interval_blocks: Option<u64>,
+	/// rpc url of an independent relay chain node headers used for client updates are checked
+	/// against
+	#[serde(default)]
+	pub verification_relay_chain_rpc_url: Option<String>,
 }
 
 impl<T> ParachainClient<T>
@@ -202,58 +396,334 @@ where
 		})
 		.await?;
 
+		let verification_relay_client = match config.verification_relay_chain_rpc_url.as_deref() {
+			Some(url) => {
+				let ws_client = Arc::new(
+					WsClientBuilder::default()
+						.build(url)
+						.await
+						.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?,
+				);
+				let client = subxt::OnlineClient::from_rpc_client(unsafe {
+					unsafe_cast_to_jsonrpsee_client(&ws_client)
+				})
+				.await?;
+				Some(client)
+			},
+			None => None,
+		};
+
 		let max_extrinsic_weight = fetch_max_extrinsic_weight(&para_client).await?;
+		let token_metadata = fetch_token_metadata(&para_client).await?;
 
-		let key_store: SyncCryptoStorePtr = Arc::new(KeyStore::new());
-		let key_type = KeyType::from_str(&config.key_type)?;
-		let key_type_id = key_type.to_key_type_id();
-
-		let public_key: MultiSigner = match key_type {
-			KeyType::Sr25519 => sr25519::Pair::from_string_with_seed(&config.private_key, None)
-				.map_err(|_| Error::Custom("invalid key".to_owned()))?
-				.0
-				.public()
-				.into(),
-			KeyType::Ed25519 => ed25519::Pair::from_string_with_seed(&config.private_key, None)
-				.map_err(|_| Error::Custom("invalid key".to_owned()))?
-				.0
-				.public()
-				.into(),
-			KeyType::Ecdsa => ecdsa::Pair::from_string_with_seed(&config.private_key, None)
-				.map_err(|_| Error::Custom("invalid key".to_owned()))?
-				.0
-				.public()
-				.into(),
-		};
+		let asset_denoms = config
+			.asset_denoms
+			.into_iter()
+			.map(|(asset_id, denom)| {
+				let denom = PrefixedDenom::from_str(&denom)
+					.map_err(|e| Error::Custom(format!("Invalid denom {}: {:?}", denom, e)))?;
+				Ok((T::AssetId::from(asset_id), denom))
+			})
+			.collect::<Result<BTreeMap<_, _>, Error>>()?;
 
-		SyncCryptoStore::insert_unknown(
-			&*key_store,
-			key_type_id,
-			&*config.private_key,
-			public_key.as_ref(),
-		)
-		.unwrap();
+		for (name, client) in [
+			(config.name.clone(), para_client.clone()),
+			(format!("{} relay chain", config.name), relay_client.clone()),
+		] {
+			tokio::spawn(follow_runtime_upgrades(name, client));
+		}
+
+		let connection_loss_alert = config
+			.connection_loss_alert_secs
+			.map(Duration::from_secs)
+			.unwrap_or(utils::DEFAULT_CONNECTION_LOSS_ALERT);
+		for (name, client) in [
+			(config.name.clone(), para_ws_client.clone()),
+			(format!("{} relay chain", config.name), relay_ws_client.clone()),
+		] {
+			tokio::spawn(utils::alert_on_connection_loss(
+				name,
+				client,
+				connection_loss_alert,
+				config.alerts.clone(),
+			));
+		}
 
-		Ok(Self {
+		let key_store: SyncCryptoStorePtr = Arc::new(KeyStore::new());
+		let signing_key = config
+			.private_key
+			.as_deref()
+			.map(|private_key| insert_key(&key_store, &config.key_type, private_key))
+			.transpose()?;
+
+		let client = Self {
 			name: config.name,
 			para_client,
 			relay_client,
 			para_id: config.para_id,
 			client_id: config.client_id,
+			connection_clients: config.connection_clients.into_iter().collect(),
 			commitment_prefix: config.commitment_prefix.0,
 			connection_id: config.connection_id,
 			beefy_activation_block: config.beefy_activation_block,
-			public_key,
+			signing_key: Arc::new(Mutex::new(signing_key)),
 			key_store,
-			key_type_id,
 			max_extrinsic_weight,
+			token_metadata,
 			para_ws_client,
 			relay_ws_client,
 			ss58_version: Ss58AddressFormat::from(config.ss58_version),
 			channel_whitelist: config.channel_whitelist,
+			packet_filter: config.packet_filter,
+			relay_modes: config
+				.relay_modes
+				.into_iter()
+				.map(|(channel_id, port_id, relay_mode)| ((channel_id, port_id), relay_mode))
+				.collect(),
 			finality_protocol: config.finality_protocol,
+			timestamp_cache: Default::default(),
+			expected_block_time: config.expected_block_time_ms.map(Duration::from_millis),
+			finality_batch_window: config.finality_batch_window_ms.map(Duration::from_millis),
+			verify_proofs: config.verify_proofs.unwrap_or(true),
+			tip: config.tip,
+			max_fee: config.max_fee,
+			dry_run: config.dry_run,
+			heartbeat_interval: config.heartbeat_interval_secs.map(Duration::from_secs),
+			full_scan_interval: config.full_scan_interval_blocks,
+			verification_relay_client,
+			alerts: config.alerts,
+			#[cfg(any(test, feature = "testing"))]
+			faucet_key: config.faucet_key,
+			#[cfg(any(test, feature = "testing"))]
+			faucet_top_up: config.faucet_top_up.unwrap_or(test_provider::DEFAULT_FAUCET_TOP_UP),
+			asset_denoms,
+			nonce: Default::default(),
+			undelivered_sequences: Default::default(),
+		};
+		client.validate_commitment_prefix().await?;
+		Ok(client)
+	}
+
+	/// Checks that the configured commitment prefix is the one the ibc pallet stores its state
+	/// under, by reading the client state of a known client from the ibc child trie.
+	/// Without a known client there is nothing to prove yet, so the check is skipped.
+	pub async fn validate_commitment_prefix(&self) -> Result<(), Error> {
+		let clients = IbcApiClient::<u32, H256, T::AssetId>::query_clients(&*self.para_ws_client)
+			.await
+			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		let client_id = match clients.into_iter().next() {
+			Some(client) => ClientId::from_str(&client.client_id)
+				.map_err(|_| Error::Custom("Invalid client id ".to_string()))?,
+			None => {
+				log::debug!("No clients on {}, skipping commitment prefix check", self.name);
+				return Ok(())
+			},
+		};
+		let key = apply_prefix(
+			self.commitment_prefix.clone(),
+			ClientStatePath(client_id.clone()).to_string(),
+		);
+		if self.query_ibc_storage(key, None).await?.is_none() {
+			return Err(Error::Custom(format!(
+				"Commitment prefix {:?} doesn't match the ibc store of {}, client {client_id} not found under it",
+				String::from_utf8_lossy(&self.commitment_prefix),
+				self.name
+			)))
+		}
+		Ok(())
+	}
+
+	/// Reads the value stored under `key` in the ibc child trie, at the given block or the best
+	/// block.
+	pub async fn query_ibc_storage(
+		&self,
+		key: Vec<u8>,
+		at: Option<H256>,
+	) -> Result<Option<Vec<u8>>, Error> {
+		let child_storage_key = ChildInfo::new_default(&self.commitment_prefix)
+			.prefixed_storage_key()
+			.into_inner();
+		let para_client = unsafe { unsafe_cast_to_jsonrpsee_client(&self.para_ws_client) };
+		let value: Option<Bytes> = para_client
+			.request("childstate_getStorage", rpc_params![Bytes(child_storage_key), Bytes(key), at])
+			.await?;
+		Ok(value.map(|value| value.0))
+	}
+
+	/// Returns the native token balance escrowed by the ics20 module for transfers out of the
+	/// given channel, to audit it against the vouchers minted on the counterparty.
+	pub async fn query_escrow_balance(
+		&self,
+		port_id: &PortId,
+		channel_id: ChannelId,
+	) -> Result<Coin, Error> {
+		self.query_account_balance(&channel_escrow_account(port_id, channel_id)?).await
+	}
+
+	/// Returns the native token balance of the given account.
+	pub async fn query_account_balance(&self, account: &AccountId32) -> Result<Coin, Error> {
+		IbcApiClient::<u32, H256, T::AssetId>::query_balance_with_address(
+			&*self.para_ws_client,
+			format!("0x{}", hex::encode(account)),
+		)
+		.await
+		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))
+	}
+
+	/// Returns the escrow account of the given channel, encoded with the ss58 prefix of the chain.
+	pub fn escrow_account(&self, port_id: &PortId, channel_id: ChannelId) -> Result<String, Error> {
+		let account = channel_escrow_account(port_id, channel_id)?;
+		Ok(account.to_ss58check_with_version(self.ss58_version))
+	}
+
+	/// Returns the accounts the ics20 module has escrowed tokens in.
+	pub async fn query_escrow_accounts(&self) -> Result<Vec<AccountId32>, Error> {
+		let address = api::storage().ibc().escrow_addresses();
+		let accounts = self.para_client.storage().fetch_or_default(&address, None).await?;
+		Ok(accounts
+			.into_iter()
+			.map(|account| AccountId32::from(<[u8; 32]>::from(account)))
+			.collect())
+	}
+
+	/// Returns the native token balance of every account the ics20 module has escrowed tokens
+	/// in, with the account encoded with the ss58 prefix of the chain.
+	pub async fn query_escrow_balances(&self) -> Result<Vec<(String, Coin)>, Error> {
+		let mut balances = vec![];
+		for account in self.query_escrow_accounts().await? {
+			let balance = self.query_account_balance(&account).await?;
+			balances.push((account.to_ss58check_with_version(self.ss58_version), balance));
+		}
+		Ok(balances)
+	}
+
+	/// Resolves the ibc denom of an asset registered by the ics20 module.
+	pub async fn query_denom_trace(&self, asset_id: T::AssetId) -> Result<PrefixedDenom, Error> {
+		let response = IbcApiClient::<u32, H256, T::AssetId>::query_denom_trace(
+			&*self.para_ws_client,
+			asset_id,
+		)
+		.await
+		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		let denom_trace = response
+			.denom_trace
+			.ok_or_else(|| Error::Custom("Denom trace missing from response".to_string()))?;
+		PrefixedDenom::try_from(denom_trace)
+			.map_err(|e| Error::from(format!("Invalid denom trace: {:?}", e)))
+	}
+
+	/// Returns the sequences of packets on a channel that are still awaiting delivery, sent and
+	/// received ones alike. Both are fetched at once and kept for the next call for the same
+	/// channel and height, since the relayer scans them one after the other.
+	pub async fn query_undelivered_sequences(
+		&self,
+		at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<UndeliveredSequences, Error> {
+		let key = (at, channel_id, port_id);
+		if let Some((cached, sequences)) = &*self.undelivered_sequences.lock().unwrap() {
+			if *cached == key {
+				return Ok(sequences.clone())
+			}
+		}
+		let (at, channel_id, port_id) = key.clone();
+		let result = IbcApiClient::<u32, H256, T::AssetId>::query_undelivered_sequences(
+			&*self.para_ws_client,
+			at.revision_height as u32,
+			channel_id.to_string(),
+			port_id.to_string(),
+		)
+		.await;
+		let sequences = match result {
+			Ok(sequences) => sequences,
+			// nodes built before the rpc was added
+			Err(e) if rpc_error_code(&e) == Some(METHOD_NOT_FOUND_CODE) =>
+				self.query_stored_sequences(at, channel_id, port_id).await?,
+			Err(e) => return Err(query_error_at(at, e)),
+		};
+		*self.undelivered_sequences.lock().unwrap() = Some((key, sequences.clone()));
+		Ok(sequences)
+	}
+
+	/// Fetches the sequences of packets awaiting delivery from the packet commitments and
+	/// acknowledgements stored on the channel.
+	async fn query_stored_sequences(
+		&self,
+		at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<UndeliveredSequences, Error> {
+		let commitments = IbcApiClient::<u32, H256, T::AssetId>::query_packet_commitments(
+			&*self.para_ws_client,
+			at.revision_height as u32,
+			channel_id.to_string(),
+			port_id.to_string(),
+		)
+		.await
+		.map_err(|e| query_error_at(at, e))?;
+		let acknowledgements =
+			IbcApiClient::<u32, H256, T::AssetId>::query_packet_acknowledgements(
+				&*self.para_ws_client,
+				at.revision_height as u32,
+				channel_id.to_string(),
+				port_id.to_string(),
+			)
+			.await
+			.map_err(|e| query_error_at(at, e))?;
+		Ok(UndeliveredSequences {
+			send: commitments.commitments.into_iter().map(|state| state.sequence).collect(),
+			recv: acknowledgements
+				.acknowledgements
+				.into_iter()
+				.map(|state| state.sequence)
+				.collect(),
+			height: commitments.height.unwrap_or_default(),
 		})
 	}
+
+	/// Returns the id of the asset with the given ibc denom, either the native token or one of
+	/// the configured assets.
+	pub fn asset_id(&self, denom: &PrefixedDenom) -> Result<T::AssetId, Error> {
+		if denom.to_string() == NATIVE_DENOM {
+			return Ok(NATIVE_ASSET_ID.into())
+		}
+		self.asset_denoms
+			.iter()
+			.find(|(_, asset_denom)| *asset_denom == denom)
+			.map(|(asset_id, _)| asset_id.clone())
+			.ok_or_else(|| Error::Custom(format!("No asset configured for denom {}", denom)))
+	}
+
+	/// Returns the ibc denoms of all assets registered by the ics20 module, following the
+	/// pagination of the rpc until every page has been fetched.
+	pub async fn query_denom_traces(&self) -> Result<Vec<PrefixedDenom>, Error> {
+		let mut denoms = vec![];
+		let mut next_key = None;
+		loop {
+			let response = IbcApiClient::<u32, H256, T::AssetId>::query_denom_traces(
+				&*self.para_ws_client,
+				next_key.take(),
+				None,
+				None,
+				false,
+			)
+			.await
+			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+			for denom_trace in response.denom_traces {
+				denoms.push(
+					PrefixedDenom::try_from(denom_trace)
+						.map_err(|e| Error::from(format!("Invalid denom trace: {:?}", e)))?,
+				);
+			}
+			match response.pagination {
+				Some(page) if !page.next_key.is_empty() =>
+					next_key = Some(T::AssetId::decode(&mut &*page.next_key)?),
+				_ => break,
+			}
+		}
+		Ok(denoms)
+	}
 }
 
 impl<T: config::Config + Send + Sync> ParachainClient<T>
@@ -280,6 +750,41 @@ where
 		}
 	}
 
+	/// Checks that the relay chain block `number` has the given `hash` on the verification node, if
+	/// one is configured. Headers the nodes disagree on must not be submitted, since the primary
+	/// node may be feeding the counterparty light client a fork.
+	pub async fn verify_relay_header(&self, number: u32, hash: H256) -> Result<(), Error> {
+		let client = match self.verification_relay_client.as_ref() {
+			Some(client) => client,
+			None => return Ok(()),
+		};
+		let verified_hash = client.rpc().block_hash(Some(number.into())).await?;
+		match verified_hash {
+			Some(verified_hash) if verified_hash.as_ref() == hash.as_bytes() => Ok(()),
+			Some(verified_hash) => {
+				let verified_hash = H256::from(verified_hash);
+				self.alerts
+					.alert(
+						&format!("equivocation/{}", self.name),
+						Severity::Critical,
+						format!(
+							"Relay chain block {} of {} is {:?} on the primary node but {:?} on \
+							 the verification node, client updates are not submitted",
+							number, self.name, hash, verified_hash
+						),
+					)
+					.await;
+				Err(Error::HeaderMismatch { number, primary: hash, verified: verified_hash })
+			},
+			// the verification node may lag behind, the update is retried with the next
+			// finality notification
+			None => Err(Error::Custom(format!(
+				"Relay chain block {} of {} is not yet known to the verification node",
+				number, self.name
+			))),
+		}
+	}
+
 	/// Queries parachain headers that have been finalized by BEEFY in between the given relay chain
 	/// heights
 	pub async fn query_beefy_finalized_parachain_headers_between(
@@ -384,15 +889,14 @@ where
 	/// Submits the given transaction to the parachain node, waits for it to be included in a block
 	/// and asserts that it was successfully dispatched on-chain.
 	///
-	/// We retry sending the transaction up to 5 times in the case where the transaction pool might
-	/// reject the transaction because of conflicting nonces.
-	pub async fn submit_call<C: TxPayload>(&self, call: C) -> Result<(T::Hash, T::Hash), Error> {
-		let signer = ExtrinsicSigner::<T, Self>::new(
-			self.key_store.clone(),
-			self.key_type_id.clone(),
-			self.public_key.clone(),
-		);
-
+	/// Nonces are assigned from [`ParachainClient::nonce`] and advanced once the transaction pool
+	/// accepts the transaction. We retry sending the transaction up to 5 times in the case where
+	/// the transaction pool might reject the transaction because of conflicting nonces, querying
+	/// the nonce from the chain again.
+	pub async fn submit_call<C: TxPayload>(
+		&self,
+		call: C,
+	) -> Result<TransactionId<T::Hash>, Error> {
 		// Try extrinsic submission five times in case of failures
 		let mut count = 0;
 		let progress = loop {
@@ -400,29 +904,151 @@ where
 				Err(Error::Custom("Failed to submit extrinsic after 5 tries".to_string()))?
 			}
 
-			let other_params = T::custom_extrinsic_params(&self.para_client).await?;
+			let other_params = T::custom_extrinsic_params(&self.para_client, self.tip).await?;
+			let mut next_nonce = self.nonce.lock().await;
+			// the signer is created under the nonce lock, so a rotated key can't be paired with
+			// the nonce of the previous account
+			let SigningKey { public_key, signer } = self.signing_key()?;
+			let mut signer = ExtrinsicSigner::<T, Self>::new(public_key, signer);
+			let nonce = match *next_nonce {
+				Some(nonce) => nonce,
+				None =>
+					self.para_client.rpc().system_account_next_index(signer.account_id()).await?,
+			};
+			signer.set_nonce(nonce);
+			let extrinsic = signer.create_signed(&self.para_client, &call, other_params).await?;
+
+			let fee = self.query_extrinsic_fee(extrinsic.encoded().to_vec()).await?;
+			log::debug!("Estimated fee of extrinsic on {}: {} + {} tip", self.name, fee, self.tip);
+			if let Some(max_fee) = self.max_fee {
+				if fee.saturating_add(self.tip) > max_fee {
+					return Err(Error::Custom(format!(
+						"Estimated fee {} with tip {} exceeds the maximum fee {}",
+						fee, self.tip, max_fee
+					)))
+				}
+			}
+
+			if self.dry_run {
+				return Err(self.dry_run_error(extrinsic.encoded()))
+			}
+
+			#[cfg(any(test, feature = "testing"))]
+			self.top_up_from_faucet(fee.saturating_add(self.tip)).await?;
 
-			let res = self
-				.para_client
-				.tx()
-				.sign_and_submit_then_watch(&call, &signer, other_params)
-				.await;
+			let res = extrinsic.submit_and_watch().await;
 			match res {
-				Ok(progress) => break progress,
+				Ok(progress) => {
+					*next_nonce = Some(nonce + 1u32.into());
+					break progress
+				},
 				Err(e) => {
 					log::warn!("Failed to submit extrinsic: {:?}. Retrying...", e);
+					*next_nonce = None;
 					count += 1;
 				},
 			}
 		};
 
-		let tx_in_block = progress.wait_for_in_block().await?;
-		tx_in_block.wait_for_success().await?;
-		Ok((tx_in_block.extrinsic_hash(), tx_in_block.block_hash()))
+		let tx_in_block = match progress.wait_for_in_block().await {
+			Ok(tx_in_block) => tx_in_block,
+			Err(e) => {
+				// the extrinsic was dropped, e.g. because its era expired, so the nonces after it
+				// can't be included until the nonce is queried again
+				*self.nonce.lock().await = None;
+				return Err(e.into())
+			},
+		};
+		let events = tx_in_block.fetch_events().await?;
+		if let Some(failed) = events.find_first::<api::system::events::ExtrinsicFailed>()? {
+			return Err(primitives::error::Error::TransactionFailed {
+				chain: self.name.clone(),
+				tx_hash: format!("{:?}", tx_in_block.extrinsic_hash()),
+				code: dispatch_error_code(&failed.dispatch_error),
+				reason: format!("{:?}", failed.dispatch_error),
+			}
+			.into())
+		}
+		let cost = TransactionCost {
+			gas_used: events
+				.find_first::<api::system::events::ExtrinsicSuccess>()?
+				.map(|event| event.dispatch_info.weight)
+				.unwrap_or_default(),
+			fee: events
+				.find_first::<api::transaction_payment::events::TransactionFeePaid>()?
+				.map(|event| event.actual_fee)
+				.unwrap_or_default(),
+		};
+		Ok(TransactionId {
+			ext_hash: tx_in_block.extrinsic_hash(),
+			block_hash: tx_in_block.block_hash(),
+			cost,
+		})
+	}
+
+	/// Queries the fee, excluding the tip, charged for including the given signed extrinsic.
+	pub async fn query_extrinsic_fee(&self, extrinsic: Vec<u8>) -> Result<u128, Error> {
+		let dispatch_info =
+			TransactionPaymentApiClient::<H256, RuntimeDispatchInfo<u128>>::query_info(
+				&*self.para_ws_client,
+				extrinsic.into(),
+				None,
+			)
+			.await
+			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		Ok(dispatch_info.partial_fee)
+	}
+
+	/// Logs the extrinsic that a dry run doesn't submit, returning the error reporting it.
+	pub fn dry_run_error(&self, extrinsic: &[u8]) -> Error {
+		let ext_hash = <T::Hashing as subxt::ext::sp_runtime::traits::Hash>::hash(extrinsic);
+		log::info!(
+			"Dry run, not submitting extrinsic {:?} to {}: 0x{}",
+			ext_hash,
+			self.name,
+			hex::encode(extrinsic)
+		);
+		primitives::error::Error::DryRun {
+			chain: self.name.clone(),
+			tx_hash: format!("{:?}", ext_hash),
+		}
+		.into()
+	}
+
+	/// Returns the key extrinsics are currently signed with, [`Error::NoSigner`] for watch-only
+	/// clients.
+	pub fn signing_key(&self) -> Result<SigningKey, Error> {
+		self.signing_key
+			.lock()
+			.unwrap()
+			.clone()
+			.ok_or_else(|| Error::NoSigner(self.name.clone()))
 	}
 
+	/// Replaces the key extrinsics are signed with, `None` makes the client watch-only. Extrinsics
+	/// already submitted with the previous key are left to complete, and the nonce of the new
+	/// account is queried from the chain.
+	pub async fn rotate_key(&self, key_type: &str, private_key: Option<&str>) -> Result<(), Error> {
+		let signing_key = private_key
+			.map(|private_key| insert_key(&self.key_store, key_type, private_key))
+			.transpose()?;
+		// no extrinsic is signed while the nonce lock is held
+		let mut nonce = self.nonce.lock().await;
+		*self.signing_key.lock().unwrap() = signing_key;
+		*nonce = None;
+		log::info!("Rotated signing key of {}, now signing as {}", self.name, self.account_id());
+		Ok(())
+	}
+
+	/// Returns the light client id on the counterparty chain for the current connection, falling
+	/// back to the default client id if the connection has no client of its own.
 	pub fn client_id(&self) -> ClientId {
-		self.client_id.as_ref().expect("Client Id should be defined").clone()
+		self.connection_id
+			.as_ref()
+			.and_then(|connection_id| self.connection_clients.get(connection_id))
+			.or(self.client_id.as_ref())
+			.expect("Client Id should be defined")
+			.clone()
 	}
 }
 
@@ -517,13 +1143,16 @@ where
 				.fetch(&timestamp_addr, block_hash)
 				.await?
 				.expect("Timestamp should exist");
-			let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
+			let timestamp = timestamp_from_millis(unix_timestamp_millis)
+				.and_then(|timestamp| timestamp.into_tm_time())
+				.ok_or_else(|| {
+					Error::from(format!(
+						"Invalid timestamp {unix_timestamp_millis}ms at {block_number}"
+					))
+				})?;
 
 			let consensus_state = AnyConsensusState::Beefy(BeefyConsensusState {
-				timestamp: Timestamp::from_nanoseconds(timestamp_nanos)
-					.unwrap()
-					.into_tm_time()
-					.unwrap(),
+				timestamp,
 				root: decoded_para_head.state_root.as_bytes().to_vec().into(),
 			});
 
@@ -600,13 +1229,16 @@ where
 				.fetch(&timestamp_addr, block_hash)
 				.await?
 				.expect("Timestamp should exist");
-			let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
+			let timestamp = timestamp_from_millis(unix_timestamp_millis)
+				.and_then(|timestamp| timestamp.into_tm_time())
+				.ok_or_else(|| {
+					Error::from(format!(
+						"Invalid timestamp {unix_timestamp_millis}ms at {block_number}"
+					))
+				})?;
 
 			let consensus_state = AnyConsensusState::Grandpa(GrandpaConsensusState {
-				timestamp: Timestamp::from_nanoseconds(timestamp_nanos)
-					.unwrap()
-					.into_tm_time()
-					.unwrap(),
+				timestamp,
 				root: decoded_para_head.state_root.as_bytes().to_vec().into(),
 			});
 
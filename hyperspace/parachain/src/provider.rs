@@ -14,11 +14,17 @@
 
 use super::{error::Error, ParachainClient};
 use crate::{
-	config, finality_protocol::FinalityEvent, parachain, utils::MetadataIbcEventWrapper,
-	FinalityProtocol, GrandpaClientState,
+	config,
+	finality_protocol::FinalityEvent,
+	parachain,
+	utils::{
+		consensus_state_pruned, median_block_time, query_error_at, unsafe_cast_to_jsonrpsee_client,
+		MetadataIbcEventWrapper,
+	},
+	FinalityProtocol, GrandpaClientState, MAX_CACHED_TIMESTAMPS, NATIVE_DENOM,
 };
 use beefy_prover::helpers::fetch_timestamp_extrinsic_with_proof;
-use codec::Encode;
+use codec::{Decode, Encode};
 use finality_grandpa::BlockNumberOps;
 use futures::Stream;
 use grandpa_light_client_primitives::{FinalityProof, ParachainHeaderProofs};
@@ -50,22 +56,40 @@ use ibc_proto::{
 use ibc_rpc::{IbcApiClient, PacketInfo};
 use ics10_grandpa::client_message::RelayChainHeader;
 use ics11_beefy::client_state::ClientState as BeefyClientState;
+use jsonrpsee::{core::client::ClientT, rpc_params};
 use pallet_ibc::{
 	light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager},
 	HostConsensusProof,
 };
-use primitives::{Chain, IbcProvider, KeyProvider, UpdateType};
-use sp_core::H256;
+use primitives::{
+	packet_data::PacketFilter,
+	time::{millis_to_nanos, timestamp_from_millis},
+	Chain, IbcProvider, KeyProvider, RelayMode, TransactionCost, UpdateType,
+};
+use sp_core::{twox_128, Bytes, H256};
 use sp_runtime::{
 	traits::{Header as HeaderT, IdentifyAccount, One, Verify},
 	MultiSignature, MultiSigner,
 };
-use std::{collections::BTreeMap, fmt::Display, pin::Pin, str::FromStr, time::Duration};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt::Display,
+	pin::Pin,
+	str::FromStr,
+	time::Duration,
+};
 use subxt::tx::{BaseExtrinsicParamsBuilder, ExtrinsicParams, PlainTip};
 
 pub struct TransactionId<Hash> {
 	pub ext_hash: Hash,
 	pub block_hash: Hash,
+	pub cost: TransactionCost,
+}
+
+impl<Hash: std::fmt::Debug> Display for TransactionId<Hash> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{:?}", self.ext_hash)
+	}
 }
 
 #[async_trait::async_trait]
@@ -97,7 +121,7 @@ where
 		&mut self,
 		finality_event: Self::FinalityEvent,
 		counterparty: &C,
-	) -> Result<(Any, Vec<IbcEvent>, UpdateType), anyhow::Error>
+	) -> Result<(Vec<Any>, Vec<IbcEvent>, UpdateType), anyhow::Error>
 	where
 		C: Chain,
 	{
@@ -167,7 +191,10 @@ where
 			false,
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| {
+			consensus_state_pruned(&self.name, &client_id, consensus_height, &e)
+				.unwrap_or_else(|| query_error_at(at, e))
+		})?;
 		Ok(res)
 	}
 
@@ -183,7 +210,35 @@ where
 				client_id.to_string(),
 			)
 			.await
-			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+			.map_err(|e| query_error_at(at, e))?;
+		Ok(response)
+	}
+
+	async fn query_upgraded_client_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryClientStateResponse, Self::Error> {
+		let response =
+			IbcApiClient::<u32, H256, <T as config::Config>::AssetId>::query_upgraded_client(
+				&*self.para_ws_client,
+				upgrade_height.revision_height as u32,
+			)
+			.await
+			.map_err(|e| query_error_at(upgrade_height, e))?;
+		Ok(response)
+	}
+
+	async fn query_upgraded_consensus_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryConsensusStateResponse, Self::Error> {
+		let response =
+			IbcApiClient::<u32, H256, <T as config::Config>::AssetId>::query_upgraded_cons_state(
+				&*self.para_ws_client,
+				upgrade_height.revision_height as u32,
+			)
+			.await
+			.map_err(|e| query_error_at(upgrade_height, e))?;
 		Ok(response)
 	}
 
@@ -198,7 +253,7 @@ where
 			connection_id.to_string(),
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| query_error_at(at, e))?;
 		Ok(response)
 	}
 
@@ -215,7 +270,7 @@ where
 			port_id.to_string(),
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| query_error_at(at, e))?;
 		Ok(response)
 	}
 
@@ -226,11 +281,28 @@ where
 			keys,
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| query_error_at(at, e))?;
 
 		Ok(proof.proof)
 	}
 
+	async fn query_key_with_proof(
+		&self,
+		at: Height,
+		key: Vec<u8>,
+	) -> Result<(Option<Vec<u8>>, Vec<u8>), Self::Error> {
+		let subxt_block_number: subxt::rpc::BlockNumber = at.revision_height.into();
+		let block_hash = self
+			.para_client
+			.rpc()
+			.block_hash(Some(subxt_block_number))
+			.await?
+			.ok_or_else(|| Error::from(format!("Block {} not found", at.revision_height)))?;
+		let value = self.query_ibc_storage(key.clone(), Some(block_hash)).await?;
+		let proof = self.query_proof(at, vec![key]).await?;
+		Ok((value, proof))
+	}
+
 	async fn query_packet_commitment(
 		&self,
 		at: Height,
@@ -247,7 +319,7 @@ where
 				seq,
 			)
 			.await
-			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+			.map_err(|e| query_error_at(at, e))?;
 		Ok(res)
 	}
 
@@ -266,7 +338,7 @@ where
 			seq,
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| query_error_at(at, e))?;
 		Ok(res)
 	}
 
@@ -283,7 +355,7 @@ where
 			port_id.to_string(),
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| query_error_at(at, e))?;
 		Ok(res)
 	}
 
@@ -302,7 +374,7 @@ where
 			seq,
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| query_error_at(at, e))?;
 		Ok(res)
 	}
 
@@ -325,9 +397,11 @@ where
 			.fetch(&timestamp_addr, block_hash)
 			.await?
 			.ok_or_else(|| Error::from("Timestamp should exist".to_string()))?;
-		let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
+		let timestamp = timestamp_from_millis(unix_timestamp_millis).ok_or_else(|| {
+			Error::from(format!("Invalid timestamp {unix_timestamp_millis}ms at {height}"))
+		})?;
 
-		Ok((height, Timestamp::from_nanoseconds(timestamp_nanos)?))
+		Ok((height, timestamp))
 	}
 
 	async fn query_packet_commitments(
@@ -336,16 +410,7 @@ where
 		channel_id: ChannelId,
 		port_id: PortId,
 	) -> Result<Vec<u64>, Self::Error> {
-		let res =
-			IbcApiClient::<u32, H256, <T as config::Config>::AssetId>::query_packet_commitments(
-				&*self.para_ws_client,
-				at.revision_height as u32,
-				channel_id.to_string(),
-				port_id.to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
-		Ok(res.commitments.into_iter().map(|packet_state| packet_state.sequence).collect())
+		Ok(self.query_undelivered_sequences(at, channel_id, port_id).await?.send)
 	}
 
 	async fn query_packet_acknowledgements(
@@ -354,19 +419,7 @@ where
 		channel_id: ChannelId,
 		port_id: PortId,
 	) -> Result<Vec<u64>, Self::Error> {
-		let res = IbcApiClient::<u32, H256, <T as config::Config>::AssetId>::query_packet_acknowledgements(
-			&*self.para_ws_client,
-			at.revision_height as u32,
-			channel_id.to_string(),
-			port_id.to_string(),
-		)
-		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
-		Ok(res
-			.acknowledgements
-			.into_iter()
-			.map(|packet_state| packet_state.sequence)
-			.collect())
+		Ok(self.query_undelivered_sequences(at, channel_id, port_id).await?.recv)
 	}
 
 	async fn query_unreceived_packets(
@@ -385,7 +438,7 @@ where
 				seqs,
 			)
 			.await
-			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+			.map_err(|e| query_error_at(at, e))?;
 		Ok(res)
 	}
 
@@ -404,7 +457,7 @@ where
 			seqs,
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| query_error_at(at, e))?;
 		Ok(res)
 	}
 
@@ -412,6 +465,27 @@ where
 		self.channel_whitelist.clone()
 	}
 
+	fn add_channel_to_whitelist(&mut self, channel: (ChannelId, PortId)) {
+		if !self.channel_whitelist.contains(&channel) {
+			self.channel_whitelist.push(channel)
+		}
+	}
+
+	fn packet_filter(&self) -> &PacketFilter {
+		&self.packet_filter
+	}
+
+	fn relay_mode(&self, channel_id: ChannelId, port_id: &PortId) -> RelayMode {
+		self.relay_modes
+			.get(&(channel_id, port_id.clone()))
+			.copied()
+			.unwrap_or_default()
+	}
+
+	fn verify_proofs(&self) -> bool {
+		self.verify_proofs
+	}
+
 	async fn query_connection_channels(
 		&self,
 		at: Height,
@@ -424,7 +498,7 @@ where
 				connection_id.to_string(),
 			)
 			.await
-			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+			.map_err(|e| query_error_at(at, e))?;
 		Ok(response)
 	}
 
@@ -465,8 +539,11 @@ where
 	}
 
 	fn expected_block_time(&self) -> Duration {
+		if let Some(block_time) = self.expected_block_time {
+			return block_time
+		}
 		// Parachains have an expected block time of 12 seconds
-		Duration::from_secs(12)
+		median_block_time(&self.timestamp_cache.lock().unwrap()).unwrap_or(Duration::from_secs(12))
 	}
 
 	async fn query_client_update_time_and_height(
@@ -481,7 +558,10 @@ where
 			client_height.revision_height,
 		)
 		.await
-		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		.map_err(|e| {
+			consensus_state_pruned(&self.name, &client_id, client_height, &e)
+				.unwrap_or_else(|| Error::from(format!("Rpc Error {:?}", e)))
+		})?;
 		Ok((
 			response.height.into(),
 			Timestamp::from_nanoseconds(response.timestamp)
@@ -514,7 +594,7 @@ where
 	}
 
 	async fn query_ibc_balance(&self) -> Result<Vec<PrefixedCoin>, Self::Error> {
-		let account = self.public_key.clone().into_account();
+		let account = self.signing_key()?.public_key.into_account();
 		let account_addr = parachain::api::storage().system().account(&account);
 		let balance = self
 			.para_client
@@ -523,11 +603,33 @@ where
 			.await?
 			.expect("Account data should exist");
 
-		// todo: how should we handle assets?
-		Ok(vec![PrefixedCoin {
-			denom: PrefixedDenom::from_str("UNIT")?,
+		log::debug!(
+			"{} balance: {}",
+			self.name,
+			self.token_metadata.display_amount(balance.data.free)
+		);
+
+		let mut coins = vec![];
+		for (asset_id, denom) in &self.asset_denoms {
+			let asset_id: u128 = asset_id.clone().into();
+			let asset_addr = parachain::api::storage().assets().account(&asset_id, &account);
+			if let Some(asset_account) = self.para_client.storage().fetch(&asset_addr, None).await?
+			{
+				coins.push(PrefixedCoin {
+					denom: denom.clone(),
+					amount: Amount::from_str(&format!("{}", asset_account.balance))?,
+				});
+			}
+		}
+		coins.push(PrefixedCoin {
+			denom: PrefixedDenom::from_str(NATIVE_DENOM)?,
 			amount: Amount::from_str(&format!("{}", balance.data.free))?,
-		}])
+		});
+		Ok(coins)
+	}
+
+	fn native_denom(&self) -> String {
+		NATIVE_DENOM.to_string()
 	}
 
 	fn connection_prefix(&self) -> CommitmentPrefix {
@@ -550,18 +652,80 @@ where
 	}
 
 	async fn query_timestamp_at(&self, block_number: u64) -> Result<u64, Self::Error> {
-		let subxt_block_number: subxt::rpc::BlockNumber = block_number.into();
-		let block_hash = self.para_client.rpc().block_hash(Some(subxt_block_number)).await.unwrap();
-		let timestamp_addr = parachain::api::storage().timestamp().now();
-		let unix_timestamp_millis = self
-			.para_client
-			.storage()
-			.fetch(&timestamp_addr, block_hash)
-			.await?
-			.expect("Timestamp should exist");
-		let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
+		let timestamps = self.query_timestamps_at(vec![block_number]).await?;
+		Ok(timestamps[0])
+	}
+
+	async fn query_timestamps_at(&self, block_numbers: Vec<u64>) -> Result<Vec<u64>, Self::Error> {
+		let mut timestamps = BTreeMap::new();
+		let mut uncached = BTreeSet::new();
+		{
+			let cache = self.timestamp_cache.lock().unwrap();
+			for block_number in &block_numbers {
+				match cache.get(block_number) {
+					Some(timestamp) => {
+						timestamps.insert(*block_number, *timestamp);
+					},
+					None => {
+						uncached.insert(*block_number);
+					},
+				}
+			}
+		}
+		if !uncached.is_empty() {
+			// one batch for the block hashes, and one for the timestamps stored at them
+			let para_client = unsafe { unsafe_cast_to_jsonrpsee_client(&self.para_ws_client) };
+			let block_hashes: Vec<Option<H256>> = para_client
+				.batch_request(
+					uncached
+						.iter()
+						.map(|block_number| ("chain_getBlockHash", rpc_params![block_number]))
+						.collect(),
+				)
+				.await?;
+			let block_hashes = uncached
+				.iter()
+				.zip(block_hashes)
+				.map(|(block_number, block_hash)| {
+					block_hash.ok_or_else(|| Error::from(format!("Block {block_number} not found")))
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+			let timestamp_key = [twox_128(b"Timestamp"), twox_128(b"Now")].concat();
+			let values: Vec<Option<Bytes>> = para_client
+				.batch_request(
+					block_hashes
+						.iter()
+						.map(|block_hash| {
+							(
+								"state_getStorage",
+								rpc_params![Bytes(timestamp_key.clone()), block_hash],
+							)
+						})
+						.collect(),
+				)
+				.await?;
+
+			let mut cache = self.timestamp_cache.lock().unwrap();
+			for (block_number, value) in uncached.into_iter().zip(values) {
+				let value = value.expect("Timestamp should exist");
+				let unix_timestamp_millis = u64::decode(&mut &*value.0).map_err(|e| {
+					Error::from(format!("Failed to decode timestamp at {block_number}: {e:?}"))
+				})?;
+				let timestamp_nanos = millis_to_nanos(unix_timestamp_millis).ok_or_else(|| {
+					Error::from(format!(
+						"Invalid timestamp {unix_timestamp_millis}ms at {block_number}"
+					))
+				})?;
+				cache.insert(block_number, timestamp_nanos);
+				timestamps.insert(block_number, timestamp_nanos);
+			}
+			while cache.len() > MAX_CACHED_TIMESTAMPS {
+				let oldest = *cache.keys().next().expect("Cache is not empty");
+				cache.remove(&oldest);
+			}
+		}
 
-		Ok(timestamp_nanos)
+		Ok(block_numbers.iter().map(|block_number| timestamps[block_number]).collect())
 	}
 
 	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error> {
@@ -645,7 +809,7 @@ where
 		tx_id: Self::TransactionId,
 	) -> Result<ClientId, Self::Error> {
 		// Query newly created client Id
-		let TransactionId { ext_hash, block_hash } = tx_id;
+		let TransactionId { ext_hash, block_hash, .. } = tx_id;
 		let identified_client_state =
 			IbcApiClient::<u32, H256, <T as config::Config>::AssetId>::query_newly_created_client(
 				&*self.para_ws_client,
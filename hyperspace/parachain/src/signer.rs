@@ -12,96 +12,161 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+
 use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
 use sp_runtime::{
 	app_crypto::CryptoTypePublicPair,
 	traits::{IdentifyAccount, Verify},
 	KeyTypeId, MultiSignature, MultiSigner,
 };
-use subxt::tx::Signer;
+use subxt::{
+	ext::sp_runtime::traits::Hash,
+	tx::{ExtrinsicParams, SubmittableExtrinsic, TxPayload},
+	OnlineClient,
+};
 
-use crate::config;
-use codec::Decode;
+use crate::{config, config::CustomExtrinsicParams, error::Error};
+use codec::{Compact, Decode, Encode};
 use primitives::KeyProvider;
 
-/// A [`Signer`] implementation.
-#[derive(Clone)]
-pub struct ExtrinsicSigner<T: config::Config, Provider: KeyProvider> {
-	account_id: T::AccountId,
-	nonce: Option<T::Index>,
-	signer: MultiSigner,
+/// A [`primitives::signer::Signer`] backed by a key in the local keystore.
+pub struct KeystoreSigner {
 	key_store: SyncCryptoStorePtr,
 	key_type_id: KeyTypeId,
-	_phantom: std::marker::PhantomData<Provider>,
+	public_key: MultiSigner,
 }
 
-impl<T, P> ExtrinsicSigner<T, P>
-where
-	T: config::Config,
-	<T::Signature as Verify>::Signer: From<MultiSigner> + IdentifyAccount<AccountId = T::AccountId>,
-	P: KeyProvider,
-	MultiSigner: From<MultiSigner>,
-{
-	/// Creates a new [`Signer`] from a key store reference and key type
+impl KeystoreSigner {
+	/// Signs with the key of `public_key`, which must already be in the keystore.
 	pub fn new(
 		key_store: SyncCryptoStorePtr,
 		key_type_id: KeyTypeId,
 		public_key: MultiSigner,
 	) -> Self {
-		let account_id = <T::Signature as Verify>::Signer::from(public_key.clone()).into_account();
-		Self {
-			account_id,
-			nonce: None,
-			key_store,
-			key_type_id,
-			signer: MultiSigner::from(public_key),
-			_phantom: Default::default(),
-		}
+		Self { key_store, key_type_id, public_key }
+	}
+}
+
+#[async_trait::async_trait]
+impl primitives::signer::Signer for KeystoreSigner {
+	fn public_key(&self) -> Vec<u8> {
+		self.public_key.as_ref().to_vec()
+	}
+
+	async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, primitives::error::Error> {
+		let crypto_type_id = match &self.public_key {
+			MultiSigner::Ed25519(_) => sp_core::ed25519::CRYPTO_ID,
+			MultiSigner::Sr25519(_) => sp_core::sr25519::CRYPTO_ID,
+			MultiSigner::Ecdsa(_) => sp_core::ecdsa::CRYPTO_ID,
+		};
+		let key = CryptoTypePublicPair(crypto_type_id, self.public_key());
+		SyncCryptoStore::sign_with(&*self.key_store, self.key_type_id, &key, payload)
+			.map_err(|e| {
+				primitives::error::Error::Custom(format!("Keystore failed to sign: {:?}", e))
+			})?
+			.ok_or_else(|| {
+				primitives::error::Error::Custom("Signing key is not in the keystore".to_string())
+			})
 	}
 }
 
-impl<T, P> Signer<T> for ExtrinsicSigner<T, P>
+/// Signs extrinsics through a [`primitives::signer::Signer`]. subxt signs synchronously while the
+/// signer may have to reach a remote backend, so the signer payload is built and signed here.
+#[derive(Clone)]
+pub struct ExtrinsicSigner<T: config::Config, Provider: KeyProvider> {
+	account_id: T::AccountId,
+	nonce: Option<T::Index>,
+	public_key: MultiSigner,
+	signer: Arc<dyn primitives::signer::Signer>,
+	_phantom: std::marker::PhantomData<Provider>,
+}
+
+impl<T, P> ExtrinsicSigner<T, P>
 where
 	T: config::Config,
-	T::AccountId: Into<T::Address> + Clone + 'static,
-	P: KeyProvider + 'static,
+	<T::Signature as Verify>::Signer: From<MultiSigner> + IdentifyAccount<AccountId = T::AccountId>,
+	T::AccountId: Into<T::Address> + Clone,
 	T::Signature: From<MultiSignature>,
+	P: KeyProvider,
 {
-	fn nonce(&self) -> Option<T::Index> {
-		self.nonce
+	/// Creates a new [`ExtrinsicSigner`] signing for `public_key` through `signer`
+	pub fn new(public_key: MultiSigner, signer: Arc<dyn primitives::signer::Signer>) -> Self {
+		let account_id = <T::Signature as Verify>::Signer::from(public_key.clone()).into_account();
+		Self { account_id, nonce: None, public_key, signer, _phantom: Default::default() }
 	}
 
-	fn account_id(&self) -> &T::AccountId {
-		&self.account_id
+	/// Sets the nonce of the extrinsics signed, instead of querying it from the chain.
+	pub fn set_nonce(&mut self, nonce: T::Index) {
+		self.nonce = Some(nonce);
 	}
 
-	fn address(&self) -> T::Address {
-		self.account_id.clone().into()
+	/// Account the extrinsics are signed by.
+	pub fn account_id(&self) -> &T::AccountId {
+		&self.account_id
 	}
 
-	fn sign(&self, signer_payload: &[u8]) -> T::Signature {
-		let (crypto_type_id, public_key) = match &self.signer {
-			MultiSigner::Ed25519(key) => (sp_core::ed25519::CRYPTO_ID, key.0.to_vec()),
-			MultiSigner::Sr25519(key) => (sp_core::sr25519::CRYPTO_ID, key.0.to_vec()),
-			MultiSigner::Ecdsa(key) => (sp_core::ecdsa::CRYPTO_ID, key.0.to_vec()),
+	/// Creates the signed extrinsic for `call`, encoded the same way as subxt's
+	/// [`TxClient::create_signed`](subxt::tx::TxClient::create_signed).
+	pub async fn create_signed<Call: TxPayload>(
+		&self,
+		client: &OnlineClient<T>,
+		call: &Call,
+		other_params: CustomExtrinsicParams<T>,
+	) -> Result<SubmittableExtrinsic<T, OnlineClient<T>>, Error> {
+		client.tx().validate(call)?;
+		let call_data = client.tx().call_data(call)?;
+		let nonce = match self.nonce {
+			Some(nonce) => nonce,
+			None => client.rpc().system_account_next_index(&self.account_id).await?,
 		};
-		let key = CryptoTypePublicPair(crypto_type_id, public_key);
-		let encoded_sig =
-			SyncCryptoStore::sign_with(&*self.key_store, self.key_type_id, &key, signer_payload)
-				.ok()
-				.flatten()
-				.expect("Signing should not fail");
-		let signature: MultiSignature = match self.signer {
-			MultiSigner::Ed25519(_) => sp_core::ed25519::Signature::decode(&mut &encoded_sig[..])
-				.expect("Should decode same signature type as public key; qed")
-				.into(),
-			MultiSigner::Sr25519(_) => sp_core::sr25519::Signature::decode(&mut &encoded_sig[..])
-				.expect("Should decode same signature type as public key; qed")
-				.into(),
-			MultiSigner::Ecdsa(_) => sp_core::ecdsa::Signature::decode(&mut &encoded_sig[..])
-				.expect("Should decode same signature type as public key; qed")
-				.into(),
+		let runtime_version = client.runtime_version();
+		let params = T::ExtrinsicParams::new(
+			runtime_version.spec_version,
+			runtime_version.transaction_version,
+			nonce,
+			client.genesis_hash(),
+			other_params,
+		);
+
+		let mut payload = call_data.clone();
+		params.encode_extra_to(&mut payload);
+		params.encode_additional_to(&mut payload);
+		// payloads longer than 256 bytes are hashed before signing
+		if payload.len() > 256 {
+			payload = <T::Hashing as Hash>::hash(&payload).as_ref().to_vec();
+		}
+		let encoded_sig = self
+			.signer
+			.sign(&payload)
+			.await
+			.map_err(|e| Error::Custom(format!("Failed to sign extrinsic: {:?}", e)))?;
+		let signature: T::Signature = self.decode_signature(&encoded_sig)?.into();
+
+		let mut extrinsic = Vec::new();
+		// signed extrinsic of version 4
+		(0b10000000 + 4u8).encode_to(&mut extrinsic);
+		Into::<T::Address>::into(self.account_id.clone()).encode_to(&mut extrinsic);
+		signature.encode_to(&mut extrinsic);
+		params.encode_extra_to(&mut extrinsic);
+		extrinsic.extend(call_data);
+		let len = u32::try_from(extrinsic.len())
+			.map_err(|_| Error::Custom("Extrinsic is too large".to_string()))?;
+		let mut encoded = Compact(len).encode();
+		encoded.extend(extrinsic);
+
+		Ok(SubmittableExtrinsic::from_bytes(client.clone(), encoded))
+	}
+
+	fn decode_signature(&self, encoded_sig: &[u8]) -> Result<MultiSignature, Error> {
+		let signature = match self.public_key {
+			MultiSigner::Ed25519(_) =>
+				sp_core::ed25519::Signature::decode(&mut &encoded_sig[..])?.into(),
+			MultiSigner::Sr25519(_) =>
+				sp_core::sr25519::Signature::decode(&mut &encoded_sig[..])?.into(),
+			MultiSigner::Ecdsa(_) =>
+				sp_core::ecdsa::Signature::decode(&mut &encoded_sig[..])?.into(),
 		};
-		signature.into()
+		Ok(signature)
 	}
 }
@@ -13,15 +13,20 @@
 // limitations under the License.
 
 use crate::{
-	config, parachain::api, signer::ExtrinsicSigner, utils::unsafe_cast_to_jsonrpsee_client, Error,
-	ParachainClient,
+	config,
+	parachain::api,
+	polkadot,
+	provider::TransactionId,
+	signer::{ExtrinsicSigner, KeystoreSigner},
+	utils::unsafe_cast_to_jsonrpsee_client,
+	Error, ParachainClient,
 };
 use finality_grandpa::BlockNumberOps;
 use futures::{Stream, StreamExt};
 use grandpa_light_client_primitives::{FinalityProof, ParachainHeaderProofs};
 use ibc::{
 	applications::transfer::{msgs::transfer::MsgTransfer, PrefixedCoin},
-	core::ics24_host::identifier::{ChannelId, ClientId, PortId},
+	core::ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
 };
 use ibc_rpc::IbcApiClient;
 use ics10_grandpa::client_message::RelayChainHeader;
@@ -30,15 +35,98 @@ use pallet_ibc::{MultiAddress, Timeout, TransferParams};
 use primitives::{KeyProvider, TestProvider};
 use sp_core::{
 	crypto::{AccountId32, Ss58Codec},
-	H256,
+	sr25519, Pair, H256,
 };
+use sp_keystore::SyncCryptoStore;
 use sp_runtime::{
 	traits::{Header as HeaderT, IdentifyAccount, One, Verify},
-	MultiSignature, MultiSigner,
+	KeyTypeId, MultiSignature, MultiSigner,
 };
-use std::{collections::BTreeMap, fmt::Display, pin::Pin, str::FromStr};
+use std::{collections::BTreeMap, fmt::Display, pin::Pin, str::FromStr, sync::Arc};
 use subxt::tx::{BaseExtrinsicParamsBuilder, ExtrinsicParams, PlainTip};
 
+/// Amount transferred from the faucet on top of the missing fee when none is configured.
+pub const DEFAULT_FAUCET_TOP_UP: u128 = 1_000_000_000_000_000;
+
+impl<T: config::Config + Send + Sync> ParachainClient<T>
+where
+	u32: From<<<T as subxt::Config>::Header as HeaderT>::Number>,
+	Self: KeyProvider,
+	<T::Signature as Verify>::Signer: From<MultiSigner> + IdentifyAccount<AccountId = T::AccountId>,
+	MultiSigner: From<MultiSigner>,
+	<T as subxt::Config>::Address: From<<T as subxt::Config>::AccountId>,
+	T::Signature: From<MultiSignature>,
+	H256: From<T::Hash>,
+	T::BlockNumber: From<u32> + Ord + sp_runtime::traits::Zero + One,
+{
+	/// Funds `to` with `amount` of the native token from the faucet account derived from the
+	/// `faucet_key` secret uri, so tests can run with fresh relayer accounts.
+	pub async fn fund_account(
+		&self,
+		faucet_key: &str,
+		to: AccountId32,
+		amount: u128,
+	) -> Result<(), Error> {
+		let key_type_id = KeyTypeId(sr25519::CRYPTO_ID.0);
+		let faucet = sr25519::Pair::from_string_with_seed(faucet_key, None)
+			.map_err(|_| Error::Custom("invalid faucet key".to_owned()))?
+			.0
+			.public();
+		SyncCryptoStore::insert_unknown(&*self.key_store, key_type_id, faucet_key, faucet.as_ref())
+			.map_err(|_| Error::Custom("failed to insert faucet key".to_owned()))?;
+		let public_key = MultiSigner::from(faucet);
+		let signer = ExtrinsicSigner::<T, Self>::new(
+			public_key.clone(),
+			Arc::new(KeystoreSigner::new(self.key_store.clone(), key_type_id, public_key)),
+		);
+
+		let to: [u8; 32] = to.into();
+		let call = api::tx()
+			.balances()
+			.transfer(subxt::ext::sp_runtime::MultiAddress::Id(to.into()), amount);
+
+		let other_params = T::custom_extrinsic_params(&self.para_client, self.tip).await?;
+		let extrinsic = signer.create_signed(&self.para_client, &call, other_params).await?;
+		if self.dry_run {
+			return Err(self.dry_run_error(extrinsic.encoded()))
+		}
+		extrinsic
+			.submit_and_watch()
+			.await?
+			.wait_for_in_block()
+			.await?
+			.wait_for_success()
+			.await?;
+
+		Ok(())
+	}
+
+	/// Tops up the relayer account from the configured faucet if its free balance can't pay
+	/// `fee`, so long running tests don't stall on an empty account.
+	pub async fn top_up_from_faucet(&self, fee: u128) -> Result<(), Error> {
+		let faucet_key = match &self.faucet_key {
+			Some(faucet_key) => faucet_key,
+			None => return Ok(()),
+		};
+		let account = self.signing_key()?.public_key.into_account();
+		let account_addr = api::storage().system().account(&account);
+		let free = self
+			.para_client
+			.storage()
+			.fetch(&account_addr, None)
+			.await?
+			.map(|account| account.data.free)
+			.unwrap_or_default();
+		if free >= fee {
+			return Ok(())
+		}
+
+		let amount = fee.saturating_sub(free).saturating_add(self.faucet_top_up);
+		log::info!("Topping up relayer account on {} with {} from the faucet", self.name, amount);
+		self.fund_account(faucet_key, account, amount).await
+	}
+}
+
 impl<T: config::Config + Send + Sync> ParachainClient<T>
 where
 	u32: From<<<T as subxt::Config>::Header as HeaderT>::Number>,
@@ -59,8 +147,15 @@ where
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
 	T::BlockNumber: Ord + sp_runtime::traits::Zero,
 {
-	pub fn set_client_id(&mut self, client_id: ClientId) {
-		self.client_id = Some(client_id)
+	/// Sets the light client id on the counterparty chain used by `connection_id`, or the default
+	/// client id if no connection is given.
+	pub fn set_client_id(&mut self, connection_id: Option<ConnectionId>, client_id: ClientId) {
+		match connection_id {
+			Some(connection_id) => {
+				self.connection_clients.insert(connection_id, client_id);
+			},
+			None => self.client_id = Some(client_id),
+		}
 	}
 
 	pub async fn submit_create_client_msg(&self, msg: pallet_ibc::Any) -> Result<ClientId, Error> {
@@ -68,7 +163,7 @@ where
 			type_url: msg.type_url,
 			value: msg.value,
 		}]);
-		let (ext_hash, block_hash) = self.submit_call(call).await?;
+		let TransactionId { ext_hash, block_hash, .. } = self.submit_call(call).await?;
 
 		// Query newly created client Id
 		let identified_client_state =
@@ -120,28 +215,35 @@ where
 		&self,
 		call: api::runtime_types::parachain_runtime::Call,
 	) -> Result<(), Error> {
-		let signer = ExtrinsicSigner::<T, Self>::new(
-			self.key_store.clone(),
-			self.key_type_id.clone(),
-			self.public_key.clone(),
-		);
-
 		let ext = api::tx().sudo().sudo(call);
 		// Submit extrinsic to parachain node
+		self.submit_call(ext).await.map(|_| ())
+	}
 
-		let other_params = T::custom_extrinsic_params(&self.para_client).await?;
-
-		let _progress = self
+	/// Schedules a runtime upgrade that re-applies the current runtime code. The upgrade goes
+	/// through the relay chain like any other parachain code upgrade.
+	pub async fn schedule_runtime_upgrade(&self) -> Result<(), Error> {
+		let code = self
 			.para_client
-			.tx()
-			.sign_and_submit_then_watch(&ext, &signer, other_params)
+			.rpc()
+			.storage(sp_core::storage::well_known_keys::CODE, None)
 			.await?
-			.wait_for_in_block()
-			.await?
-			.wait_for_success()
-			.await?;
+			.ok_or_else(|| Error::Custom("Runtime code not found in storage".to_owned()))?
+			.0;
+		let call = api::runtime_types::parachain_runtime::Call::System(
+			api::runtime_types::frame_system::pallet::Call::set_code_without_checks { code },
+		);
 
-		Ok(())
+		self.submit_sudo_call(call).await
+	}
+
+	/// Returns the relay chain block at which a pending code upgrade for this parachain will be
+	/// applied, if any.
+	pub async fn pending_code_upgrade(&self) -> Result<Option<u32>, Error> {
+		let key = polkadot::api::storage().paras().future_code_upgrades(
+			&polkadot::api::runtime_types::polkadot_parachain::primitives::Id(self.para_id),
+		);
+		Ok(self.relay_client.storage().fetch(&key, None).await?)
 	}
 
 	pub async fn set_pallet_params(
@@ -187,14 +289,16 @@ where
 		let params = TransferParams {
 			to: MultiAddress::Id(account_id),
 			source_channel: transfer.source_channel.sequence(),
+			// a zero height or timestamp disables that half of the timeout
 			timeout: Timeout::Absolute {
-				timestamp: Some(transfer.timeout_timestamp.nanoseconds()),
-				height: Some(transfer.timeout_height.revision_height),
+				timestamp: Some(transfer.timeout_timestamp.nanoseconds()).filter(|ts| *ts != 0),
+				height: Some(transfer.timeout_height.revision_height).filter(|h| *h != 0),
 			},
 		};
 		let amount = str::parse::<u128>(&transfer.token.amount.to_string()).expect("Infallible!");
 		dbg!(&amount);
-		self.transfer_tokens(params, 1, amount).await?;
+		let asset_id = self.asset_id(&transfer.token.denom)?;
+		self.transfer_tokens(params, asset_id.into(), amount).await?;
 
 		Ok(())
 	}
@@ -14,16 +14,118 @@
 
 use crate::{config, Error};
 use beefy_light_client_primitives::{ClientState, MmrUpdateProof};
-use std::sync::Arc;
+use futures::{stream, Stream, StreamExt};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use tokio::time::{timeout_at, Instant};
 
-use crate::parachain::api::runtime_types::pallet_ibc::events::IbcEvent as MetadataIbcEvent;
+use crate::parachain::api::runtime_types::{
+	pallet_ibc::events::IbcEvent as MetadataIbcEvent, sp_runtime::DispatchError,
+};
 use beefy_primitives::known_payload_ids::MMR_ROOT_ID;
 use beefy_prover::helpers::unsafe_arc_cast;
-use codec::Decode;
+use codec::{Decode, Encode};
 use frame_support::weights::DispatchClass;
 use frame_system::limits::BlockWeights;
+use ibc::{
+	core::ics24_host::identifier::{ChannelId, ClientId, PortId},
+	Height,
+};
+use ibc_primitives::get_channel_escrow_address;
+use ibc_rpc::{CONSENSUS_STATE_NOT_FOUND_CODE, STATE_UNAVAILABLE_CODE};
+use jsonrpsee::types::error::CallError;
 use pallet_ibc::events::IbcEvent as RawIbcEvent;
-use sp_core::H256;
+use primitives::alert::{Alerts, Severity};
+use sp_core::{crypto::AccountId32, H256};
+
+/// Returns the code of the error object a node responded to an rpc call with.
+pub fn rpc_error_code(error: &jsonrpsee::core::Error) -> Option<i32> {
+	match error {
+		jsonrpsee::core::Error::Call(CallError::Custom(error)) => Some(error.code()),
+		_ => None,
+	}
+}
+
+/// Result code of a failed dispatch, the first bytes of its SCALE encoding: the `DispatchError`
+/// variant, followed by the pallet and error index of module errors.
+pub fn dispatch_error_code(error: &DispatchError) -> u32 {
+	error
+		.encode()
+		.into_iter()
+		.take(3)
+		.fold(0, |code, byte| code << 8 | u32::from(byte))
+}
+
+/// Converts the error of an rpc query made at `height`, detecting queries for state that the node
+/// has already pruned.
+pub fn query_error_at(height: Height, error: jsonrpsee::core::Error) -> Error {
+	if rpc_error_code(&error) == Some(STATE_UNAVAILABLE_CODE) {
+		Error::HeightPruned(height.revision_height)
+	} else {
+		Error::from(format!("Rpc Error {:?}", error))
+	}
+}
+
+/// Returns [`primitives::error::Error::ConsensusStatePruned`] if the rpc query for the consensus
+/// state of `client_id` at `height` on `chain` failed because pallet-ibc no longer holds it.
+pub fn consensus_state_pruned(
+	chain: &str,
+	client_id: &ClientId,
+	height: Height,
+	error: &jsonrpsee::core::Error,
+) -> Option<Error> {
+	(rpc_error_code(error) == Some(CONSENSUS_STATE_NOT_FOUND_CODE)).then(|| {
+		primitives::error::Error::ConsensusStatePruned {
+			client_id: client_id.clone(),
+			chain: chain.to_string(),
+			height,
+		}
+		.into()
+	})
+}
+
+/// Waits `window` after each item of `stream` and yields only the latest item received in that
+/// time, as long as `can_replace` allows an item to supersede the one before it.
+pub fn coalesce_within<S, F>(
+	stream: S,
+	window: Duration,
+	can_replace: F,
+) -> impl Stream<Item = S::Item>
+where
+	S: Stream + Unpin,
+	F: Fn(&S::Item, &S::Item) -> bool + Clone,
+{
+	stream::unfold((stream.fuse(), None), move |(mut stream, pending)| {
+		let can_replace = can_replace.clone();
+		async move {
+			let mut item = match pending {
+				Some(item) => item,
+				None => stream.next().await?,
+			};
+			let deadline = Instant::now() + window;
+			loop {
+				match timeout_at(deadline, stream.next()).await {
+					Ok(Some(next)) if can_replace(&item, &next) => item = next,
+					Ok(Some(next)) => return Some((item, (stream, Some(next)))),
+					Ok(None) | Err(_) => return Some((item, (stream, None))),
+				}
+			}
+		}
+	})
+}
+
+/// Derives the account the ics20 module of pallet-ibc escrows the tokens sent out of the given
+/// channel in.
+pub fn channel_escrow_account(
+	port_id: &PortId,
+	channel_id: ChannelId,
+) -> Result<AccountId32, Error> {
+	let address = get_channel_escrow_address(port_id, channel_id)?;
+	let bytes = hex::decode(address.as_ref().trim_start_matches("0x"))
+		.map_err(|e| Error::Custom(format!("Invalid escrow address {}: {}", address, e)))?;
+	let bytes = <[u8; 32]>::try_from(bytes)
+		.map_err(|_| Error::Custom(format!("Escrow address {} is not 32 bytes", address)))?;
+	Ok(AccountId32::from(bytes))
+}
 
 /// Allows to implement traits for the subxt generated code
 pub struct MetadataIbcEventWrapper(pub MetadataIbcEvent);
@@ -390,6 +492,142 @@ pub async fn fetch_max_extrinsic_weight<T: config::Config>(
 	Ok(max_extrinsic_weight)
 }
 
+/// Delay before resubscribing to the runtime upgrades of a chain once the subscription ended.
+const RUNTIME_UPDATES_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Applies runtime upgrades of the chain to `client` with subxt's runtime updater, so extrinsics
+/// keep being encoded with the call indices and spec version of the new runtime. The
+/// subscription is renewed whenever it ends.
+pub async fn follow_runtime_upgrades<T: subxt::Config>(
+	name: String,
+	client: subxt::OnlineClient<T>,
+) {
+	loop {
+		match client.subscribe_to_updates().perform_runtime_updates().await {
+			Ok(()) => log::warn!("Runtime upgrade subscription of {name} ended, resubscribing"),
+			Err(e) => log::error!("Failed to follow runtime upgrades of {name}: {:?}", e),
+		}
+		tokio::time::sleep(RUNTIME_UPDATES_RETRY_DELAY).await;
+	}
+}
+
+/// Time a websocket connection may be lost for before an alert is sent, when none is configured.
+pub const DEFAULT_CONNECTION_LOSS_ALERT: Duration = Duration::from_secs(30);
+
+/// Interval at which [`alert_on_connection_loss`] checks the connection.
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sends a critical alert once the websocket connection to `name` has been lost for longer than
+/// `threshold`, repeated every [`primitives::alert::ALERT_COOLDOWN`] while it stays lost and
+/// resolved once it is back.
+pub async fn alert_on_connection_loss(
+	name: String,
+	client: Arc<jsonrpsee_ws_client::WsClient>,
+	threshold: Duration,
+	alerts: Alerts,
+) {
+	let key = format!("connection/{name}");
+	let mut interval = tokio::time::interval(CONNECTION_CHECK_INTERVAL);
+	let mut lost_since = None;
+	loop {
+		interval.tick().await;
+		if client.is_connected() {
+			if lost_since.take().is_some() {
+				alerts.resolve(&key);
+			}
+			continue
+		}
+		let lost_since = *lost_since.get_or_insert_with(Instant::now);
+		if lost_since.elapsed() >= threshold {
+			let message =
+				format!("Lost the websocket connection to {name} {:?} ago", lost_since.elapsed());
+			alerts.alert(&key, Severity::Critical, message).await;
+		}
+	}
+}
+
+/// Number of recent intervals between cached block timestamps used to measure the block time.
+const BLOCK_TIME_SAMPLES: usize = 32;
+
+/// Measures the block time as the median of the average block times between consecutive
+/// entries of a cache of block timestamps in nanoseconds, keyed by block number.
+pub fn median_block_time(timestamps: &BTreeMap<u64, u64>) -> Option<Duration> {
+	let timestamps = timestamps.iter().collect::<Vec<_>>();
+	let mut samples = timestamps
+		.windows(2)
+		.rev()
+		.take(BLOCK_TIME_SAMPLES)
+		.filter_map(|pair| {
+			let ((height_a, time_a), (height_b, time_b)) = (pair[0], pair[1]);
+			time_b.checked_sub(*time_a).map(|elapsed| elapsed / (height_b - height_a))
+		})
+		.collect::<Vec<_>>();
+	if samples.is_empty() {
+		return None
+	}
+	samples.sort_unstable();
+	Some(Duration::from_nanos(samples[samples.len() / 2]))
+}
+
+/// Native token metadata advertised by a substrate node in its system properties.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMetadata {
+	/// Ticker of the native token.
+	pub symbol: String,
+	/// Number of decimals of one whole unit of the token.
+	pub decimals: u32,
+}
+
+impl Default for TokenMetadata {
+	fn default() -> Self {
+		Self { symbol: "UNIT".to_string(), decimals: 0 }
+	}
+}
+
+impl TokenMetadata {
+	/// Reads the native token from the system properties of a chain spec, falling back to
+	/// [`TokenMetadata::default`] for values it doesn't define.
+	pub fn from_properties(properties: &serde_json::Map<String, serde_json::Value>) -> Self {
+		// multi-token chains advertise arrays, the first entry is the native token.
+		let first = |key: &str| match properties.get(key) {
+			Some(serde_json::Value::Array(values)) => values.first().cloned(),
+			value => value.cloned(),
+		};
+		let default = TokenMetadata::default();
+		let symbol = first("tokenSymbol")
+			.and_then(|value| value.as_str().map(ToString::to_string))
+			.unwrap_or(default.symbol);
+		let decimals = first("tokenDecimals")
+			.and_then(|value| value.as_u64())
+			.map(|decimals| decimals as u32)
+			.unwrap_or(default.decimals);
+		Self { symbol, decimals }
+	}
+
+	/// Formats a raw on-chain amount in whole units of the token, e.g. `1.5 DOT`.
+	pub fn display_amount(&self, amount: u128) -> String {
+		let unit = match 10u128.checked_pow(self.decimals) {
+			Some(unit) if self.decimals > 0 => unit,
+			_ => return format!("{} {}", amount, self.symbol),
+		};
+		let fraction = format!("{:0width$}", amount % unit, width = self.decimals as usize);
+		let fraction = fraction.trim_end_matches('0');
+		if fraction.is_empty() {
+			format!("{} {}", amount / unit, self.symbol)
+		} else {
+			format!("{}.{} {}", amount / unit, fraction, self.symbol)
+		}
+	}
+}
+
+/// Fetch the native token symbol and decimals from a substrate node with the given client.
+pub async fn fetch_token_metadata<T: config::Config>(
+	client: &subxt::OnlineClient<T>,
+) -> Result<TokenMetadata, Error> {
+	let properties = client.rpc().system_properties().await?;
+	Ok(TokenMetadata::from_properties(&properties))
+}
+
 pub unsafe fn unsafe_cast_to_jsonrpsee_client(
 	client: &Arc<jsonrpsee_ws_client::WsClient>,
 ) -> Arc<jsonrpsee::core::client::Client> {
@@ -0,0 +1,177 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use futures::{channel::mpsc, StreamExt};
+use hyperspace_parachain::{
+	error::Error,
+	utils::{coalesce_within, median_block_time, query_error_at, TokenMetadata},
+};
+use ibc::Height;
+use ibc_rpc::{CONSENSUS_STATE_NOT_FOUND_CODE, STATE_UNAVAILABLE_CODE};
+use jsonrpsee::{
+	core::Error as RpcError,
+	types::{error::CallError, ErrorObject},
+};
+use serde_json::json;
+use std::{collections::BTreeMap, time::Duration};
+use tokio::time::sleep;
+
+const SECOND: u64 = 1_000_000_000;
+
+fn properties(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+	value.as_object().cloned().unwrap()
+}
+
+#[test]
+fn token_metadata_is_read_from_system_properties() {
+	let metadata = TokenMetadata::from_properties(&properties(
+		json!({ "tokenSymbol": "DOT", "tokenDecimals": 10, "ss58Format": 0 }),
+	));
+	assert_eq!(metadata, TokenMetadata { symbol: "DOT".to_string(), decimals: 10 });
+}
+
+#[test]
+fn first_token_of_multi_token_chains_is_native() {
+	let metadata = TokenMetadata::from_properties(&properties(
+		json!({ "tokenSymbol": ["KAR", "KUSD"], "tokenDecimals": [12, 12] }),
+	));
+	assert_eq!(metadata, TokenMetadata { symbol: "KAR".to_string(), decimals: 12 });
+}
+
+#[test]
+fn missing_token_properties_fall_back_to_the_default() {
+	let metadata = TokenMetadata::from_properties(&properties(json!({ "tokenDecimals": 18 })));
+	assert_eq!(metadata, TokenMetadata { symbol: "UNIT".to_string(), decimals: 18 });
+	let metadata = TokenMetadata::from_properties(&properties(json!({})));
+	assert_eq!(metadata, TokenMetadata::default());
+}
+
+#[test]
+fn amounts_are_displayed_in_whole_units() {
+	let dot = TokenMetadata { symbol: "DOT".to_string(), decimals: 10 };
+	assert_eq!(dot.display_amount(15_000_000_000), "1.5 DOT");
+	assert_eq!(dot.display_amount(20_000_000_000), "2 DOT");
+	assert_eq!(dot.display_amount(1), "0.0000000001 DOT");
+	assert_eq!(TokenMetadata::default().display_amount(42), "42 UNIT");
+	// more decimals than a u128 can scale by are shown raw
+	let huge = TokenMetadata { symbol: "HUGE".to_string(), decimals: 40 };
+	assert_eq!(huge.display_amount(7), "7 HUGE");
+}
+
+fn rpc_error(code: i32) -> RpcError {
+	RpcError::Call(CallError::Custom(ErrorObject::owned(
+		code,
+		"State already discarded",
+		None::<()>,
+	)))
+}
+
+#[test]
+fn queries_at_pruned_heights_are_detected_by_error_code() {
+	let height = Height::new(0, 42);
+	assert!(matches!(
+		query_error_at(height, rpc_error(STATE_UNAVAILABLE_CODE)),
+		Error::HeightPruned(42)
+	));
+	// the message alone doesn't make it a pruned height
+	assert!(!matches!(
+		query_error_at(height, rpc_error(CONSENSUS_STATE_NOT_FOUND_CODE)),
+		Error::HeightPruned(_)
+	));
+}
+
+#[test]
+fn median_block_time_needs_two_timestamps() {
+	assert_eq!(median_block_time(&BTreeMap::new()), None);
+	assert_eq!(median_block_time(&BTreeMap::from([(1, 6 * SECOND)])), None);
+}
+
+#[test]
+fn median_block_time_averages_over_skipped_blocks() {
+	let timestamps = BTreeMap::from([(1, 0), (2, 6 * SECOND), (5, 24 * SECOND)]);
+	assert_eq!(median_block_time(&timestamps), Some(Duration::from_secs(6)));
+}
+
+#[test]
+fn median_block_time_ignores_outliers() {
+	// a slow block and timestamps going backwards don't move the median
+	let timestamps = BTreeMap::from([
+		(1, 0),
+		(2, 12 * SECOND),
+		(3, 24 * SECOND),
+		(4, 90 * SECOND),
+		(5, 80 * SECOND),
+		(6, 92 * SECOND),
+		(7, 104 * SECOND),
+	]);
+	assert_eq!(median_block_time(&timestamps), Some(Duration::from_secs(12)));
+}
+
+#[test]
+fn median_block_time_follows_recent_blocks() {
+	// blocks slowed down from 6 to 12 seconds long ago, only the recent intervals are sampled
+	let mut timestamps = (0..100).map(|height| (height, height * 6 * SECOND)).collect::<BTreeMap<_, _>>();
+	let start = 99 * 6 * SECOND;
+	timestamps.extend((100..140).map(|height| (height, start + (height - 99) * 12 * SECOND)));
+	assert_eq!(median_block_time(&timestamps), Some(Duration::from_secs(12)));
+}
+
+const WINDOW: Duration = Duration::from_millis(100);
+
+/// Sends `items` after the given delays, then ends the stream, returning what
+/// [`coalesce_within`] yields for them.
+async fn coalesce(
+	items: Vec<(Duration, u64)>,
+	can_replace: impl Fn(&u64, &u64) -> bool + Clone,
+) -> Vec<u64> {
+	let (sender, receiver) = mpsc::unbounded();
+	tokio::spawn(async move {
+		for (delay, item) in items {
+			sleep(delay).await;
+			sender.unbounded_send(item).unwrap();
+		}
+	});
+	coalesce_within(receiver, WINDOW, can_replace).collect().await
+}
+
+#[tokio::test(start_paused = true)]
+async fn items_within_the_window_are_coalesced() {
+	let items = vec![(Duration::ZERO, 1), (Duration::ZERO, 2), (WINDOW - Duration::from_millis(1), 3)];
+	assert_eq!(coalesce(items, |_, _| true).await, vec![3]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn window_is_not_extended_by_later_items() {
+	// the window starts at the first item, so an item right after it opens a new one
+	let items = vec![
+		(Duration::ZERO, 1),
+		(WINDOW / 2, 2),
+		(WINDOW / 2 + Duration::from_millis(1), 3),
+		(Duration::ZERO, 4),
+	];
+	assert_eq!(coalesce(items, |_, _| true).await, vec![2, 4]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn items_that_cannot_be_replaced_are_yielded() {
+	// items of different tens, like different validator sets, are never coalesced
+	let items = vec![(Duration::ZERO, 1), (Duration::ZERO, 2), (Duration::ZERO, 11), (Duration::ZERO, 12)];
+	assert_eq!(coalesce(items, |previous, next| previous / 10 == next / 10).await, vec![2, 12]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn pending_item_is_yielded_when_the_stream_ends() {
+	assert_eq!(coalesce(vec![(Duration::ZERO, 1)], |_, _| true).await, vec![1]);
+	assert_eq!(coalesce(vec![], |_, _| true).await, Vec::<u64>::new());
+}
@@ -0,0 +1,151 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Alerts on conditions that need an operator's attention, sent to a pluggable [`AlertSink`].
+
+use std::{
+	collections::HashMap,
+	fmt,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use hyper::{client::HttpConnector, Body, Client, Method, Request};
+use hyper_rustls::HttpsConnector;
+use serde::Deserialize;
+
+/// Time during which an alert with the same key is not sent again.
+pub const ALERT_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// Time after which sending an alert is given up on.
+pub const ALERT_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How urgently an alert needs attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+	/// Relaying will stop if nothing is done
+	Warning,
+	/// Relaying has stopped
+	Critical,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Severity::Warning => write!(f, "warning"),
+			Severity::Critical => write!(f, "critical"),
+		}
+	}
+}
+
+/// Destination of relayer alerts.
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+	/// Delivers `message` with the given `severity`.
+	async fn send(&self, severity: Severity, message: &str) -> Result<(), anyhow::Error>;
+}
+
+/// Webhook urls alerts are posted to, one per severity. The payload is Slack compatible.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookSink {
+	/// Url warnings are posted to
+	pub warning: Option<String>,
+	/// Url critical alerts are posted to
+	pub critical: Option<String>,
+}
+
+impl WebhookSink {
+	fn url(&self, severity: Severity) -> Option<&str> {
+		match severity {
+			Severity::Warning => self.warning.as_deref(),
+			Severity::Critical => self.critical.as_deref(),
+		}
+	}
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+	async fn send(&self, severity: Severity, message: &str) -> Result<(), anyhow::Error> {
+		let url = match self.url(severity) {
+			Some(url) => url,
+			None => return Ok(()),
+		};
+		let connector: HttpsConnector<HttpConnector> = hyper_rustls::HttpsConnectorBuilder::new()
+			.with_native_roots()
+			.https_or_http()
+			.enable_http1()
+			.build();
+		let body = serde_json::json!({ "text": format!("[{severity}] {message}") });
+		let request = Request::builder()
+			.method(Method::POST)
+			.uri(url)
+			.header("content-type", "application/json")
+			.body(Body::from(body.to_string()))?;
+		let response = Client::builder().build::<_, Body>(connector).request(request).await?;
+		if !response.status().is_success() {
+			anyhow::bail!("Webhook {url} responded with {}", response.status())
+		}
+		Ok(())
+	}
+}
+
+/// Sends alerts to an [`AlertSink`], dropping them if there is none. Clones share the time each
+/// alert was last sent.
+#[derive(Clone, Default)]
+pub struct Alerts {
+	sink: Option<Arc<dyn AlertSink>>,
+	last_sent: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl fmt::Debug for Alerts {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Alerts").field("has_sink", &self.sink.is_some()).finish()
+	}
+}
+
+impl Alerts {
+	pub fn new(sink: Arc<dyn AlertSink>) -> Self {
+		Self { sink: Some(sink), last_sent: Default::default() }
+	}
+
+	/// Sends `message` to the sink. Alerts with the same `key` are sent at most once every
+	/// [`ALERT_COOLDOWN`], so conditions can be reported every time they are checked.
+	pub async fn alert(&self, key: &str, severity: Severity, message: String) {
+		let sink = match &self.sink {
+			Some(sink) => sink,
+			None => return,
+		};
+		{
+			let mut last_sent = self.last_sent.lock().unwrap();
+			let now = Instant::now();
+			match last_sent.get(key) {
+				Some(sent) if now.duration_since(*sent) < ALERT_COOLDOWN => return,
+				_ => {},
+			}
+			last_sent.insert(key.to_string(), now);
+		}
+		match tokio::time::timeout(ALERT_SEND_TIMEOUT, sink.send(severity, &message)).await {
+			Ok(Ok(())) => {},
+			Ok(Err(e)) => log::warn!("Failed to send {} alert \"{}\": {:?}", severity, message, e),
+			Err(_) => log::warn!("Timed out sending {} alert \"{}\"", severity, message),
+		}
+	}
+
+	/// Marks the condition reported under `key` as resolved, so it is alerted on again as soon as
+	/// it reoccurs.
+	pub fn resolve(&self, key: &str) {
+		self.last_sent.lock().unwrap().remove(key);
+	}
+}
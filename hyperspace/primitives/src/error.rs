@@ -12,7 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::string::FromUtf8Error;
+use ibc::{core::ics24_host::identifier::ClientId, Height};
+use std::{string::FromUtf8Error, time::Duration};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -43,6 +44,30 @@ pub enum Error {
 	HexDecode(#[from] hex::FromHexError),
 	#[error("String from utf-8 error")]
 	StringFromUtf8(#[from] FromUtf8Error),
+	/// The light client's trusting period has elapsed
+	#[error("Client {client_id} on {chain} has expired, {elapsed:?} elapsed since its latest consensus state")]
+	ClientExpired { client_id: ClientId, chain: String, elapsed: Duration },
+	/// The light client has been frozen after misbehaviour
+	#[error("Client {client_id} on {chain} is frozen at {height}")]
+	ClientFrozen { client_id: ClientId, chain: String, height: Height },
+	/// The transaction was included on chain, but failed with the result `code`
+	#[error("Transaction {tx_hash} failed on {chain} with code {code}: {reason}")]
+	TransactionFailed { chain: String, tx_hash: String, code: u32, reason: String },
+	/// The transaction was signed but not submitted, as the chain is in dry run mode
+	#[error("Dry run on {chain}, transaction {tx_hash} was not submitted")]
+	DryRun { chain: String, tx_hash: String },
+	/// The host no longer holds the consensus state of the client at the height
+	#[error("Consensus state of {client_id} at {height} has been pruned on {chain}")]
+	ConsensusStatePruned { client_id: ClientId, chain: String, height: Height },
+}
+
+impl Error {
+	/// Whether `error` was caused by a transaction that wasn't submitted in dry run mode.
+	pub fn is_dry_run(error: &anyhow::Error) -> bool {
+		error
+			.chain()
+			.any(|e| matches!(e.downcast_ref::<Self>(), Some(Self::DryRun { .. })))
+	}
 }
 
 impl From<String> for Error {
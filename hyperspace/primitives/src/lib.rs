@@ -14,7 +14,7 @@
 
 #![allow(clippy::all)]
 
-use std::{pin::Pin, str::FromStr, time::Duration};
+use std::{collections::BTreeMap, pin::Pin, str::FromStr, sync::Arc, time::Duration};
 
 use futures::Stream;
 use ibc_proto::{
@@ -30,7 +30,11 @@ use ibc_proto::{
 	},
 };
 
-use crate::error::Error;
+use crate::{
+	alert::{Alerts, Severity},
+	error::Error,
+	packet_data::PacketFilter,
+};
 #[cfg(feature = "testing")]
 use ibc::applications::transfer::msgs::transfer::MsgTransfer;
 use ibc::{
@@ -59,9 +63,14 @@ use ibc_proto::ibc::core::{
 };
 use ibc_rpc::PacketInfo;
 use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState, AnyConsensusState};
+use serde::Deserialize;
 
+pub mod alert;
 pub mod error;
 pub mod mock;
+pub mod packet_data;
+pub mod signer;
+pub mod time;
 pub mod utils;
 
 pub enum UpdateMessage {
@@ -85,6 +94,76 @@ impl UpdateType {
 	}
 }
 
+/// Packet messages relayed for a channel from the events of the chain it belongs to, so redundant
+/// relayers can split the work on a channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayMode {
+	/// Relay every packet message
+	#[default]
+	All,
+	/// Only deliver packets sent from the chain, and time them out when they expire
+	RecvOnly,
+	/// Only deliver acknowledgements of packets received by the chain
+	AckOnly,
+}
+
+impl RelayMode {
+	/// Whether packets sent from the chain are delivered or timed out.
+	pub fn relays_packets(&self) -> bool {
+		matches!(self, RelayMode::All | RelayMode::RecvOnly)
+	}
+
+	/// Whether acknowledgements of packets received by the chain are delivered.
+	pub fn relays_acks(&self) -> bool {
+		matches!(self, RelayMode::All | RelayMode::AckOnly)
+	}
+}
+
+/// An upgrade that has been scheduled on a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradePlan {
+	/// Name of the upgrade, used in logs.
+	pub name: String,
+	/// Height at which the chain halts for the upgrade.
+	pub height: Height,
+}
+
+impl UpgradePlan {
+	/// Returns true if a chain at `height` has reached the upgrade halt height.
+	pub fn is_reached(&self, height: Height) -> bool {
+		height.revision_height >= self.height.revision_height
+	}
+}
+
+/// Port bound by the interchain accounts host module.
+pub const ICA_HOST_PORT: &str = "icahost";
+
+/// Prefix of the ports bound by interchain accounts controllers, followed by the owner address.
+pub const ICA_CONTROLLER_PORT_PREFIX: &str = "icacontroller-";
+
+/// Returns true if `port_id` belongs to an interchain accounts host or controller.
+pub fn is_ica_port(port_id: &PortId) -> bool {
+	port_id.as_str() == ICA_HOST_PORT || port_id.as_str().starts_with(ICA_CONTROLLER_PORT_PREFIX)
+}
+
+/// Port bound by the async interchain queries host module, which executes the queries it
+/// receives and acknowledges them with the responses.
+pub const ICQ_HOST_PORT: &str = "icqhost";
+
+/// Returns true if `port_id` belongs to an async interchain queries host.
+pub fn is_icq_port(port_id: &PortId) -> bool {
+	port_id.as_str() == ICQ_HOST_PORT
+}
+
+/// Prefix of the ports bound by IBC enabled CosmWasm contracts, followed by the contract address.
+pub const WASM_PORT_PREFIX: &str = "wasm.";
+
+/// Returns true if `port_id` is bound by a CosmWasm contract.
+pub fn is_wasm_port(port_id: &PortId) -> bool {
+	port_id.as_str().starts_with(WASM_PORT_PREFIX)
+}
+
 pub fn apply_prefix(mut commitment_prefix: Vec<u8>, path: String) -> Vec<u8> {
 	let path = path.as_bytes().to_vec();
 	commitment_prefix.extend_from_slice(&path);
@@ -98,20 +177,21 @@ pub trait IbcProvider {
 	/// Finality event type, passed on to [`Chain::query_latest_ibc_events`]
 	type FinalityEvent;
 
-	/// A representation of the transaction id for the chain
-	type TransactionId;
+	/// A representation of the transaction id for the chain, displayed as its hash
+	type TransactionId: std::fmt::Display;
 
 	/// Error type, just needs to implement standard error trait.
 	type Error: std::error::Error + From<String> + Send + Sync + 'static;
 
 	/// Query the latest ibc events finalized by the recent finality event. Use the counterparty
 	/// [`Chain`] to query the on-chain [`ClientState`] so you can scan for new events in between
-	/// the client state and the new finality event.
+	/// the client state and the new finality event. Returns the client updates to submit in order,
+	/// the last one proving the events.
 	async fn query_latest_ibc_events<T>(
 		&mut self,
 		finality_event: Self::FinalityEvent,
 		counterparty: &T,
-	) -> Result<(Any, Vec<IbcEvent>, UpdateType), anyhow::Error>
+	) -> Result<(Vec<Any>, Vec<IbcEvent>, UpdateType), anyhow::Error>
 	where
 		T: Chain;
 
@@ -134,6 +214,19 @@ pub trait IbcProvider {
 		client_id: ClientId,
 	) -> Result<QueryClientStateResponse, Self::Error>;
 
+	/// Query the client state committed for a planned upgrade at `upgrade_height`, with proof.
+	/// Used to build `MsgUpgradeClient` for the counterparty's client of this chain.
+	async fn query_upgraded_client_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryClientStateResponse, Self::Error>;
+
+	/// Query the consensus state committed for a planned upgrade at `upgrade_height`, with proof.
+	async fn query_upgraded_consensus_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryConsensusStateResponse, Self::Error>;
+
 	/// Query connection end with proof
 	async fn query_connection_end(
 		&self,
@@ -152,6 +245,14 @@ pub trait IbcProvider {
 	/// Query proof for provided key path
 	async fn query_proof(&self, at: Height, keys: Vec<Vec<u8>>) -> Result<Vec<u8>, Self::Error>;
 
+	/// Query the value stored under `key` in the ibc store, with a proof of its membership or of
+	/// its non-membership if it isn't set. Used to answer ICS-31 cross-chain queries.
+	async fn query_key_with_proof(
+		&self,
+		at: Height,
+		key: Vec<u8>,
+	) -> Result<(Option<Vec<u8>>, Vec<u8>), Self::Error>;
+
 	/// Query packet commitment with proof
 	async fn query_packet_commitment(
 		&self,
@@ -235,6 +336,18 @@ pub trait IbcProvider {
 	/// Channel whitelist
 	fn channel_whitelist(&self) -> Vec<(ChannelId, PortId)>;
 
+	/// Add a channel to the channel whitelist, if it isn't whitelisted already
+	fn add_channel_to_whitelist(&mut self, channel: (ChannelId, PortId));
+
+	/// Return the filter for token transfers sent from this chain that must not be relayed
+	fn packet_filter(&self) -> &PacketFilter;
+
+	/// Return the packet messages relayed for the given channel of this chain
+	fn relay_mode(&self, channel_id: ChannelId, port_id: &PortId) -> RelayMode;
+
+	/// Whether proofs queried from this chain are checked locally before they are submitted
+	fn verify_proofs(&self) -> bool;
+
 	/// Query all channels for a connection
 	async fn query_connection_channels(
 		&self,
@@ -282,6 +395,9 @@ pub trait IbcProvider {
 	/// Should return the list of ibc denoms available to this account to spend.
 	async fn query_ibc_balance(&self) -> Result<Vec<PrefixedCoin>, Self::Error>;
 
+	/// Ibc denom of the native token of the chain, which pays for the relayer's transactions.
+	fn native_denom(&self) -> String;
+
 	/// Return the chain connection prefix
 	fn connection_prefix(&self) -> CommitmentPrefix;
 
@@ -297,6 +413,10 @@ pub trait IbcProvider {
 	/// Should return timestamp in nanoseconds of chain at a given block height
 	async fn query_timestamp_at(&self, block_number: u64) -> Result<u64, Self::Error>;
 
+	/// Should return timestamps in nanoseconds of chain at the given block heights, in the same
+	/// order, fetching them in a single batched request rather than one request per height.
+	async fn query_timestamps_at(&self, block_numbers: Vec<u64>) -> Result<Vec<u64>, Self::Error>;
+
 	/// Should return a list of all clients on the chain
 	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error>;
 
@@ -352,11 +472,28 @@ pub trait TestProvider: Chain + Clone + 'static {
 	fn set_channel_whitelist(&mut self, channel_whitelist: Vec<(ChannelId, PortId)>);
 }
 
+/// Gas used and fee paid by a submitted transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransactionCost {
+	/// Gas, or weight, used by the transaction
+	pub gas_used: u64,
+	/// Fee paid for the transaction, tip included, in the smallest unit of the fee token
+	pub fee: u128,
+}
+
 /// Provides an interface for managing key management for signing.
 pub trait KeyProvider {
 	/// Should return the relayer's account id on the host chain as a string in the expected format
 	/// Could be a hexadecimal, bech32 or ss58 string, any format the chain supports
 	fn account_id(&self) -> Signer;
+
+	/// Returns the signer transactions are signed with, `None` if the relayer can't sign on the
+	/// host chain.
+	fn signer(&self) -> Option<Arc<dyn signer::Signer>>;
+
+	/// Returns true if the relayer has no key on the host chain. Messages for a watch-only chain
+	/// are logged instead of submitted.
+	fn is_watch_only(&self) -> bool;
 }
 
 /// Provides an interface for managing IBC misbehaviour.
@@ -381,23 +518,41 @@ pub trait Chain: IbcProvider + MisbehaviourHandler + KeyProvider + Send + Sync {
 	fn block_max_weight(&self) -> u64;
 
 	/// Should return an estimate of the weight of a batch of messages.
-	async fn estimate_weight(&self, msg: Vec<Any>) -> Result<u64, Self::Error>;
+	async fn estimate_weight(&self, msg: &[Any]) -> Result<u64, Self::Error>;
 
 	/// Return a stream that yields when new [`IbcEvents`] are ready to be queried.
 	async fn finality_notifications(
 		&self,
 	) -> Pin<Box<dyn Stream<Item = Self::FinalityEvent> + Send + Sync>>;
 
-	/// This should be used to submit new messages [`Vec<Any>`] from a counterparty chain to this
+	/// This should be used to submit new messages [`Any`] from a counterparty chain to this
 	/// chain.
 	/// Should return the transaction id
-	async fn submit(&self, messages: Vec<Any>) -> Result<Self::TransactionId, Self::Error>;
+	async fn submit(&self, messages: &[Any]) -> Result<Self::TransactionId, Self::Error>;
+
+	/// Should return the gas used and fee paid by a transaction submitted with [`Chain::submit`].
+	fn transaction_cost(&self, tx_id: &Self::TransactionId) -> TransactionCost;
 
 	/// Returns an [`AnyClientMessage`] for an [`UpdateClient`] event
 	async fn query_client_message(
 		&self,
 		update: UpdateClient,
 	) -> Result<AnyClientMessage, Self::Error>;
+
+	/// Should return the upgrade plan currently scheduled on this chain, if any.
+	/// Packet relay from this chain is paused once the halt height has been reached, until the
+	/// counterparty client has been upgraded.
+	async fn detect_scheduled_upgrade(&self) -> Option<UpgradePlan>;
+
+	/// Should return the interval after which the counterparty client of this chain is updated
+	/// even without new events, so monitoring can see that the relayer is serving it. `None`
+	/// disables the heartbeat.
+	fn heartbeat_interval(&self) -> Option<Duration>;
+
+	/// Should return the number of blocks of this chain between full scans of its channels for
+	/// packets that are ready to be delivered or timed out. Events are still relayed as soon as
+	/// they are finalized. `None` scans on every finality notification.
+	fn full_scan_interval(&self) -> Option<u64>;
 }
 
 /// Returns undelivered packet sequences that have been sent out from
@@ -572,6 +727,62 @@ pub async fn find_suitable_proof_height_for_client(
 	None
 }
 
+/// Returns [`Error::ClientFrozen`] if the light client for `source` on `sink` is frozen, or
+/// [`Error::ClientExpired`] if its trusting period has elapsed since its latest consensus state.
+/// Messages verified by such a client are rejected, so they should not be submitted. Inactive
+/// clients and clients close to expiry are reported to `alerts`.
+pub async fn check_client_status(
+	source: &impl Chain,
+	sink: &impl Chain,
+	alerts: &Alerts,
+) -> Result<(), anyhow::Error> {
+	let client_id = source.client_id();
+	let (sink_height, sink_timestamp) = sink.latest_height_and_timestamp().await?;
+	let client_state = sink
+		.query_client_state(sink_height, client_id.clone())
+		.await?
+		.client_state
+		.ok_or_else(|| Error::Custom(format!("Client state for {client_id} not found")))?;
+	let client_state = AnyClientState::try_from(client_state)
+		.map_err(|_| Error::Custom(format!("Failed to decode client state for {client_id}")))?;
+	let alert_key = format!("client/{}/{client_id}", sink.name());
+	if let Some(height) = client_state.frozen_height() {
+		let err = Error::ClientFrozen { client_id, chain: sink.name().to_string(), height };
+		alerts.alert(&alert_key, Severity::Critical, err.to_string()).await;
+		Err(err)?
+	}
+	let consensus_state = sink
+		.query_client_consensus(sink_height, client_id.clone(), client_state.latest_height())
+		.await?
+		.consensus_state
+		.ok_or_else(|| Error::Custom(format!("Consensus state for {client_id} not found")))?;
+	let consensus_state = AnyConsensusState::try_from(consensus_state)
+		.map_err(|_| Error::Custom(format!("Failed to decode consensus state for {client_id}")))?;
+
+	let elapsed = sink_timestamp.duration_since(&consensus_state.timestamp()).unwrap_or_default();
+	if client_state.expired(elapsed) {
+		let err = Error::ClientExpired { client_id, chain: sink.name().to_string(), elapsed };
+		alerts.alert(&alert_key, Severity::Critical, err.to_string()).await;
+		Err(err)?
+	}
+	// the client is near expiry once two thirds of its trusting period have elapsed
+	if client_state.expired(elapsed * 3 / 2) {
+		alerts
+			.alert(
+				&format!("{alert_key}/near-expiry"),
+				Severity::Warning,
+				format!(
+					"Client {client_id} on {} is close to expiry, {elapsed:?} elapsed since its latest consensus state",
+					sink.name()
+				),
+			)
+			.await;
+	} else {
+		alerts.resolve(&format!("{alert_key}/near-expiry"));
+	}
+	Ok(())
+}
+
 pub async fn query_maximum_height_for_timeout_proofs(
 	source: &impl Chain,
 	sink: &impl Chain,
@@ -579,6 +790,10 @@ pub async fn query_maximum_height_for_timeout_proofs(
 	let mut min_timeout_height = None;
 	let (source_height, ..) = source.latest_height_and_timestamp().await.ok()?;
 	let (sink_height, ..) = sink.latest_height_and_timestamp().await.ok()?;
+	// packets sent in the same block see the same client state, so it is only queried once per
+	// height, along with the sink timestamp at the client's latest height
+	let mut client_timestamps = BTreeMap::<u64, u64>::new();
+	let revision_number = source_height.revision_number;
 	for (channel, port_id) in source.channel_whitelist() {
 		let undelivered_sequences = query_undelivered_sequences(
 			source_height,
@@ -592,19 +807,28 @@ pub async fn query_maximum_height_for_timeout_proofs(
 		.ok()?;
 		let send_packets =
 			source.query_send_packets(channel, port_id, undelivered_sequences).await.ok()?;
-		for send_packet in send_packets {
+		// latest heights of the client at the heights packets were sent at, whose timestamps on
+		// the sink are then fetched at once
+		let mut client_heights = BTreeMap::<u64, u64>::new();
+		for height in send_packets.iter().map(|packet| packet.height) {
+			if client_timestamps.contains_key(&height) || client_heights.contains_key(&height) {
+				continue
+			}
 			let sink_client_state = source
-				.query_client_state(
-					Height::new(source_height.revision_number, send_packet.height),
-					sink.client_id(),
-				)
+				.query_client_state(Height::new(revision_number, height), sink.client_id())
 				.await
 				.ok()?;
 			let sink_client_state =
 				AnyClientState::try_from(sink_client_state.client_state?).ok()?;
-			let height = sink_client_state.latest_height();
-			let timestamp_at_creation =
-				sink.query_timestamp_at(height.revision_height).await.ok()?;
+			client_heights.insert(height, sink_client_state.latest_height().revision_height);
+		}
+		let timestamps = sink
+			.query_timestamps_at(client_heights.values().copied().collect())
+			.await
+			.ok()?;
+		client_timestamps.extend(client_heights.into_keys().zip(timestamps));
+		for send_packet in send_packets {
+			let timestamp_at_creation = *client_timestamps.get(&send_packet.height)?;
 			let period = send_packet.timeout_timestamp.saturating_sub(timestamp_at_creation);
 			if period == 0 {
 				min_timeout_height =
@@ -15,6 +15,9 @@
 use ibc::core::ics02_client::context::ClientTypes;
 use pallet_ibc::light_clients::{AnyClient, AnyClientMessage, AnyClientState, AnyConsensusState};
 
+#[cfg(feature = "testing")]
+pub mod chain;
+
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct LocalClientTypes;
 
@@ -0,0 +1,942 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A deterministic in-memory [`Chain`] for testing the relayer without live nodes.
+//!
+//! The chain keeps only its latest ibc state, every query ignores the requested height and all
+//! proofs are fake. Messages submitted to the chain are recorded but not executed, tests drive
+//! the state through the `set_*` and packet methods and then call [`MockChain::produce_block`].
+
+use crate::{
+	error::Error, packet_data::PacketFilter, Chain, IbcProvider, KeyProvider, MisbehaviourHandler,
+	RelayMode, TransactionCost, UpdateType, UpgradePlan,
+};
+use futures::Stream;
+use ibc::{
+	applications::transfer::PrefixedCoin,
+	core::{
+		ics02_client::{client_state::ClientType, events::UpdateClient},
+		ics03_connection::connection::{ConnectionEnd, IdentifiedConnectionEnd},
+		ics04_channel::{
+			channel::{ChannelEnd, IdentifiedChannelEnd},
+			events::{ReceivePacket, SendPacket, WriteAcknowledgement},
+			packet::Packet,
+		},
+		ics23_commitment::commitment::CommitmentPrefix,
+		ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
+	},
+	events::IbcEvent,
+	signer::Signer,
+	timestamp::Timestamp,
+	Height,
+};
+use ibc_proto::{
+	google::protobuf::Any,
+	ibc::core::{
+		channel::v1::{
+			QueryChannelResponse, QueryChannelsResponse, QueryNextSequenceReceiveResponse,
+			QueryPacketAcknowledgementResponse, QueryPacketCommitmentResponse,
+			QueryPacketReceiptResponse,
+		},
+		client::v1::{QueryClientStateResponse, QueryConsensusStateResponse},
+		connection::v1::{IdentifiedConnection, QueryConnectionResponse},
+	},
+};
+use ibc_rpc::PacketInfo;
+use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState, AnyConsensusState};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	pin::Pin,
+	str::FromStr,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+use tokio::sync::broadcast;
+
+/// Type url of the placeholder client update returned by [`MockChain::query_latest_ibc_events`].
+pub const MOCK_UPDATE_TYPE_URL: &str = "/hyperspace.mock.UpdateClient";
+
+/// Proof returned for every query, it is never verified.
+const MOCK_PROOF: &[u8] = b"mock-proof";
+
+/// Timestamp of the first block, in nanoseconds.
+const GENESIS_TIMESTAMP: u64 = 1_600_000_000_000_000_000;
+
+type PacketKey = (PortId, ChannelId, u64);
+
+#[derive(Default)]
+struct MockState {
+	height: u64,
+	timestamp: u64,
+	client_states: BTreeMap<ClientId, AnyClientState>,
+	consensus_states: BTreeMap<(ClientId, Height), AnyConsensusState>,
+	client_updates: BTreeMap<(ClientId, Height), (Height, Timestamp)>,
+	connections: BTreeMap<ConnectionId, ConnectionEnd>,
+	channels: BTreeMap<(PortId, ChannelId), ChannelEnd>,
+	commitments: BTreeSet<PacketKey>,
+	acknowledgements: BTreeMap<PacketKey, Vec<u8>>,
+	receipts: BTreeSet<PacketKey>,
+	next_sequence_recv: BTreeMap<(PortId, ChannelId), u64>,
+	send_packets: BTreeMap<PacketKey, PacketInfo>,
+	recv_packets: BTreeMap<PacketKey, PacketInfo>,
+	block_timestamps: BTreeMap<u64, u64>,
+	pending_events: Vec<IbcEvent>,
+	unreported_events: Vec<IbcEvent>,
+	submitted: Vec<Vec<Any>>,
+	balance: Vec<PrefixedCoin>,
+	storage: BTreeMap<Vec<u8>, Vec<u8>>,
+	upgrade_plan: Option<UpgradePlan>,
+	upgraded_states: Option<(AnyClientState, AnyConsensusState)>,
+	initial_client_state: Option<(AnyClientState, AnyConsensusState)>,
+	/// Subscribed on creation, so the first finality stream also sees the blocks produced before
+	/// it was requested
+	finality_receiver: Option<broadcast::Receiver<Option<u64>>>,
+}
+
+/// An in-memory chain, clones share the same state.
+#[derive(Clone)]
+pub struct MockChain {
+	name: String,
+	client_id: ClientId,
+	connection_id: ConnectionId,
+	client_type: ClientType,
+	block_time: Duration,
+	channel_whitelist: Vec<(ChannelId, PortId)>,
+	packet_filter: PacketFilter,
+	relay_modes: BTreeMap<(ChannelId, PortId), RelayMode>,
+	full_scan_interval: Option<u64>,
+	heartbeat_interval: Option<Duration>,
+	watch_only: bool,
+	state: Arc<Mutex<MockState>>,
+	finality: broadcast::Sender<Option<u64>>,
+	events: broadcast::Sender<IbcEvent>,
+}
+
+impl MockChain {
+	/// Creates a chain at height 1. `client_id` is the id of this chain's client on the
+	/// counterparty and `client_type` the type of that client.
+	pub fn new(
+		name: &str,
+		client_id: ClientId,
+		connection_id: ConnectionId,
+		client_type: ClientType,
+	) -> Self {
+		let (finality, finality_receiver) = broadcast::channel(64);
+		let (events, _) = broadcast::channel(1024);
+		let state = MockState {
+			height: 1,
+			timestamp: GENESIS_TIMESTAMP,
+			block_timestamps: BTreeMap::from([(1, GENESIS_TIMESTAMP)]),
+			finality_receiver: Some(finality_receiver),
+			..Default::default()
+		};
+		Self {
+			name: name.to_string(),
+			client_id,
+			connection_id,
+			client_type,
+			block_time: Duration::from_secs(6),
+			channel_whitelist: vec![],
+			packet_filter: Default::default(),
+			relay_modes: Default::default(),
+			full_scan_interval: None,
+			heartbeat_interval: None,
+			watch_only: false,
+			state: Arc::new(Mutex::new(state)),
+			finality,
+			events,
+		}
+	}
+
+	/// Sets the channels the relayer should process for this chain.
+	pub fn set_channel_whitelist(&mut self, channel_whitelist: Vec<(ChannelId, PortId)>) {
+		self.channel_whitelist = channel_whitelist;
+	}
+
+	/// Sets the token transfers sent from this chain that the relayer must not deliver.
+	pub fn set_packet_filter(&mut self, packet_filter: PacketFilter) {
+		self.packet_filter = packet_filter;
+	}
+
+	/// Sets the packet messages relayed for a channel of this chain.
+	pub fn set_relay_mode(
+		&mut self,
+		channel_id: ChannelId,
+		port_id: PortId,
+		relay_mode: RelayMode,
+	) {
+		self.relay_modes.insert((channel_id, port_id), relay_mode);
+	}
+
+	/// Sets the number of blocks between full scans of this chain's channels.
+	pub fn set_full_scan_interval(&mut self, full_scan_interval: Option<u64>) {
+		self.full_scan_interval = full_scan_interval;
+	}
+
+	/// Sets the time without submissions after which an optional client update is sent anyway.
+	pub fn set_heartbeat_interval(&mut self, heartbeat_interval: Option<Duration>) {
+		self.heartbeat_interval = heartbeat_interval;
+	}
+
+	/// Makes the relayer watch-only on this chain, it has no key to sign with.
+	pub fn set_watch_only(&mut self, watch_only: bool) {
+		self.watch_only = watch_only;
+	}
+
+	/// Latest height of the chain.
+	pub fn height(&self) -> Height {
+		Height::new(0, self.state().height)
+	}
+
+	/// Latest timestamp of the chain.
+	pub fn timestamp(&self) -> Timestamp {
+		Timestamp::from_nanoseconds(self.state().timestamp).expect("Timestamp is not zero")
+	}
+
+	/// Stores the client state of a counterparty client.
+	pub fn set_client_state(&self, client_id: ClientId, client_state: AnyClientState) {
+		self.state().client_states.insert(client_id, client_state);
+	}
+
+	/// Stores a consensus state for a counterparty client, recording the current height and
+	/// timestamp as the time of the client update.
+	pub fn set_consensus_state(
+		&self,
+		client_id: ClientId,
+		height: Height,
+		consensus_state: AnyConsensusState,
+	) {
+		let update = (self.height(), self.timestamp());
+		let mut state = self.state();
+		state.consensus_states.insert((client_id.clone(), height), consensus_state);
+		state.client_updates.insert((client_id, height), update);
+	}
+
+	/// Removes the update time and height recorded for a consensus state, as a host pruning its
+	/// client update metadata would.
+	pub fn prune_client_update(&self, client_id: ClientId, height: Height) {
+		self.state().client_updates.remove(&(client_id, height));
+	}
+
+	/// Sets the client and consensus states of new clients of this chain.
+	pub fn set_initial_client_state(
+		&self,
+		client_state: AnyClientState,
+		consensus_state: AnyConsensusState,
+	) {
+		self.state().initial_client_state = Some((client_state, consensus_state));
+	}
+
+	/// Schedules an upgrade of this chain.
+	pub fn set_upgrade_plan(&self, upgrade_plan: Option<UpgradePlan>) {
+		self.state().upgrade_plan = upgrade_plan;
+	}
+
+	/// Sets the client and consensus states this chain committed for upgrading its counterparty
+	/// clients.
+	pub fn set_upgraded_states(
+		&self,
+		client_state: AnyClientState,
+		consensus_state: AnyConsensusState,
+	) {
+		self.state().upgraded_states = Some((client_state, consensus_state));
+	}
+
+	/// Stores a connection end.
+	pub fn set_connection(&self, connection_id: ConnectionId, connection_end: ConnectionEnd) {
+		self.state().connections.insert(connection_id, connection_end);
+	}
+
+	/// Stores a channel end.
+	pub fn set_channel(&self, port_id: PortId, channel_id: ChannelId, channel_end: ChannelEnd) {
+		self.state().channels.insert((port_id, channel_id), channel_end);
+	}
+
+	/// Sets the balance returned by [`IbcProvider::query_ibc_balance`].
+	pub fn set_balance(&self, balance: Vec<PrefixedCoin>) {
+		self.state().balance = balance;
+	}
+
+	/// Sets the value returned by [`IbcProvider::query_key_with_proof`] for `key`.
+	pub fn set_storage(&self, key: Vec<u8>, value: Vec<u8>) {
+		self.state().storage.insert(key, value);
+	}
+
+	/// Commits an outgoing packet at the current height.
+	pub fn send_packet(&self, packet: Packet) {
+		let height = self.height();
+		let mut state = self.state();
+		let key = source_key(&packet);
+		state.commitments.insert(key.clone());
+		state
+			.send_packets
+			.insert(key, packet_info(&packet, height.revision_height, None));
+		state.pending_events.push(IbcEvent::SendPacket(SendPacket { height, packet }));
+	}
+
+	/// Receives an incoming packet at the current height and writes `ack` for it.
+	pub fn receive_packet(&self, packet: Packet, ack: Vec<u8>) {
+		let height = self.height();
+		let mut state = self.state();
+		let key = destination_key(&packet);
+		let sequence = u64::from(packet.sequence);
+		let next = state.next_sequence_recv.entry((key.0.clone(), key.1)).or_insert(1);
+		*next = (*next).max(sequence + 1);
+		state.receipts.insert(key.clone());
+		state.acknowledgements.insert(key.clone(), ack.clone());
+		state
+			.recv_packets
+			.insert(key, packet_info(&packet, height.revision_height, Some(ack.clone())));
+		state
+			.pending_events
+			.push(IbcEvent::ReceivePacket(ReceivePacket { height, packet: packet.clone() }));
+		state.pending_events.push(IbcEvent::WriteAcknowledgement(WriteAcknowledgement {
+			height,
+			packet,
+			ack,
+		}));
+	}
+
+	/// Deletes the commitment of an outgoing packet, as done when it is acknowledged or times out.
+	pub fn clear_packet_commitment(&self, packet: &Packet) {
+		self.state().commitments.remove(&source_key(packet));
+	}
+
+	/// Finalizes a new block containing the events queued since the last block.
+	pub fn produce_block(&self) {
+		let height = {
+			let mut state = self.state();
+			state.height += 1;
+			state.timestamp += self.block_time.as_nanos() as u64;
+			let (height, timestamp) = (state.height, state.timestamp);
+			state.block_timestamps.insert(height, timestamp);
+			let events = std::mem::take(&mut state.pending_events);
+			for event in &events {
+				let _ = self.events.send(event.clone());
+			}
+			state.unreported_events.extend(events);
+			height
+		};
+		let _ = self.finality.send(Some(height));
+	}
+
+	/// Ends the finality notification streams of this chain after the blocks produced so far,
+	/// which stops a relayer following it once it has processed them.
+	pub fn end_finality(&self) {
+		let _ = self.finality.send(None);
+	}
+
+	/// Message batches submitted to this chain, in order.
+	pub fn submitted_messages(&self) -> Vec<Vec<Any>> {
+		self.state().submitted.clone()
+	}
+
+	fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+		self.state.lock().expect("Mock chain state lock is poisoned")
+	}
+
+	fn proof_height(&self) -> Option<ibc_proto::ibc::core::client::v1::Height> {
+		Some(self.height().into())
+	}
+}
+
+fn source_key(packet: &Packet) -> PacketKey {
+	(packet.source_port.clone(), packet.source_channel, u64::from(packet.sequence))
+}
+
+fn destination_key(packet: &Packet) -> PacketKey {
+	(packet.destination_port.clone(), packet.destination_channel, u64::from(packet.sequence))
+}
+
+fn packet_info(packet: &Packet, height: u64, ack: Option<Vec<u8>>) -> PacketInfo {
+	PacketInfo {
+		height,
+		sequence: packet.sequence.into(),
+		source_port: packet.source_port.to_string(),
+		source_channel: packet.source_channel.to_string(),
+		destination_port: packet.destination_port.to_string(),
+		destination_channel: packet.destination_channel.to_string(),
+		channel_order: String::new(),
+		data: packet.data.clone(),
+		timeout_height: packet.timeout_height.into(),
+		timeout_timestamp: packet.timeout_timestamp.nanoseconds(),
+		ack,
+	}
+}
+
+fn not_found(item: String) -> Error {
+	Error::Custom(format!("{item} not found on mock chain"))
+}
+
+#[async_trait::async_trait]
+impl IbcProvider for MockChain {
+	type FinalityEvent = u64;
+	type TransactionId = usize;
+	type Error = Error;
+
+	async fn query_latest_ibc_events<T>(
+		&mut self,
+		finality_event: Self::FinalityEvent,
+		_counterparty: &T,
+	) -> Result<(Vec<Any>, Vec<IbcEvent>, UpdateType), anyhow::Error>
+	where
+		T: Chain,
+	{
+		let events = std::mem::take(&mut self.state().unreported_events);
+		let update = Any {
+			type_url: MOCK_UPDATE_TYPE_URL.to_string(),
+			value: finality_event.to_be_bytes().to_vec(),
+		};
+		Ok((vec![update], events, UpdateType::Optional))
+	}
+
+	async fn ibc_events(&self) -> Pin<Box<dyn Stream<Item = IbcEvent> + Send + 'static>> {
+		let receiver = self.events.subscribe();
+		let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+			loop {
+				match receiver.recv().await {
+					Ok(event) => return Some((event, receiver)),
+					Err(broadcast::error::RecvError::Lagged(_)) => continue,
+					Err(broadcast::error::RecvError::Closed) => return None,
+				}
+			}
+		});
+		Box::pin(stream)
+	}
+
+	async fn query_client_consensus(
+		&self,
+		_at: Height,
+		client_id: ClientId,
+		consensus_height: Height,
+	) -> Result<QueryConsensusStateResponse, Self::Error> {
+		let consensus_state = self
+			.state()
+			.consensus_states
+			.get(&(client_id.clone(), consensus_height))
+			.cloned()
+			.ok_or_else(|| not_found(format!("Consensus state {client_id}/{consensus_height}")))?;
+		Ok(QueryConsensusStateResponse {
+			consensus_state: Some(consensus_state.into()),
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn query_client_state(
+		&self,
+		_at: Height,
+		client_id: ClientId,
+	) -> Result<QueryClientStateResponse, Self::Error> {
+		let client_state = self
+			.state()
+			.client_states
+			.get(&client_id)
+			.cloned()
+			.ok_or_else(|| not_found(format!("Client state {client_id}")))?;
+		Ok(QueryClientStateResponse {
+			client_state: Some(client_state.into()),
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn query_upgraded_client_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryClientStateResponse, Self::Error> {
+		let (client_state, _) = self
+			.state()
+			.upgraded_states
+			.clone()
+			.ok_or_else(|| not_found(format!("Upgraded client state at {upgrade_height}")))?;
+		Ok(QueryClientStateResponse {
+			client_state: Some(client_state.into()),
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: Some(upgrade_height.into()),
+		})
+	}
+
+	async fn query_upgraded_consensus_state(
+		&self,
+		upgrade_height: Height,
+	) -> Result<QueryConsensusStateResponse, Self::Error> {
+		let (_, consensus_state) =
+			self.state().upgraded_states.clone().ok_or_else(|| {
+				not_found(format!("Upgraded consensus state at {upgrade_height}"))
+			})?;
+		Ok(QueryConsensusStateResponse {
+			consensus_state: Some(consensus_state.into()),
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: Some(upgrade_height.into()),
+		})
+	}
+
+	async fn query_connection_end(
+		&self,
+		_at: Height,
+		connection_id: ConnectionId,
+	) -> Result<QueryConnectionResponse, Self::Error> {
+		let connection = self.state().connections.get(&connection_id).cloned();
+		Ok(QueryConnectionResponse {
+			connection: connection.map(Into::into),
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn query_channel_end(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<QueryChannelResponse, Self::Error> {
+		let channel = self.state().channels.get(&(port_id, channel_id)).cloned();
+		Ok(QueryChannelResponse {
+			channel: channel.map(Into::into),
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn query_proof(&self, _at: Height, _keys: Vec<Vec<u8>>) -> Result<Vec<u8>, Self::Error> {
+		Ok(MOCK_PROOF.to_vec())
+	}
+
+	async fn query_key_with_proof(
+		&self,
+		_at: Height,
+		key: Vec<u8>,
+	) -> Result<(Option<Vec<u8>>, Vec<u8>), Self::Error> {
+		Ok((self.state().storage.get(&key).cloned(), MOCK_PROOF.to_vec()))
+	}
+
+	async fn query_packet_commitment(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		seq: u64,
+	) -> Result<QueryPacketCommitmentResponse, Self::Error> {
+		let exists = self.state().commitments.contains(&(port_id.clone(), *channel_id, seq));
+		Ok(QueryPacketCommitmentResponse {
+			commitment: if exists { seq.to_be_bytes().to_vec() } else { vec![] },
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn query_packet_acknowledgement(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		seq: u64,
+	) -> Result<QueryPacketAcknowledgementResponse, Self::Error> {
+		let acknowledgement = self
+			.state()
+			.acknowledgements
+			.get(&(port_id.clone(), *channel_id, seq))
+			.cloned()
+			.unwrap_or_default();
+		Ok(QueryPacketAcknowledgementResponse {
+			acknowledgement,
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn query_next_sequence_recv(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<QueryNextSequenceReceiveResponse, Self::Error> {
+		let next_sequence_receive = self
+			.state()
+			.next_sequence_recv
+			.get(&(port_id.clone(), *channel_id))
+			.copied()
+			.unwrap_or(1);
+		Ok(QueryNextSequenceReceiveResponse {
+			next_sequence_receive,
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn query_packet_receipt(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		seq: u64,
+	) -> Result<QueryPacketReceiptResponse, Self::Error> {
+		let received = self.state().receipts.contains(&(port_id.clone(), *channel_id, seq));
+		Ok(QueryPacketReceiptResponse {
+			received,
+			proof: MOCK_PROOF.to_vec(),
+			proof_height: self.proof_height(),
+		})
+	}
+
+	async fn latest_height_and_timestamp(&self) -> Result<(Height, Timestamp), Self::Error> {
+		Ok((self.height(), self.timestamp()))
+	}
+
+	async fn query_packet_commitments(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Vec<u64>, Self::Error> {
+		Ok(self
+			.state()
+			.commitments
+			.iter()
+			.filter(|(port, channel, _)| *port == port_id && *channel == channel_id)
+			.map(|(.., seq)| *seq)
+			.collect())
+	}
+
+	async fn query_packet_acknowledgements(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Vec<u64>, Self::Error> {
+		Ok(self
+			.state()
+			.acknowledgements
+			.keys()
+			.filter(|(port, channel, _)| *port == port_id && *channel == channel_id)
+			.map(|(.., seq)| *seq)
+			.collect())
+	}
+
+	async fn query_unreceived_packets(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<u64>, Self::Error> {
+		let state = self.state();
+		Ok(seqs
+			.into_iter()
+			.filter(|seq| !state.receipts.contains(&(port_id.clone(), channel_id, *seq)))
+			.collect())
+	}
+
+	async fn query_unreceived_acknowledgements(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<u64>, Self::Error> {
+		// an ack is unreceived as long as the packet commitment hasn't been deleted
+		let state = self.state();
+		Ok(seqs
+			.into_iter()
+			.filter(|seq| state.commitments.contains(&(port_id.clone(), channel_id, *seq)))
+			.collect())
+	}
+
+	fn channel_whitelist(&self) -> Vec<(ChannelId, PortId)> {
+		self.channel_whitelist.clone()
+	}
+
+	fn add_channel_to_whitelist(&mut self, channel: (ChannelId, PortId)) {
+		if !self.channel_whitelist.contains(&channel) {
+			self.channel_whitelist.push(channel)
+		}
+	}
+
+	fn packet_filter(&self) -> &PacketFilter {
+		&self.packet_filter
+	}
+
+	fn relay_mode(&self, channel_id: ChannelId, port_id: &PortId) -> RelayMode {
+		self.relay_modes
+			.get(&(channel_id, port_id.clone()))
+			.copied()
+			.unwrap_or_default()
+	}
+
+	fn verify_proofs(&self) -> bool {
+		// mock proofs are not trie proofs
+		false
+	}
+
+	async fn query_connection_channels(
+		&self,
+		_at: Height,
+		connection_id: &ConnectionId,
+	) -> Result<QueryChannelsResponse, Self::Error> {
+		let channels = self
+			.state()
+			.channels
+			.iter()
+			.filter(|(_, end)| end.connection_hops.first() == Some(connection_id))
+			.map(|((port_id, channel_id), end)| {
+				IdentifiedChannelEnd::new(port_id.clone(), *channel_id, end.clone()).into()
+			})
+			.collect();
+		Ok(QueryChannelsResponse { channels, pagination: None, height: self.proof_height() })
+	}
+
+	async fn query_send_packets(
+		&self,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<PacketInfo>, Self::Error> {
+		let state = self.state();
+		Ok(seqs
+			.into_iter()
+			.filter_map(|seq| state.send_packets.get(&(port_id.clone(), channel_id, seq)).cloned())
+			.collect())
+	}
+
+	async fn query_recv_packets(
+		&self,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<PacketInfo>, Self::Error> {
+		let state = self.state();
+		Ok(seqs
+			.into_iter()
+			.filter_map(|seq| state.recv_packets.get(&(port_id.clone(), channel_id, seq)).cloned())
+			.collect())
+	}
+
+	fn expected_block_time(&self) -> Duration {
+		self.block_time
+	}
+
+	async fn query_client_update_time_and_height(
+		&self,
+		client_id: ClientId,
+		client_height: Height,
+	) -> Result<(Height, Timestamp), Self::Error> {
+		self.state()
+			.client_updates
+			.get(&(client_id.clone(), client_height))
+			.cloned()
+			.ok_or_else(|| Error::ConsensusStatePruned {
+				client_id,
+				chain: self.name.clone(),
+				height: client_height,
+			})
+	}
+
+	async fn query_host_consensus_state_proof(
+		&self,
+		_height: Height,
+	) -> Result<Option<Vec<u8>>, Self::Error> {
+		Ok(None)
+	}
+
+	async fn query_ibc_balance(&self) -> Result<Vec<PrefixedCoin>, Self::Error> {
+		Ok(self.state().balance.clone())
+	}
+
+	fn native_denom(&self) -> String {
+		"UNIT".to_string()
+	}
+
+	fn connection_prefix(&self) -> CommitmentPrefix {
+		CommitmentPrefix::try_from(b"ibc/".to_vec()).expect("Prefix is not empty")
+	}
+
+	fn client_id(&self) -> ClientId {
+		self.client_id.clone()
+	}
+
+	fn connection_id(&self) -> ConnectionId {
+		self.connection_id.clone()
+	}
+
+	fn client_type(&self) -> ClientType {
+		self.client_type.clone()
+	}
+
+	async fn query_timestamp_at(&self, block_number: u64) -> Result<u64, Self::Error> {
+		self.state()
+			.block_timestamps
+			.get(&block_number)
+			.copied()
+			.ok_or_else(|| not_found(format!("Block {block_number}")))
+	}
+
+	async fn query_timestamps_at(&self, block_numbers: Vec<u64>) -> Result<Vec<u64>, Self::Error> {
+		let state = self.state();
+		block_numbers
+			.into_iter()
+			.map(|block_number| {
+				state
+					.block_timestamps
+					.get(&block_number)
+					.copied()
+					.ok_or_else(|| not_found(format!("Block {block_number}")))
+			})
+			.collect()
+	}
+
+	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error> {
+		Ok(self.state().client_states.keys().cloned().collect())
+	}
+
+	async fn query_channels(&self) -> Result<Vec<(ChannelId, PortId)>, Self::Error> {
+		Ok(self
+			.state()
+			.channels
+			.keys()
+			.map(|(port_id, channel_id)| (*channel_id, port_id.clone()))
+			.collect())
+	}
+
+	async fn query_connection_using_client(
+		&self,
+		_height: u32,
+		client_id: String,
+	) -> Result<Vec<IdentifiedConnection>, Self::Error> {
+		let client_id = ClientId::from_str(&client_id).map_err(|e| Error::Custom(e.to_string()))?;
+		Ok(self
+			.state()
+			.connections
+			.iter()
+			.filter(|(_, end)| *end.client_id() == client_id)
+			.map(|(id, end)| IdentifiedConnectionEnd::new(id.clone(), end.clone()).into())
+			.collect())
+	}
+
+	fn is_update_required(
+		&self,
+		_latest_height: u64,
+		_latest_client_height_on_counterparty: u64,
+	) -> bool {
+		false
+	}
+
+	async fn initialize_client_state(
+		&self,
+	) -> Result<(AnyClientState, AnyConsensusState), Self::Error> {
+		self.state()
+			.initial_client_state
+			.clone()
+			.ok_or_else(|| Error::Custom("Mock chain can't create light clients".to_string()))
+	}
+
+	async fn query_client_id_from_tx_hash(
+		&self,
+		_tx_id: Self::TransactionId,
+	) -> Result<ClientId, Self::Error> {
+		Err(Error::Custom("Mock chain doesn't execute transactions".to_string()))
+	}
+}
+
+impl KeyProvider for MockChain {
+	fn account_id(&self) -> Signer {
+		Signer::from_str(&format!("{}-relayer", self.name)).expect("Signer is not empty")
+	}
+
+	fn signer(&self) -> Option<Arc<dyn crate::signer::Signer>> {
+		None
+	}
+
+	fn is_watch_only(&self) -> bool {
+		self.watch_only
+	}
+}
+
+#[async_trait::async_trait]
+impl MisbehaviourHandler for MockChain {
+	async fn check_for_misbehaviour<C: Chain>(
+		&self,
+		_counterparty: &C,
+		_client_message: AnyClientMessage,
+	) -> Result<(), anyhow::Error> {
+		Ok(())
+	}
+}
+
+#[async_trait::async_trait]
+impl Chain for MockChain {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn block_max_weight(&self) -> u64 {
+		u64::MAX
+	}
+
+	async fn estimate_weight(&self, messages: &[Any]) -> Result<u64, Self::Error> {
+		Ok(messages.len() as u64)
+	}
+
+	async fn finality_notifications(
+		&self,
+	) -> Pin<Box<dyn Stream<Item = Self::FinalityEvent> + Send + Sync>> {
+		let receiver = self
+			.state()
+			.finality_receiver
+			.take()
+			.unwrap_or_else(|| self.finality.subscribe());
+		let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+			loop {
+				match receiver.recv().await {
+					Ok(Some(height)) => return Some((height, receiver)),
+					Ok(None) => return None,
+					Err(broadcast::error::RecvError::Lagged(_)) => continue,
+					Err(broadcast::error::RecvError::Closed) => return None,
+				}
+			}
+		});
+		Box::pin(stream)
+	}
+
+	async fn submit(&self, messages: &[Any]) -> Result<Self::TransactionId, Self::Error> {
+		let mut state = self.state();
+		state.submitted.push(messages.to_vec());
+		Ok(state.submitted.len() - 1)
+	}
+
+	fn transaction_cost(&self, tx_id: &Self::TransactionId) -> TransactionCost {
+		// every message costs one unit of gas and fee
+		let messages = self.state().submitted.get(*tx_id).map(Vec::len).unwrap_or_default();
+		TransactionCost { gas_used: messages as u64, fee: messages as u128 }
+	}
+
+	async fn query_client_message(
+		&self,
+		_update: UpdateClient,
+	) -> Result<AnyClientMessage, Self::Error> {
+		Err(Error::Custom("Mock chain doesn't store client messages".to_string()))
+	}
+
+	async fn detect_scheduled_upgrade(&self) -> Option<UpgradePlan> {
+		self.state().upgrade_plan.clone()
+	}
+
+	fn heartbeat_interval(&self) -> Option<Duration> {
+		self.heartbeat_interval
+	}
+
+	fn full_scan_interval(&self) -> Option<u64> {
+		self.full_scan_interval
+	}
+}
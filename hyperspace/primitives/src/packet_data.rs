@@ -0,0 +1,243 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoding of the packet data of known applications, for logging.
+
+use crate::{is_ica_port, is_icq_port, is_wasm_port};
+use ibc::{
+	applications::transfer::{packet::PacketData, Amount},
+	bigint::U256,
+	core::{ics04_channel::packet::Packet, ics24_host::identifier::PortId},
+};
+use ibc_proto::ibc::applications::interchain_accounts::v1::CosmosTx;
+use prost::Message;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+/// Port bound by the ICS-721 non fungible token transfer module.
+pub const NFT_TRANSFER_PORT: &str = "nft-transfer";
+
+/// Packet data of an ICS-721 non fungible token transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonFungibleTokenPacketData {
+	/// Id of the class the tokens belong to, prefixed with its trace path.
+	pub class_id: String,
+	#[serde(default)]
+	pub class_uri: String,
+	#[serde(default)]
+	pub class_data: String,
+	/// Ids of the transferred tokens.
+	pub token_ids: Vec<String>,
+	#[serde(default)]
+	pub token_uris: Vec<String>,
+	#[serde(default)]
+	pub token_data: Vec<String>,
+	pub sender: String,
+	pub receiver: String,
+	#[serde(default)]
+	pub memo: String,
+}
+
+impl fmt::Display for NonFungibleTokenPacketData {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"nft transfer of {} token(s) [{}] of class {} from {} to {}",
+			self.token_ids.len(),
+			self.token_ids.join(", "),
+			self.class_id,
+			self.sender,
+			self.receiver
+		)
+	}
+}
+
+/// Packet data of an async interchain queries request, the queries are a protobuf encoded
+/// `CosmosQuery` executed by the host.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InterchainQueryPacketData {
+	/// Base64 encoded queries
+	pub data: String,
+	#[serde(default)]
+	pub memo: String,
+}
+
+impl fmt::Display for InterchainQueryPacketData {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "interchain query request of {} base64 bytes", self.data.len())?;
+		if !self.memo.is_empty() {
+			write!(f, " ({})", self.memo)?;
+		}
+		Ok(())
+	}
+}
+
+/// Packet data of an interchain accounts transaction, as json encoded by the controller.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InterchainAccountPacketData {
+	/// Type of the packet, e.g. `TYPE_EXECUTE_TX`
+	#[serde(rename = "type")]
+	pub packet_type: String,
+	/// Base64 encoded protobuf `CosmosTx`
+	pub data: String,
+	#[serde(default)]
+	pub memo: String,
+}
+
+impl InterchainAccountPacketData {
+	/// Returns the type urls of the messages executed by the interchain account, if the
+	/// transaction is protobuf encoded.
+	pub fn message_types(&self) -> Option<Vec<String>> {
+		let bytes = base64::decode(&self.data).ok()?;
+		let tx = CosmosTx::decode(bytes.as_slice()).ok()?;
+		Some(tx.messages.into_iter().map(|msg| msg.type_url).collect())
+	}
+}
+
+impl fmt::Display for InterchainAccountPacketData {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.message_types() {
+			Some(types) => write!(
+				f,
+				"interchain account {} executing [{}]",
+				self.packet_type,
+				types.join(", ")
+			)?,
+			None => write!(
+				f,
+				"interchain account {} of {} base64 bytes",
+				self.packet_type,
+				self.data.len()
+			)?,
+		}
+		if !self.memo.is_empty() {
+			write!(f, " ({})", self.memo)?;
+		}
+		Ok(())
+	}
+}
+
+/// Returns true if `port_id` is bound by an ICS-721 module.
+pub fn is_nft_transfer_port(port_id: &PortId) -> bool {
+	port_id.as_str() == NFT_TRANSFER_PORT
+}
+
+/// Returns a human readable description of the packet data. Data of known applications is
+/// decoded, anything else is shown base64 encoded.
+pub fn describe_packet_data(packet: &Packet) -> String {
+	let described = if packet.source_port == PortId::transfer() {
+		serde_json::from_slice::<PacketData>(&packet.data).ok().map(|data| {
+			format!("transfer of {} from {} to {}", data.token, data.sender, data.receiver)
+		})
+	} else if is_nft_transfer_port(&packet.source_port) {
+		serde_json::from_slice::<NonFungibleTokenPacketData>(&packet.data)
+			.ok()
+			.map(|data| data.to_string())
+	} else if is_ica_port(&packet.source_port) {
+		serde_json::from_slice::<InterchainAccountPacketData>(&packet.data)
+			.ok()
+			.map(|data| data.to_string())
+	} else if is_icq_port(&packet.destination_port) {
+		serde_json::from_slice::<InterchainQueryPacketData>(&packet.data)
+			.ok()
+			.map(|data| data.to_string())
+	} else if is_wasm_port(&packet.source_port) || is_wasm_port(&packet.destination_port) {
+		Some(describe_contract_packet_data(&packet.data))
+	} else {
+		None
+	};
+	described.unwrap_or_else(|| format!("packet data {}", base64::encode(&packet.data)))
+}
+
+/// Contracts define their own packet data, which is JSON by convention but not necessarily, so
+/// it is shown as is when it is valid JSON and hex encoded otherwise.
+fn describe_contract_packet_data(data: &[u8]) -> String {
+	match serde_json::from_slice::<serde_json::Value>(data) {
+		Ok(value) => format!("contract packet {value}"),
+		Err(_) => format!("contract packet 0x{}", hex::encode(data)),
+	}
+}
+
+/// Fungible token transfers the relayer refuses to deliver, for operators with regulatory
+/// constraints. Packets of other applications are never filtered.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct PacketFilter {
+	/// Base denoms or full trace paths, e.g. `transfer/channel-0/uatom`, whose transfers are
+	/// denied
+	#[serde(default)]
+	pub denied_denoms: Vec<String>,
+	/// Sender or receiver addresses whose transfers are denied
+	#[serde(default)]
+	pub denied_addresses: Vec<String>,
+	/// Largest amounts of denoms a single transfer may move
+	#[serde(default)]
+	pub amount_limits: Vec<AmountLimit>,
+}
+
+/// Largest amount of a denom a single transfer may move, on one port or on all of them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AmountLimit {
+	/// Port the limit applies to, every port if unset
+	#[serde(default)]
+	pub port_id: Option<String>,
+	/// Base denom or full trace path the limit applies to
+	pub denom: String,
+	/// Transfers of more than this amount, in the smallest unit of the denom, are denied. A
+	/// decimal string, as amounts may not fit in a 64 bit integer
+	#[serde(deserialize_with = "deserialize_amount")]
+	pub max_amount: U256,
+}
+
+impl AmountLimit {
+	/// Returns true if the limit applies to transfers of `denom` sent from `port_id`.
+	fn applies_to(&self, port_id: &PortId, denom: &str, base_denom: &str) -> bool {
+		self.port_id.as_ref().map_or(true, |port| port == port_id.as_str()) &&
+			(self.denom == denom || self.denom == base_denom)
+	}
+}
+
+fn deserialize_amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+	let amount = String::deserialize(deserializer)?;
+	U256::from_dec_str(&amount)
+		.map_err(|e| serde::de::Error::custom(format!("invalid amount {amount}: {e:?}")))
+}
+
+impl PacketFilter {
+	/// Returns the reason the packet must not be relayed, if it is a denied token transfer.
+	pub fn denial_reason(&self, packet: &Packet) -> Option<String> {
+		let data = serde_json::from_slice::<PacketData>(&packet.data).ok()?;
+		let denom = data.token.denom.to_string();
+		let base_denom = data.token.denom.base_denom().to_string();
+		if self
+			.denied_denoms
+			.iter()
+			.any(|denied| *denied == denom || *denied == base_denom)
+		{
+			return Some(format!("denom {denom} is denied"))
+		}
+		for address in [data.sender.as_ref(), data.receiver.as_ref()] {
+			if self.denied_addresses.iter().any(|denied| denied == address) {
+				return Some(format!("address {address} is denied"))
+			}
+		}
+		self.amount_limits
+			.iter()
+			.filter(|limit| limit.applies_to(&packet.source_port, &denom, &base_denom))
+			.find(|limit| data.token.amount > Amount::from(limit.max_amount))
+			.map(|limit| {
+				format!("amount {} of {denom} exceeds {}", data.token.amount, limit.max_amount)
+			})
+	}
+}
@@ -0,0 +1,27 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+
+/// Signs transaction payloads with the relayer's key. The key doesn't have to be held by the
+/// relayer, implementations can forward the payload to a hardware wallet or a remote signing
+/// service.
+#[async_trait::async_trait]
+pub trait Signer: Send + Sync {
+	/// Public key of the signing key, encoded the way the host chain expects it.
+	fn public_key(&self) -> Vec<u8>;
+
+	/// Signs the payload, returning the encoded signature.
+	async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Error>;
+}
@@ -0,0 +1,45 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Overflow checked conversions between the time units used by chains and ibc timestamps, which
+//! are unix nanoseconds in a `u64` and can only represent times up to about the year 2554.
+
+use ibc::timestamp::Timestamp;
+use std::time::Duration;
+
+pub const NANOS_PER_MILLI: u64 = 1_000_000;
+pub const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Converts unix milliseconds, as stored by `pallet_timestamp`, to nanoseconds.
+pub fn millis_to_nanos(millis: u64) -> Option<u64> {
+	millis.checked_mul(NANOS_PER_MILLI)
+}
+
+/// Converts unix seconds to nanoseconds.
+pub fn secs_to_nanos(secs: u64) -> Option<u64> {
+	secs.checked_mul(NANOS_PER_SEC)
+}
+
+/// Builds a [`Timestamp`] from unix milliseconds. Returns `None` for zero, which ibc treats as an
+/// unset timestamp, and for values that overflow.
+pub fn timestamp_from_millis(millis: u64) -> Option<Timestamp> {
+	let nanos = millis_to_nanos(millis).filter(|nanos| *nanos != 0)?;
+	Timestamp::from_nanoseconds(nanos).ok()
+}
+
+/// Time elapsed between two unix timestamps in nanoseconds, zero if `later` is not after
+/// `earlier`.
+pub fn elapsed_nanos(earlier: u64, later: u64) -> Duration {
+	Duration::from_nanos(later.saturating_sub(earlier))
+}
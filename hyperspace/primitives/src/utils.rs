@@ -30,7 +30,7 @@ use ibc::{
 	protobuf::Protobuf,
 	tx_msg::Msg,
 };
-use ibc_proto::google::protobuf::Any;
+use ibc_proto::{google::protobuf::Any, ibc::core::client::v1::ClientUpdateProposal};
 use std::{future::Future, time::Duration};
 
 pub async fn timeout_future<T: Future>(future: T, secs: u64, reason: String) -> T::Output {
@@ -56,7 +56,7 @@ pub async fn create_clients(
 
 	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec() };
 
-	let tx_id = chain_a.submit(vec![msg]).await?;
+	let tx_id = chain_a.submit(&[msg]).await?;
 	let client_id_b_on_a = chain_a.query_client_id_from_tx_hash(tx_id).await?;
 
 	let msg = MsgCreateAnyClient::<LocalClientTypes> {
@@ -67,7 +67,7 @@ pub async fn create_clients(
 
 	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec() };
 
-	let tx_id = chain_b.submit(vec![msg]).await?;
+	let tx_id = chain_b.submit(&[msg]).await?;
 	let client_id_a_on_b = chain_b.query_client_id_from_tx_hash(tx_id).await?;
 
 	Ok((client_id_a_on_b, client_id_b_on_a))
@@ -90,7 +90,7 @@ pub async fn create_connection(
 
 	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec() };
 
-	chain_a.submit(vec![msg]).await?;
+	chain_a.submit(&[msg]).await?;
 
 	log::info!(target: "hyperspace", "============= Wait till both chains have completed connection handshake =============");
 
@@ -120,6 +120,40 @@ pub async fn create_connection(
 	Ok((connection_id_a, connection_id_b))
 }
 
+/// Creates a new client of `source` on `sink`, to stand in for an expired or frozen client of
+/// `source` in a [`ClientUpdateProposal`].
+pub async fn create_substitute_client(
+	source: &impl Chain,
+	sink: &impl Chain,
+) -> Result<ClientId, anyhow::Error> {
+	let (client_state, consensus_state) = source.initialize_client_state().await?;
+	let msg = MsgCreateAnyClient::<LocalClientTypes> {
+		client_state,
+		consensus_state,
+		signer: sink.account_id(),
+	};
+	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec() };
+	let tx_id = sink.submit(&[msg]).await?;
+	Ok(sink.query_client_id_from_tx_hash(tx_id).await?)
+}
+
+/// Builds the governance proposal copying the latest consensus state of `substitute_client_id`
+/// over to `subject_client_id` on `chain`.
+pub fn client_update_proposal(
+	chain: &str,
+	subject_client_id: &ClientId,
+	substitute_client_id: &ClientId,
+) -> ClientUpdateProposal {
+	ClientUpdateProposal {
+		title: format!("Recover client {subject_client_id} on {chain}"),
+		description: format!(
+			"Replaces the state of the expired or frozen client {subject_client_id} with the one of {substitute_client_id}"
+		),
+		subject_client_id: subject_client_id.to_string(),
+		substitute_client_id: substitute_client_id.to_string(),
+	}
+}
+
 /// Completes the chanel handshake process
 /// The relayer process must be running before this function is executed
 pub async fn create_channel(
@@ -142,7 +176,7 @@ pub async fn create_channel(
 
 	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec() };
 
-	chain_a.submit(vec![msg]).await?;
+	chain_a.submit(&[msg]).await?;
 
 	log::info!(target: "hyperspace", "============= Wait till both chains have completed channel handshake =============");
 
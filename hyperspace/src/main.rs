@@ -30,5 +30,9 @@ async fn main() -> Result<()> {
 		Subcommand::CreateClients(cmd) => cmd.create_clients().await,
 		Subcommand::CreateConnection(cmd) => cmd.create_connection().await,
 		Subcommand::CreateChannel(cmd) => cmd.create_channel().await,
+		Subcommand::QueryEscrow(cmd) => cmd.query_escrow().await,
+		Subcommand::Report(cmd) => cmd.report().await,
+		Subcommand::Replay(cmd) => cmd.replay().await,
+		Subcommand::RecoverClient(cmd) => cmd.recover_client().await,
 	}
 }
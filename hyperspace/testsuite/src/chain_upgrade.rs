@@ -0,0 +1,82 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{send_packet_with_connection_delay, setup_connection_and_channel, StreamExt};
+use futures::{future, Future};
+use hyperspace_primitives::{utils::timeout_future, TestProvider};
+use ibc::{core::ics24_host::identifier::PortId, events::IbcEvent};
+use std::time::Duration;
+
+/// Upgrades chain_a while the relayer is running, then checks that the client of chain_a on
+/// chain_b is updated past the upgrade and that packets are still relayed in both directions.
+/// `upgrade` should schedule the upgrade and resolve once the upgraded chain is producing blocks.
+pub async fn ibc_messaging_across_chain_upgrade<A, B, F>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	upgrade: F,
+) where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+	F: Future<Output = ()>,
+{
+	let (handle, channel_id, channel_b, _connection_id) =
+		setup_connection_and_channel(chain_a, chain_b, Duration::from_secs(0)).await;
+	handle.abort();
+	// Set channel whitelist and restart relayer loop
+	chain_a.set_channel_whitelist(vec![(channel_id, PortId::transfer())]);
+	chain_b.set_channel_whitelist(vec![(channel_b, PortId::transfer())]);
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None)
+			.await
+			.unwrap()
+	});
+
+	timeout_future(upgrade, 30 * 60, format!("{} wasn't upgraded", chain_a.name())).await;
+	let (upgrade_height, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
+	log::info!(target: "hyperspace", "{} upgraded at {upgrade_height}", chain_a.name());
+
+	let client_id = chain_a.client_id();
+	let updated = chain_b
+		.ibc_events()
+		.await
+		.skip_while(|ev| {
+			future::ready(!matches!(
+				ev,
+				IbcEvent::UpdateClient(update)
+					if *update.client_id() == client_id && update.consensus_height() >= upgrade_height
+			))
+		})
+		.take(1)
+		.collect::<Vec<_>>();
+	timeout_future(
+		updated,
+		20 * 60,
+		format!(
+			"Client of {} wasn't updated on {} after the upgrade",
+			chain_a.name(),
+			chain_b.name()
+		),
+	)
+	.await;
+
+	send_packet_with_connection_delay(chain_a, chain_b, channel_id).await;
+	handle.abort();
+	log::info!(target: "hyperspace", "🚀🚀 Packets relayed across chain upgrade");
+}
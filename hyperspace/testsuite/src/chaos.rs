@@ -0,0 +1,177 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A TCP proxy that sits between the relayer and a node's rpc endpoint and injects network faults.
+
+use std::{
+	io,
+	net::SocketAddr,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{
+		tcp::{OwnedReadHalf, OwnedWriteHalf},
+		TcpListener, TcpStream,
+	},
+	task::JoinHandle,
+	time::sleep,
+};
+
+/// Faults injected by a [`ChaosProxy`].
+#[derive(Debug, Clone, Default)]
+pub struct ChaosConfig {
+	/// Delay added before forwarding every chunk of data, in both directions.
+	pub latency: Duration,
+	/// Close connections once they have been open for this long.
+	pub max_connection_lifetime: Option<Duration>,
+	/// Refuse every nth connection, the rpc client sees these as failed requests.
+	pub reject_every: Option<usize>,
+}
+
+/// Forwards connections from a local port to an upstream rpc endpoint, applying the faults in
+/// its [`ChaosConfig`]. The proxy is shut down when dropped.
+pub struct ChaosProxy {
+	local_addr: SocketAddr,
+	handle: JoinHandle<()>,
+	stats: Arc<ChaosStats>,
+}
+
+/// Number of connections a [`ChaosProxy`] has seen, by what happened to them.
+#[derive(Debug, Default)]
+struct ChaosStats {
+	accepted: AtomicUsize,
+	refused: AtomicUsize,
+	dropped: AtomicUsize,
+}
+
+impl ChaosProxy {
+	/// Starts proxying to `upstream`, given as a `ws://host:port` url or a plain `host:port`.
+	pub async fn spawn(upstream: &str, config: ChaosConfig) -> io::Result<Self> {
+		let upstream = upstream.trim_start_matches("ws://").trim_end_matches('/').to_string();
+		let listener = TcpListener::bind("127.0.0.1:0").await?;
+		let local_addr = listener.local_addr()?;
+		let stats = Arc::new(ChaosStats::default());
+
+		let proxy_stats = stats.clone();
+		let handle = tokio::spawn(async move {
+			let stats = proxy_stats;
+			loop {
+				let (inbound, _) = match listener.accept().await {
+					Ok(conn) => conn,
+					Err(e) => {
+						log::error!(target: "hyperspace", "Chaos proxy failed to accept connection: {e}");
+						continue
+					},
+				};
+				let count = stats.accepted.load(Ordering::SeqCst) +
+					stats.refused.load(Ordering::SeqCst) +
+					1;
+				if matches!(config.reject_every, Some(n) if n != 0 && count % n == 0) {
+					log::info!(target: "hyperspace", "Chaos proxy refusing connection #{count} to {upstream}");
+					stats.refused.fetch_add(1, Ordering::SeqCst);
+					drop(inbound);
+					continue
+				}
+				stats.accepted.fetch_add(1, Ordering::SeqCst);
+				tokio::spawn(proxy_connection(
+					inbound,
+					upstream.clone(),
+					config.clone(),
+					stats.clone(),
+				));
+			}
+		});
+
+		Ok(Self { local_addr, handle, stats })
+	}
+
+	/// Number of connections forwarded upstream.
+	pub fn accepted_connections(&self) -> usize {
+		self.stats.accepted.load(Ordering::SeqCst)
+	}
+
+	/// Number of connections refused because of [`ChaosConfig::reject_every`].
+	pub fn refused_connections(&self) -> usize {
+		self.stats.refused.load(Ordering::SeqCst)
+	}
+
+	/// Number of connections closed because of [`ChaosConfig::max_connection_lifetime`].
+	pub fn dropped_connections(&self) -> usize {
+		self.stats.dropped.load(Ordering::SeqCst)
+	}
+
+	/// Websocket url that clients should connect to instead of the upstream endpoint.
+	pub fn url(&self) -> String {
+		format!("ws://{}", self.local_addr)
+	}
+}
+
+impl Drop for ChaosProxy {
+	fn drop(&mut self) {
+		self.handle.abort();
+	}
+}
+
+async fn proxy_connection(
+	inbound: TcpStream,
+	upstream: String,
+	config: ChaosConfig,
+	stats: Arc<ChaosStats>,
+) {
+	let outbound = match TcpStream::connect(&upstream).await {
+		Ok(stream) => stream,
+		Err(e) => {
+			log::error!(target: "hyperspace", "Chaos proxy failed to connect to {upstream}: {e}");
+			return
+		},
+	};
+	let (inbound_read, inbound_write) = inbound.into_split();
+	let (outbound_read, outbound_write) = outbound.into_split();
+	let lifetime = async {
+		match config.max_connection_lifetime {
+			Some(lifetime) => sleep(lifetime).await,
+			None => futures::future::pending().await,
+		}
+	};
+
+	tokio::select! {
+		_ = forward(inbound_read, outbound_write, config.latency) => {},
+		_ = forward(outbound_read, inbound_write, config.latency) => {},
+		_ = lifetime => {
+			log::info!(target: "hyperspace", "Chaos proxy dropping connection to {upstream}");
+			stats.dropped.fetch_add(1, Ordering::SeqCst);
+		},
+	}
+}
+
+async fn forward(
+	mut from: OwnedReadHalf,
+	mut to: OwnedWriteHalf,
+	latency: Duration,
+) -> io::Result<()> {
+	let mut buf = vec![0u8; 16 * 1024];
+	loop {
+		let read = from.read(&mut buf).await?;
+		if read == 0 {
+			return Ok(())
+		}
+		sleep(latency).await;
+		to.write_all(&buf[..read]).await?;
+	}
+}
@@ -0,0 +1,70 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::timeout_future;
+use futures::{future, StreamExt};
+use hyperspace_primitives::{check_client_status, error::Error, TestProvider};
+use ibc::events::IbcEvent;
+
+/// Runs the relayer without any packet traffic and asserts the light client of chain B on chain A
+/// is still refreshed, so it never gets close to expiring.
+pub async fn ibc_client_refresh_without_traffic<A, B>(chain_a: &mut A, chain_b: &mut B)
+where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	const UPDATES: usize = 2;
+	let client_id = chain_b.client_id();
+	// subscribe before starting the relayer so no updates are missed
+	let updates = chain_a
+		.ibc_events()
+		.await
+		.filter(|ev| {
+			future::ready(
+				matches!(ev, IbcEvent::UpdateClient(update) if *update.client_id() == client_id),
+			)
+		})
+		.take(UPDATES)
+		.collect::<Vec<_>>();
+
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None)
+			.await
+			.unwrap()
+	});
+
+	timeout_future(
+		updates,
+		20 * 60,
+		format!("Client of {} wasn't refreshed on {}", chain_b.name(), chain_a.name()),
+	)
+	.await;
+	handle.abort();
+
+	match check_client_status(chain_b, chain_a, &Default::default()).await {
+		Ok(()) => {},
+		Err(e) => match e.downcast_ref::<Error>() {
+			Some(Error::ClientExpired { .. }) =>
+				panic!("Client expired while being refreshed: {e}"),
+			_ => panic!("Failed to check client status: {e:?}"),
+		},
+	}
+	log::info!(target: "hyperspace", "🚀🚀 Client was refreshed without packet traffic");
+}
@@ -16,18 +16,15 @@
 
 use crate::utils::{assert_timeout_packet, parse_amount};
 use futures::{future, StreamExt};
-use hyperspace_core::send_packet_relay::set_relay_status;
-use hyperspace_primitives::{
-	utils::{create_channel, create_connection, timeout_future},
-	TestProvider,
+use hyperspace_core::{
+	bootstrap::{reconcile_channels, BootstrapConfig, ChannelConfig},
+	send_packet_relay::set_relay_status,
 };
+use hyperspace_primitives::{utils::timeout_future, TestProvider};
 use ibc::{
 	applications::transfer::{msgs::transfer::MsgTransfer, Amount, PrefixedCoin, VERSION},
 	core::{
-		ics04_channel::{
-			channel::{ChannelEnd, Order, State},
-			msgs::chan_close_init::MsgChannelCloseInit,
-		},
+		ics04_channel::msgs::chan_close_init::MsgChannelCloseInit,
 		ics24_host::identifier::{ChannelId, ConnectionId, PortId},
 	},
 	events::IbcEvent,
@@ -39,8 +36,12 @@ use std::{str::FromStr, time::Duration};
 use tendermint_proto::Protobuf;
 use tokio::task::JoinHandle;
 
+pub mod chain_upgrade;
+pub mod chaos;
+pub mod client_refresh;
 pub mod misbehaviour;
 pub mod ordered_channels;
+pub mod packet_storm;
 mod utils;
 
 /// This will set up a connection and ics20 channel in-between the two chains.
@@ -66,79 +67,18 @@ where
 			.await
 			.unwrap()
 	});
-	// check if an open transfer channel exists
-	let (latest_height, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
-	let connections = chain_a
-		.query_connection_using_client(
-			latest_height.revision_height as u32,
-			chain_b.client_id().to_string(),
-		)
-		.await
-		.unwrap();
-
-	for connection in connections {
-		let connection_id = ConnectionId::from_str(&connection.id).unwrap();
-		let connection_end = chain_a
-			.query_connection_end(latest_height, connection_id.clone())
-			.await
-			.unwrap()
-			.connection
-			.unwrap();
-
-		let delay_period = Duration::from_nanos(connection_end.delay_period);
-
-		dbg!(&connection_delay);
-		dbg!(&delay_period);
-
-		if delay_period != connection_delay {
-			continue
-		}
-
-		let channels = chain_a
-			.query_connection_channels(latest_height, &connection_id)
-			.await
-			.unwrap()
-			.channels;
-
-		for channel in channels {
-			let channel_id = ChannelId::from_str(&channel.channel_id).unwrap();
-			let channel_end = chain_a
-				.query_channel_end(latest_height, channel_id, PortId::transfer())
-				.await
-				.unwrap()
-				.channel
-				.unwrap();
-			let channel_end = ChannelEnd::try_from(channel_end).unwrap();
-
-			if channel_end.state == State::Open && channel.port_id == PortId::transfer().to_string()
-			{
-				return (
-					handle,
-					channel_id,
-					channel_end.counterparty().channel_id.unwrap().clone(),
-					channel_end.connection_hops[0].clone(),
-				)
-			}
-		}
-	}
-
-	let (connection_id, ..) = create_connection(chain_a, chain_b, connection_delay).await.unwrap();
-
-	log::info!(target: "hyperspace", "============ Connection handshake completed: ConnectionId({connection_id}) ============");
-	log::info!(target: "hyperspace", "=========================== Starting channel handshake ===========================");
-
-	let (channel_id_a, channel_id_b) = create_channel(
-		chain_a,
-		chain_b,
-		connection_id.clone(),
-		PortId::transfer(),
-		VERSION.to_string(),
-		Order::Unordered,
-	)
-	.await
-	.unwrap();
-	// channel handshake completed
-	log::info!(target: "hyperspace", "============ Channel handshake completed: ChannelId({channel_id_a}) ============");
+	let bootstrap = BootstrapConfig {
+		connection_delay_secs: connection_delay.as_secs(),
+		channels: vec![ChannelConfig {
+			port_id: PortId::transfer(),
+			version: VERSION.to_string(),
+			order: "unordered".to_string(),
+		}],
+	};
+	let ((connection_id, _), channels) =
+		reconcile_channels(chain_a, chain_b, None, &bootstrap).await.unwrap();
+	let (channel_id_a, channel_id_b) = channels[0];
+	log::info!(target: "hyperspace", "============ Using channel {channel_id_a} over connection {connection_id} ============");
 
 	(handle, channel_id_a, channel_id_b, connection_id)
 }
@@ -373,7 +313,7 @@ async fn send_channel_close_init_and_assert_channel_close_confirm<A, B>(
 
 	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec() };
 
-	chain_a.submit(vec![msg]).await.unwrap();
+	chain_a.submit(&[msg]).await.unwrap();
 
 	// wait channel close confirmation on chain b
 	let future = chain_b
@@ -424,7 +364,7 @@ async fn send_packet_and_assert_timeout_on_channel_close<A, B>(
 
 	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec() };
 
-	chain_a.submit(vec![msg]).await.unwrap();
+	chain_a.submit(&[msg]).await.unwrap();
 
 	// Wait timeout timestamp to elapse, then
 	let future = chain_b
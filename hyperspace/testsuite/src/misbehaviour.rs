@@ -6,10 +6,15 @@ use grandpa_client_primitives::{
 };
 use hyperspace_primitives::{mock::LocalClientTypes, TestProvider};
 use ibc::{
-	core::ics02_client::msgs::update_client::MsgUpdateAnyClient, events::IbcEvent, tx_msg::Msg,
+	core::ics02_client::{client_state::ClientState, msgs::update_client::MsgUpdateAnyClient},
+	events::IbcEvent,
+	tx_msg::Msg,
 };
 use ibc_proto::google::protobuf::Any;
-use ics10_grandpa::client_message::{ClientMessage, Header as GrandpaHeader, RelayChainHeader};
+use ics10_grandpa::{
+	client_message::{ClientMessage, Header as GrandpaHeader, RelayChainHeader},
+	client_state::ClientState as GrandpaClientState,
+};
 use log::info;
 use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState};
 use polkadot_core_primitives::Header;
@@ -66,6 +71,74 @@ where
 	};
 
 	let finality_event = chain_b.finality_notifications().await.next().await.expect("no event");
+
+	let (update_client_msgs, _, _) = chain_b
+		.query_latest_ibc_events(finality_event, chain_a)
+		.await
+		.expect("no event");
+	let update_client_msg = update_client_msgs.last().expect("no client update");
+	let mut msg =
+		MsgUpdateAnyClient::<LocalClientTypes>::decode(&mut update_client_msg.value.as_slice())
+			.unwrap();
+	let round = match &mut msg.client_message {
+		AnyClientMessage::Grandpa(ClientMessage::Header(header)) => {
+			let justification = GrandpaJustification::<RelayChainHeader>::decode(
+				&mut &*header.finality_proof.justification,
+			)
+			.unwrap();
+
+			justification.round
+		},
+		_ => panic!("unexpected client message"),
+	};
+
+	let client_message = AnyClientMessage::Grandpa(ClientMessage::Header(forge_grandpa_header(
+		&client_state,
+		round,
+		&relaychain_authorities,
+	)));
+
+	let msg =
+		MsgUpdateAnyClient::<LocalClientTypes>::new(msg.client_id, client_message, msg.signer);
+
+	let client_a_clone = chain_a.clone();
+	let misbehavour_event_handle = tokio::task::spawn(async move {
+		let mut events = client_a_clone.ibc_events().await;
+		while let Some(event) = events.next().await {
+			match event {
+				IbcEvent::ClientMisbehaviour { .. } => return,
+				_ => (),
+			}
+		}
+	});
+
+	chain_a
+		.submit(&[Any { value: msg.encode_vec(), type_url: msg.type_url() }])
+		.await
+		.expect("failed to submit message");
+
+	timeout(Duration::from_secs(5 * 60), misbehavour_event_handle)
+		.await
+		.expect("timeout")
+		.expect("failed to receive misbehaviour event");
+
+	// the misbehaviour should have frozen the client
+	let latest_height = chain_a.latest_height_and_timestamp().await.unwrap().0;
+	let response = chain_a.query_client_state(latest_height, chain_b.client_id()).await.unwrap();
+	let client_state = AnyClientState::try_from(response.client_state.unwrap()).unwrap();
+	assert!(client_state.is_frozen(), "Client wasn't frozen after misbehaviour was submitted");
+
+	handle.abort()
+}
+
+/// Forges a GRANDPA header on top of the latest relay chain block known to `client_state`, with a
+/// justification for `round` signed by the given test `authorities`. The forged header conflicts
+/// with the canonical relay chain, so the relayer should report it as misbehaviour.
+pub fn forge_grandpa_header<H>(
+	client_state: &GrandpaClientState<H>,
+	round: u64,
+	authorities: &[Keyring],
+) -> GrandpaHeader {
 	let set_id = client_state.current_set_id;
 
 	// construct an extrinsic proof with the mandatory timestamp extrinsic
@@ -126,27 +199,8 @@ where
 	let precommit = Precommit { target_hash: header_hash, target_number: header.number };
 	let message = finality_grandpa::Message::Precommit(precommit.clone());
 
-	let (update_client_msg, _, _) = chain_b
-		.query_latest_ibc_events(finality_event, chain_a)
-		.await
-		.expect("no event");
-	let mut msg =
-		MsgUpdateAnyClient::<LocalClientTypes>::decode(&mut update_client_msg.value.as_slice())
-			.unwrap();
-	let round = match &mut msg.client_message {
-		AnyClientMessage::Grandpa(ClientMessage::Header(header)) => {
-			let justification = GrandpaJustification::<RelayChainHeader>::decode(
-				&mut &*header.finality_proof.justification,
-			)
-			.unwrap();
-
-			justification.round
-		},
-		_ => panic!("unexpected client message"),
-	};
-
 	// sign pre-commits by the authorities to vote for the highest block in the chain
-	let precommits = relaychain_authorities
+	let precommits = authorities
 		.iter()
 		.map(|id| {
 			let key = id.pair();
@@ -190,32 +244,5 @@ where
 		);
 	}
 
-	let grandpa_header = GrandpaHeader { finality_proof, parachain_headers };
-	let client_message = AnyClientMessage::Grandpa(ClientMessage::Header(grandpa_header));
-
-	let msg =
-		MsgUpdateAnyClient::<LocalClientTypes>::new(msg.client_id, client_message, msg.signer);
-
-	let client_a_clone = chain_a.clone();
-	let misbehavour_event_handle = tokio::task::spawn(async move {
-		let mut events = client_a_clone.ibc_events().await;
-		while let Some(event) = events.next().await {
-			match event {
-				IbcEvent::ClientMisbehaviour { .. } => return,
-				_ => (),
-			}
-		}
-	});
-
-	chain_a
-		.submit(vec![Any { value: msg.encode_vec(), type_url: msg.type_url() }])
-		.await
-		.expect("failed to submit message");
-
-	timeout(Duration::from_secs(5 * 60), misbehavour_event_handle)
-		.await
-		.expect("timeout")
-		.expect("failed to receive misbehaviour event");
-
-	handle.abort()
+	GrandpaHeader { finality_proof, parachain_headers }
 }
@@ -14,20 +14,20 @@
 
 use crate::{assert_timeout_packet, timeout_future, StreamExt};
 use futures::future;
-use hyperspace_core::send_packet_relay::set_relay_status;
-use hyperspace_primitives::{
-	utils::{create_channel, create_connection},
-	TestProvider,
+use hyperspace_core::{
+	bootstrap::{reconcile_channels, BootstrapConfig, ChannelConfig},
+	send_packet_relay::set_relay_status,
 };
+use hyperspace_primitives::{time::secs_to_nanos, TestProvider};
 use ibc::{
 	core::{
-		ics04_channel::channel::{ChannelEnd, Order, State},
+		ics04_channel::channel::{ChannelEnd, State},
 		ics24_host::identifier::{ChannelId, ConnectionId, PortId},
 	},
 	events::IbcEvent,
 };
 use pallet_ibc::Timeout;
-use std::{str::FromStr, time::Duration};
+use std::time::Duration;
 use tokio::task::JoinHandle;
 
 /// This will set up a connection and an ordered channel in-between the two chains with the provided
@@ -55,77 +55,19 @@ where
 			.await
 			.unwrap()
 	});
-	// check if an open ping channel exists
-	let (latest_height, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
-	let connections = chain_a
-		.query_connection_using_client(
-			latest_height.revision_height as u32,
-			chain_b.client_id().to_string(),
-		)
-		.await
-		.unwrap();
-
-	for connection in connections {
-		let connection_id = ConnectionId::from_str(&connection.id).unwrap();
-		let connection_end = chain_a
-			.query_connection_end(latest_height, connection_id.clone())
-			.await
-			.unwrap()
-			.connection
-			.unwrap();
-
-		let delay_period = Duration::from_nanos(connection_end.delay_period);
-
-		dbg!(&connection_delay);
-		dbg!(&delay_period);
-
-		if delay_period != connection_delay {
-			continue
-		}
-
-		let channels = chain_a
-			.query_connection_channels(latest_height, &connection_id)
-			.await
-			.unwrap()
-			.channels;
-
-		for channel in channels {
-			let channel_id = ChannelId::from_str(&channel.channel_id).unwrap();
-			let channel_end = chain_a
-				.query_channel_end(latest_height, channel_id, port_id.clone())
-				.await
-				.unwrap()
-				.channel
-				.unwrap();
-			let channel_end = ChannelEnd::try_from(channel_end).unwrap();
-
-			if channel_end.state == State::Open && channel.port_id == port_id.to_string() {
-				return (
-					handle,
-					channel_id,
-					channel_end.counterparty().channel_id.unwrap().clone(),
-					channel_end.connection_hops[0].clone(),
-				)
-			}
-		}
-	}
-
-	let (connection_id, ..) = create_connection(chain_a, chain_b, connection_delay).await.unwrap();
-
-	log::info!(target: "hyperspace", "============ Connection handshake completed: ConnectionId({connection_id}) ============");
-	log::info!(target: "hyperspace", "=========================== Starting channel handshake ===========================");
-
-	let (channel_id_a, channel_id_b) =
-		create_channel(chain_a, chain_b, connection_id.clone(), port_id, version, Order::Ordered)
-			.await
-			.unwrap();
-	// channel handshake completed
-	log::info!(target: "hyperspace", "============ Channel handshake completed: ChannelId({channel_id_a}) ============");
+	let bootstrap = BootstrapConfig {
+		connection_delay_secs: connection_delay.as_secs(),
+		channels: vec![ChannelConfig { port_id, version, order: "ordered".to_string() }],
+	};
+	let ((connection_id, _), channels) =
+		reconcile_channels(chain_a, chain_b, None, &bootstrap).await.unwrap();
+	let (channel_id_a, channel_id_b) = channels[0];
+	log::info!(target: "hyperspace", "============ Using channel {channel_id_a} over connection {connection_id} ============");
 
 	(handle, channel_id_a, channel_id_b, connection_id)
 }
 
-/// Send a ordered packets and assert acknowledgement
+/// Send ordered packets and assert they are received in order and acknowledged
 async fn send_ordered_packet_and_assert_acknowledgement<A, B>(
 	chain_a: &A,
 	chain_b: &B,
@@ -138,32 +80,52 @@ async fn send_ordered_packet_and_assert_acknowledgement<A, B>(
 	B::FinalityEvent: Send + Sync,
 	B::Error: From<A::Error>,
 {
-	chain_a
-		.send_ordered_packet(
-			channel_id,
-			Timeout::Offset { height: Some(100), timestamp: Some(60 * 60) },
-		)
-		.await
-		.unwrap();
-
-	chain_a
-		.send_ordered_packet(
-			channel_id,
-			Timeout::Offset { height: Some(100), timestamp: Some(60 * 60) },
-		)
+	const PACKETS: usize = 3;
+	// subscribe before sending so no events are missed
+	let received = chain_b
+		.ibc_events()
 		.await
-		.unwrap();
-
-	let future = chain_b
+		.filter_map(|ev| {
+			future::ready(match ev {
+				IbcEvent::ReceivePacket(ev) => Some(u64::from(ev.packet.sequence)),
+				_ => None,
+			})
+		})
+		.take(PACKETS)
+		.collect::<Vec<_>>();
+	let acknowledged = chain_a
 		.ibc_events()
 		.await
-		.skip_while(|ev| future::ready(!matches!(ev, IbcEvent::AcknowledgePacket(_))))
-		.take(2)
+		.filter(|ev| future::ready(matches!(ev, IbcEvent::AcknowledgePacket(_))))
+		.take(PACKETS)
 		.collect::<Vec<_>>();
+
+	// submit all packets before the relayer gets a chance to deliver any of them
+	for _ in 0..PACKETS {
+		chain_a
+			.send_ordered_packet(
+				channel_id,
+				Timeout::Offset { height: Some(100), timestamp: Some(60 * 60) },
+			)
+			.await
+			.unwrap();
+	}
+
+	let sequences = timeout_future(
+		received,
+		20 * 60,
+		format!("Didn't see Receive packets on {}", chain_b.name()),
+	)
+	.await;
+	assert!(
+		sequences.windows(2).all(|seqs| seqs[1] == seqs[0] + 1),
+		"Ordered packets were received out of order: {sequences:?}"
+	);
+
 	timeout_future(
-		future,
+		acknowledged,
 		20 * 60,
-		format!("Didn't see Acknowledgement packet on {}", chain_b.name()),
+		format!("Didn't see Acknowledgement packets on {}", chain_a.name()),
 	)
 	.await;
 }
@@ -173,6 +135,7 @@ async fn send_ordered_packet_and_assert_timeout<A, B>(
 	chain_a: &A,
 	chain_b: &B,
 	channel_id: ChannelId,
+	port_id: PortId,
 ) where
 	A: TestProvider,
 	A::FinalityEvent: Send + Sync,
@@ -192,7 +155,7 @@ async fn send_ordered_packet_and_assert_timeout<A, B>(
 		)
 		.await
 		.unwrap();
-	let timeout_timestamp = Duration::from_secs(timestamp).as_nanos() as u64;
+	let timeout_timestamp = secs_to_nanos(timestamp).expect("Timeout offset fits in nanoseconds");
 
 	// Wait timeout timestamp to elapse, then
 	let future = chain_b
@@ -221,9 +184,20 @@ async fn send_ordered_packet_and_assert_timeout<A, B>(
 
 	assert_timeout_packet(chain_a).await;
 	log::info!(target: "hyperspace", "🚀🚀 Timeout packet successfully processed for ordered channel");
+
+	// a timeout on an ordered channel closes it
+	let (latest_height, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
+	let channel_end = chain_a
+		.query_channel_end(latest_height, channel_id, port_id)
+		.await
+		.unwrap()
+		.channel
+		.unwrap();
+	let channel_end = ChannelEnd::try_from(channel_end).unwrap();
+	assert_eq!(channel_end.state, State::Closed, "Ordered channel wasn't closed on timeout");
 }
 
-///
+/// Relays packets over an ordered channel and asserts they're delivered in order
 pub async fn ibc_messaging_ordered_packet_with_connection_delay<A, B>(
 	chain_a: &mut A,
 	chain_b: &mut B,
@@ -260,7 +234,7 @@ pub async fn ibc_messaging_ordered_packet_with_connection_delay<A, B>(
 	handle.abort()
 }
 
-///
+/// Times out a packet on an ordered channel and asserts the channel gets closed
 pub async fn ibc_messaging_ordered_packet_timeout<A, B>(
 	chain_a: &mut A,
 	chain_b: &mut B,
@@ -285,7 +259,7 @@ pub async fn ibc_messaging_ordered_packet_timeout<A, B>(
 	// Set channel whitelist and restart relayer loop
 	handle.abort();
 	chain_a.set_channel_whitelist(vec![(channel_id, port_id.clone())]);
-	chain_b.set_channel_whitelist(vec![(channel_b, port_id)]);
+	chain_b.set_channel_whitelist(vec![(channel_b, port_id.clone())]);
 	let client_a_clone = chain_a.clone();
 	let client_b_clone = chain_b.clone();
 	let handle = tokio::task::spawn(async move {
@@ -293,6 +267,6 @@ pub async fn ibc_messaging_ordered_packet_timeout<A, B>(
 			.await
 			.unwrap()
 	});
-	send_ordered_packet_and_assert_timeout(chain_a, chain_b, channel_id).await;
+	send_ordered_packet_and_assert_timeout(chain_a, chain_b, channel_id, port_id).await;
 	handle.abort()
 }
@@ -0,0 +1,149 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{setup_connection_and_channel, utils::parse_amount, StreamExt};
+use futures::{future, Stream};
+use hyperspace_primitives::{utils::timeout_future, TestProvider};
+use ibc::{
+	applications::transfer::{msgs::transfer::MsgTransfer, Amount, PrefixedCoin},
+	core::ics24_host::identifier::{ChannelId, PortId},
+	events::IbcEvent,
+};
+use std::{str::FromStr, time::Duration};
+use tokio::time::Instant;
+
+/// Submits `count` ICS-20 transfers of a small amount from chain_a to chain_b over `channel_id`.
+async fn send_transfers<A, B>(chain_a: &A, chain_b: &B, channel_id: ChannelId, count: usize)
+where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+{
+	let balance = chain_a
+		.query_ibc_balance()
+		.await
+		.expect("Can't query ibc balance")
+		.pop()
+		.expect("No Ibc balances");
+	// spend at most half of the balance across all transfers
+	let amount = parse_amount(balance.amount.to_string()) / (count as u128 * 2);
+	assert!(amount > 0, "Balance on {} is too low for {count} transfers", chain_a.name());
+
+	for _ in 0..count {
+		let (mut timeout_height, timestamp) = chain_b
+			.latest_height_and_timestamp()
+			.await
+			.expect("Couldn't fetch latest_height_and_timestamp");
+		timeout_height.revision_height += 1000;
+		let timeout_timestamp =
+			(timestamp + Duration::from_secs(60 * 60 * 2)).expect("Overflow evaluating timeout");
+
+		let msg = MsgTransfer {
+			source_port: PortId::transfer(),
+			source_channel: channel_id,
+			token: PrefixedCoin {
+				denom: balance.denom.clone(),
+				amount: Amount::from_str(&amount.to_string()).expect("Infallible"),
+			},
+			sender: chain_a.account_id(),
+			receiver: chain_b.account_id(),
+			timeout_height,
+			timeout_timestamp,
+		};
+		chain_a.send_transfer(msg).await.expect("Failed to send transfer: ");
+	}
+}
+
+/// Resolves once `count` packets sent over `channel_id` have been acknowledged in the `events`
+/// of `chain`. The events should be subscribed to before the packets are sent.
+async fn wait_for_acknowledgements(
+	events: impl Stream<Item = IbcEvent>,
+	chain: &str,
+	channel_id: ChannelId,
+	count: usize,
+) {
+	let acknowledged = events
+		.filter(|ev| {
+			future::ready(matches!(
+				ev,
+				IbcEvent::AcknowledgePacket(ack) if ack.packet.source_channel == channel_id
+			))
+		})
+		.take(count)
+		.collect::<Vec<_>>()
+		.await;
+	assert_eq!(acknowledged.len(), count, "Event stream for {chain} ended early");
+}
+
+/// Fires `packets_per_direction` ICS-20 transfers from both chains at the same time and checks
+/// that all of them are acknowledged within `time_budget`. Both chains need an ibc balance, so
+/// this should run after the unordered channel tests.
+pub async fn ibc_messaging_packet_storm<A, B>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	packets_per_direction: usize,
+	time_budget: Duration,
+) where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	let (handle, channel_a, channel_b, _connection_id) =
+		setup_connection_and_channel(chain_a, chain_b, Duration::from_secs(0)).await;
+	handle.abort();
+	// Set channel whitelist and restart relayer loop
+	chain_a.set_channel_whitelist(vec![(channel_a, PortId::transfer())]);
+	chain_b.set_channel_whitelist(vec![(channel_b, PortId::transfer())]);
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None)
+			.await
+			.unwrap()
+	});
+
+	// subscribed before any transfer is sent, so no acknowledgement is missed
+	let (events_a, events_b) = (chain_a.ibc_events().await, chain_b.ibc_events().await);
+	let start = Instant::now();
+	let storm = async {
+		futures::join!(
+			wait_for_acknowledgements(events_a, chain_a.name(), channel_a, packets_per_direction),
+			wait_for_acknowledgements(events_b, chain_b.name(), channel_b, packets_per_direction),
+			send_transfers(&*chain_a, &*chain_b, channel_a, packets_per_direction),
+			send_transfers(&*chain_b, &*chain_a, channel_b, packets_per_direction),
+		)
+	};
+	timeout_future(
+		storm,
+		time_budget.as_secs(),
+		format!(
+			"Not all of {} packets per direction were acknowledged between {} and {}",
+			packets_per_direction,
+			chain_a.name(),
+			chain_b.name()
+		),
+	)
+	.await;
+	log::info!(
+		target: "hyperspace",
+		"Relayed {} packets in {:?}",
+		packets_per_direction * 2,
+		start.elapsed()
+	);
+	handle.abort()
+}
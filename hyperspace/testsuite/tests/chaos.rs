@@ -0,0 +1,95 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hyperspace_testsuite::chaos::{ChaosConfig, ChaosProxy};
+use std::time::Duration;
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{TcpListener, TcpStream},
+	time::{timeout, Instant},
+};
+
+/// Starts a server echoing back everything it receives, returning its address.
+async fn echo_server() -> String {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap().to_string();
+	tokio::spawn(async move {
+		while let Ok((mut stream, _)) = listener.accept().await {
+			tokio::spawn(async move {
+				let (mut read, mut write) = stream.split();
+				let _ = tokio::io::copy(&mut read, &mut write).await;
+			});
+		}
+	});
+	addr
+}
+
+async fn connect(proxy: &ChaosProxy) -> TcpStream {
+	TcpStream::connect(proxy.url().trim_start_matches("ws://")).await.unwrap()
+}
+
+/// Sends `ping` and returns what was echoed back, or `None` if the connection was closed.
+async fn echo(stream: &mut TcpStream) -> Option<Vec<u8>> {
+	stream.write_all(b"ping").await.ok()?;
+	let mut buf = [0u8; 4];
+	match timeout(Duration::from_secs(5), stream.read_exact(&mut buf)).await {
+		Ok(Ok(_)) => Some(buf.to_vec()),
+		_ => None,
+	}
+}
+
+#[tokio::test]
+async fn latency_is_added_in_both_directions() {
+	let config = ChaosConfig { latency: Duration::from_millis(100), ..Default::default() };
+	let proxy = ChaosProxy::spawn(&echo_server().await, config).await.unwrap();
+	let mut stream = connect(&proxy).await;
+
+	let start = Instant::now();
+	assert_eq!(echo(&mut stream).await, Some(b"ping".to_vec()));
+	assert!(start.elapsed() >= Duration::from_millis(200));
+	assert_eq!(proxy.accepted_connections(), 1);
+}
+
+#[tokio::test]
+async fn every_nth_connection_is_refused() {
+	let config = ChaosConfig { reject_every: Some(2), ..Default::default() };
+	let proxy = ChaosProxy::spawn(&echo_server().await, config).await.unwrap();
+
+	let mut first = connect(&proxy).await;
+	assert_eq!(echo(&mut first).await, Some(b"ping".to_vec()));
+	let mut second = connect(&proxy).await;
+	assert_eq!(echo(&mut second).await, None);
+	let mut third = connect(&proxy).await;
+	assert_eq!(echo(&mut third).await, Some(b"ping".to_vec()));
+
+	assert_eq!(proxy.accepted_connections(), 2);
+	assert_eq!(proxy.refused_connections(), 1);
+	assert_eq!(proxy.dropped_connections(), 0);
+}
+
+#[tokio::test]
+async fn connections_are_closed_after_their_lifetime() {
+	let config = ChaosConfig {
+		max_connection_lifetime: Some(Duration::from_millis(200)),
+		..Default::default()
+	};
+	let proxy = ChaosProxy::spawn(&echo_server().await, config).await.unwrap();
+	let mut stream = connect(&proxy).await;
+	assert_eq!(echo(&mut stream).await, Some(b"ping".to_vec()));
+
+	tokio::time::sleep(Duration::from_millis(400)).await;
+	assert_eq!(echo(&mut stream).await, None);
+	assert_eq!(proxy.dropped_connections(), 1);
+	assert_eq!(proxy.refused_connections(), 0);
+}
@@ -21,14 +21,24 @@ use hyperspace_parachain::{
 };
 use hyperspace_primitives::{utils::create_clients, IbcProvider};
 use hyperspace_testsuite::{
+	chain_upgrade::ibc_messaging_across_chain_upgrade,
+	chaos::{ChaosConfig, ChaosProxy},
+	client_refresh::ibc_client_refresh_without_traffic,
 	ibc_channel_close, ibc_messaging_packet_height_timeout_with_connection_delay,
 	ibc_messaging_packet_timeout_on_channel_close,
 	ibc_messaging_packet_timestamp_timeout_with_connection_delay,
-	ibc_messaging_with_connection_delay, misbehaviour::ibc_messaging_submit_misbehaviour,
+	ibc_messaging_with_connection_delay,
+	misbehaviour::ibc_messaging_submit_misbehaviour,
+	ordered_channels::{
+		ibc_messaging_ordered_packet_timeout, ibc_messaging_ordered_packet_with_connection_delay,
+	},
+	packet_storm::ibc_messaging_packet_storm,
 };
+use ibc::core::ics24_host::identifier::PortId;
 use sp_runtime::generic::Era;
+use std::{str::FromStr, time::Duration};
 use subxt::{
-	tx::{PolkadotExtrinsicParams, PolkadotExtrinsicParamsBuilder},
+	tx::{PlainTip, PolkadotExtrinsicParams, PolkadotExtrinsicParamsBuilder},
 	Error, OnlineClient,
 };
 
@@ -68,9 +78,11 @@ impl config::Config for DefaultConfig {
 	type AssetId = u128;
 	async fn custom_extrinsic_params(
 		client: &OnlineClient<Self>,
+		tip: u128,
 	) -> Result<CustomExtrinsicParams<Self>, Error> {
-		let params =
-			PolkadotExtrinsicParamsBuilder::new().era(Era::Immortal, client.genesis_hash());
+		let params = PolkadotExtrinsicParamsBuilder::new()
+			.tip(PlainTip::new(tip))
+			.era(Era::Immortal, client.genesis_hash());
 		Ok(params.into())
 	}
 }
@@ -88,9 +100,10 @@ impl subxt::Config for DefaultConfig {
 	type ExtrinsicParams = PolkadotExtrinsicParams<Self>;
 }
 
-async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<DefaultConfig>) {
+async fn setup_clients(
+	args: Args,
+) -> (ParachainClient<DefaultConfig>, ParachainClient<DefaultConfig>) {
 	log::info!(target: "hyperspace", "=========================== Starting Test ===========================");
-	let args = Args::default();
 
 	// Create client configurations
 	let config_a = ParachainClientConfig {
@@ -99,14 +112,32 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 		parachain_rpc_url: args.chain_a,
 		relay_chain_rpc_url: args.relay_chain.clone(),
 		client_id: None,
+		connection_clients: vec![],
 		beefy_activation_block: None,
 		connection_id: None,
 		commitment_prefix: args.connection_prefix_b.as_bytes().to_vec().into(),
 		ss58_version: 49,
 		channel_whitelist: vec![],
+		packet_filter: Default::default(),
+		relay_modes: vec![],
 		finality_protocol: FinalityProtocol::Grandpa,
-		private_key: "//Alice".to_string(),
+		private_key: Some("//Alice".to_string()),
 		key_type: "sr25519".to_string(),
+		expected_block_time_ms: None,
+		finality_batch_window_ms: None,
+		verify_proofs: None,
+		tip: 0,
+		max_fee: None,
+		min_balance: None,
+		faucet_key: None,
+		faucet_top_up: None,
+		asset_denoms: vec![],
+		connection_loss_alert_secs: None,
+		alerts: Default::default(),
+		dry_run: false,
+		heartbeat_interval_secs: None,
+		verification_relay_chain_rpc_url: None,
+		full_scan_interval_blocks: None,
 	};
 	let config_b = ParachainClientConfig {
 		name: format!("9188"),
@@ -114,14 +145,32 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 		parachain_rpc_url: args.chain_b,
 		relay_chain_rpc_url: args.relay_chain,
 		client_id: None,
+		connection_clients: vec![],
 		beefy_activation_block: None,
 		connection_id: None,
 		commitment_prefix: args.connection_prefix_b.as_bytes().to_vec().into(),
-		private_key: "//Alice".to_string(),
+		private_key: Some("//Alice".to_string()),
 		ss58_version: 49,
 		channel_whitelist: vec![],
+		packet_filter: Default::default(),
+		relay_modes: vec![],
 		finality_protocol: FinalityProtocol::Grandpa,
 		key_type: "sr25519".to_string(),
+		expected_block_time_ms: None,
+		finality_batch_window_ms: None,
+		verify_proofs: None,
+		tip: 0,
+		max_fee: None,
+		min_balance: None,
+		faucet_key: None,
+		faucet_top_up: None,
+		asset_denoms: vec![],
+		connection_loss_alert_secs: None,
+		alerts: Default::default(),
+		dry_run: false,
+		heartbeat_interval_secs: None,
+		verification_relay_chain_rpc_url: None,
+		full_scan_interval_blocks: None,
 	};
 
 	let mut chain_a = ParachainClient::<DefaultConfig>::new(config_a).await.unwrap();
@@ -147,8 +196,8 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 	let clients_on_b = chain_b.query_clients().await.unwrap();
 
 	if !clients_on_a.is_empty() && !clients_on_b.is_empty() {
-		chain_a.set_client_id(clients_on_b[0].clone());
-		chain_b.set_client_id(clients_on_b[0].clone());
+		chain_a.set_client_id(None, clients_on_b[0].clone());
+		chain_b.set_client_id(None, clients_on_b[0].clone());
 		return (chain_a, chain_b)
 	}
 
@@ -160,20 +209,36 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 	res_2.unwrap();
 
 	let (client_a, client_b) = create_clients(&chain_a, &chain_b).await.unwrap();
-	chain_a.set_client_id(client_a);
-	chain_b.set_client_id(client_b);
+	chain_a.set_client_id(None, client_a);
+	chain_b.set_client_id(None, client_b);
 	(chain_a, chain_b)
 }
 
+/// Re-applies the current runtime of the parachain and waits until the relay chain has enacted
+/// the code upgrade.
+async fn upgrade_parachain(chain: ParachainClient<DefaultConfig>) {
+	chain.schedule_runtime_upgrade().await.unwrap();
+	while chain.pending_code_upgrade().await.unwrap().is_none() {
+		tokio::time::sleep(Duration::from_secs(6)).await;
+	}
+	log::info!(target: "hyperspace", "Code upgrade scheduled for {}", chain.name);
+	while chain.pending_code_upgrade().await.unwrap().is_some() {
+		tokio::time::sleep(Duration::from_secs(6)).await;
+	}
+}
+
 #[tokio::test]
 async fn parachain_to_parachain_ibc_messaging_full_integration_test() {
 	logging::setup_logging();
-	let (mut chain_a, mut chain_b) = setup_clients().await;
+	let (mut chain_a, mut chain_b) = setup_clients(Args::default()).await;
 	// Run tests sequentially
 
 	// no timeouts + connection delay
 	ibc_messaging_with_connection_delay(&mut chain_a, &mut chain_b).await;
 
+	// throughput
+	ibc_messaging_packet_storm(&mut chain_a, &mut chain_b, 200, Duration::from_secs(30 * 60)).await;
+
 	// timeouts + connection delay
 	ibc_messaging_packet_height_timeout_with_connection_delay(&mut chain_a, &mut chain_b).await;
 	ibc_messaging_packet_timestamp_timeout_with_connection_delay(&mut chain_a, &mut chain_b).await;
@@ -182,6 +247,51 @@ async fn parachain_to_parachain_ibc_messaging_full_integration_test() {
 	ibc_messaging_packet_timeout_on_channel_close(&mut chain_a, &mut chain_b).await;
 	ibc_channel_close(&mut chain_a, &mut chain_b).await;
 
+	// ordered channels
+	let ping_port = PortId::from_str(pallet_ibc_ping::PORT_ID).unwrap();
+	ibc_messaging_ordered_packet_with_connection_delay(
+		&mut chain_a,
+		&mut chain_b,
+		ping_port.clone(),
+		pallet_ibc_ping::VERSION.to_string(),
+	)
+	.await;
+	ibc_messaging_ordered_packet_timeout(
+		&mut chain_a,
+		&mut chain_b,
+		ping_port,
+		pallet_ibc_ping::VERSION.to_string(),
+	)
+	.await;
+
+	// runtime upgrade
+	let upgraded = chain_a.clone();
+	ibc_messaging_across_chain_upgrade(&mut chain_a, &mut chain_b, upgrade_parachain(upgraded))
+		.await;
+
+	// client refresh
+	ibc_client_refresh_without_traffic(&mut chain_a, &mut chain_b).await;
+
+	// relaying over a degraded network
+	let args = Args::default();
+	let chaos = ChaosConfig { latency: Duration::from_millis(300), ..Default::default() };
+	let proxy_a = ChaosProxy::spawn(&args.chain_a, chaos.clone()).await.unwrap();
+	let proxy_b = ChaosProxy::spawn(&args.chain_b, chaos.clone()).await.unwrap();
+	let proxy_relay = ChaosProxy::spawn(&args.relay_chain, chaos).await.unwrap();
+	let (mut chaos_chain_a, mut chaos_chain_b) = setup_clients(Args {
+		chain_a: proxy_a.url(),
+		chain_b: proxy_b.url(),
+		relay_chain: proxy_relay.url(),
+		..args
+	})
+	.await;
+	ibc_messaging_with_connection_delay(&mut chaos_chain_a, &mut chaos_chain_b).await;
+	// the relayer went through the proxies, which only added latency
+	for proxy in [&proxy_a, &proxy_b, &proxy_relay] {
+		assert!(proxy.accepted_connections() > 0);
+		assert_eq!(proxy.refused_connections() + proxy.dropped_connections(), 0);
+	}
+
 	// misbehaviour
 	ibc_messaging_submit_misbehaviour(&mut chain_a, &mut chain_b).await;
 }
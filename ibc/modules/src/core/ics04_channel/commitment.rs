@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::prelude::*;
+use crate::{prelude::*, timestamp::Timestamp, Height};
 
 use serde_derive::{Deserialize, Serialize};
 
@@ -32,6 +32,23 @@ impl From<Vec<u8>> for PacketCommitment {
 	}
 }
 
+/// Computes the commitment stored for a sent packet, using `hash` as the Sha2_256 hashing function
+pub fn compute_packet_commitment(
+	packet_data: Vec<u8>,
+	timeout_height: Height,
+	timeout_timestamp: Timestamp,
+	hash: impl Fn(Vec<u8>) -> Vec<u8>,
+) -> PacketCommitment {
+	let mut input = timeout_timestamp.nanoseconds().to_be_bytes().to_vec();
+	let revision_number = timeout_height.revision_number.to_be_bytes();
+	input.append(&mut revision_number.to_vec());
+	let revision_height = timeout_height.revision_height.to_be_bytes();
+	input.append(&mut revision_height.to_vec());
+	let data = hash(packet_data);
+	input.append(&mut data.to_vec());
+	hash(input).into()
+}
+
 /// Acknowledgement commitment to be stored
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct AcknowledgementCommitment(Vec<u8>);
@@ -21,7 +21,7 @@ use crate::{
 	core::{
 		ics04_channel::{
 			channel::ChannelEnd,
-			commitment::{AcknowledgementCommitment, PacketCommitment},
+			commitment::{compute_packet_commitment, AcknowledgementCommitment, PacketCommitment},
 			error::Error,
 			handler::{recv_packet::RecvPacketResult, ChannelIdState, ChannelResult},
 			msgs::acknowledgement::Acknowledgement,
@@ -76,14 +76,9 @@ pub trait ChannelReader {
 		timeout_height: Height,
 		timeout_timestamp: Timestamp,
 	) -> PacketCommitment {
-		let mut input = timeout_timestamp.nanoseconds().to_be_bytes().to_vec();
-		let revision_number = timeout_height.revision_number.to_be_bytes();
-		input.append(&mut revision_number.to_vec());
-		let revision_height = timeout_height.revision_height.to_be_bytes();
-		input.append(&mut revision_height.to_vec());
-		let data = self.hash(packet_data);
-		input.append(&mut data.to_vec());
-		self.hash(input).into()
+		compute_packet_commitment(packet_data, timeout_height, timeout_timestamp, |value| {
+			self.hash(value)
+		})
 	}
 
 	fn ack_commitment(&self, ack: Acknowledgement) -> AcknowledgementCommitment {
@@ -891,6 +891,14 @@ impl_runtime_apis! {
 			Ibc::consensus_state(client_id, revision_number, revision_height, latest_cs).ok()
 		}
 
+		fn upgraded_client_state() -> Option<Vec<u8>> {
+			Ibc::upgraded_client_state()
+		}
+
+		fn upgraded_consensus_state() -> Option<Vec<u8>> {
+			Ibc::upgraded_consensus_state()
+		}
+
 		fn clients() -> Option<Vec<(Vec<u8>, Vec<u8>)>> {
 			Some(Ibc::clients())
 		}
@@ -935,6 +943,10 @@ impl_runtime_apis! {
 			Ibc::packet_acknowledgements(channel_id, port_id).ok()
 		}
 
+		fn undelivered_sequences(channel_id: Vec<u8>, port_id: Vec<u8>) -> Option<ibc_primitives::QueryUndeliveredSequencesResponse> {
+			Ibc::undelivered_sequences(channel_id, port_id).ok()
+		}
+
 		fn unreceived_packets(channel_id: Vec<u8>, port_id: Vec<u8>, seqs: Vec<u64>) -> Option<Vec<u64>> {
 			Ibc::unreceived_packets(channel_id, port_id, seqs).ok()
 		}